@@ -1,9 +1,32 @@
+use crate::products::low_stock_data::LowStockItem;
 use anyhow::Result;
 use lettre::message::header::ContentType;
 use lettre::transport::smtp::authentication::Credentials;
 use lettre::{Message, SmtpTransport, Transport};
 use log::*;
 
+/// Address the portal is reachable at, for links in emails
+fn public_base_url() -> String {
+    if cfg!(debug_assertions) {
+        let port = std::env::var("APP_PORT")
+            .map(|port_str| port_str.parse::<u16>().unwrap_or(1423))
+            .unwrap_or_else(|_| 1423);
+        format!("http://127.0.0.1:{}", port)
+    } else {
+        std::env::var("PUBLIC_BASE_URL")
+            .unwrap_or_else(|_| "https://store-orders.mardens.com".to_string())
+    }
+}
+
+/// Escapes user-entered text for inclusion in an HTML email body
+pub(crate) fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
 pub struct EmailService {
     transport: SmtpTransport,
     from_email: String,
@@ -50,15 +73,7 @@ impl EmailService {
             to_email, is_new_user
         );
 
-        let base_url = if cfg!(debug_assertions) {
-            let port = std::env::var("APP_PORT")
-                .map(|port_str| port_str.parse::<u16>().unwrap_or(1423))
-                .unwrap_or_else(|_| 1423);
-            format!("http://127.0.0.1:{}", port)
-        } else {
-            std::env::var("PUBLIC_BASE_URL")
-                .unwrap_or_else(|_| "https://store-orders.mardens.com".to_string())
-        };
+        let base_url = public_base_url();
         debug!("Using base URL: {}", base_url);
         let reset_url = format!("{}/reset-password?token={}", base_url, reset_token);
 
@@ -373,4 +388,283 @@ impl EmailService {
             }
         }
     }
+
+    /// Tells the user who placed an order that it was rejected during approval
+    pub async fn send_order_rejected_email(
+        &self,
+        to_email: &str,
+        order_number: &str,
+        reason: &str,
+    ) -> Result<()> {
+        info!(
+            "Sending order rejection email for {} to: {}",
+            order_number, to_email
+        );
+
+        let orders_url = format!("{}/app/orders", public_base_url());
+        let subject = format!("Mardens Store Portal - Order {} was not approved", order_number);
+        let body = format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="UTF-8">
+    <meta name="viewport" content="width=device-width, initial-scale=1.0">
+    <title>Order Not Approved</title>
+    <style>
+        body {{
+            font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', Roboto, 'Helvetica Neue', Arial, sans-serif;
+            line-height: 1.6;
+            color: #333;
+            max-width: 600px;
+            margin: 0 auto;
+            padding: 20px;
+            background-color: #f5f5f5;
+        }}
+        .email-container {{
+            background-color: #ffffff;
+            border-radius: 8px;
+            padding: 40px;
+            box-shadow: 0 2px 10px rgba(0, 0, 0, 0.1);
+        }}
+        .title {{
+            color: #1f2937;
+            font-size: 24px;
+            font-weight: 600;
+            margin: 0 0 20px 0;
+            text-align: center;
+        }}
+        .reason {{
+            background-color: #fef2f2;
+            border: 1px solid #fca5a5;
+            border-radius: 6px;
+            padding: 12px 16px;
+            margin: 20px 0;
+            font-size: 14px;
+            color: #991b1b;
+            white-space: pre-wrap;
+        }}
+        .cta-button {{
+            display: inline-block;
+            background: #f13848;
+            color: white;
+            text-decoration: none;
+            padding: 16px 32px;
+            border-radius: 8px;
+            font-weight: 600;
+            font-size: 16px;
+            margin: 20px 0;
+        }}
+        .footer {{
+            margin-top: 30px;
+            padding-top: 20px;
+            border-top: 1px solid #e5e7eb;
+            font-size: 14px;
+            color: #6b7280;
+            text-align: center;
+        }}
+    </style>
+</head>
+<body>
+    <div class="email-container">
+        <h1 class="title">Order {order_number} was not approved</h1>
+
+        <p>
+            Your order needed approval because its total is above your store's approval limit. It has been rejected and cancelled, and any reserved stock has been released.
+        </p>
+
+        <div class="reason"><strong>Reason:</strong> {reason}</div>
+
+        <div style="text-align: center;">
+            <a href="{orders_url}" class="cta-button">View Your Orders</a>
+        </div>
+
+        <div class="footer">
+            <p style="font-size: 12px;">
+                This is an automated message from the Mardens Store Portal system.
+            </p>
+        </div>
+    </div>
+</body>
+</html>"#,
+            order_number = escape_html(order_number),
+            reason = escape_html(reason),
+            orders_url = orders_url
+        );
+
+        let email = Message::builder()
+            .from(self.from_email.parse()?)
+            .to(to_email.parse()?)
+            .subject(subject)
+            .header(ContentType::TEXT_HTML)
+            .body(body)?;
+
+        match self.transport.send(&email) {
+            Ok(_) => {
+                info!("Order rejection email sent successfully to: {}", to_email);
+                Ok(())
+            }
+            Err(e) => {
+                error!("Failed to send email to {}: {}", to_email, e);
+                Err(anyhow::anyhow!("Failed to send email: {}", e))
+            }
+        }
+    }
+
+    /// Lists products at or below their reorder point; `order_number` names the order that
+    /// took them there, `None` for the scheduled report
+    pub async fn send_low_stock_email(
+        &self,
+        to_email: &str,
+        items: &[LowStockItem],
+        order_number: Option<&str>,
+    ) -> Result<()> {
+        info!(
+            "Sending low-stock alert for {} product(s) to: {}",
+            items.len(),
+            to_email
+        );
+
+        let products_url = format!("{}/app/admin/product-management", public_base_url());
+        let (subject, intro) = match order_number {
+            Some(order_number) => (
+                format!("Mardens Store Portal - Order {} reached reorder points", order_number),
+                format!(
+                    "Order {} took the products below to or under their reorder point.",
+                    escape_html(order_number)
+                ),
+            ),
+            None => (
+                format!("Mardens Store Portal - {} product(s) low on stock", items.len()),
+                "The products below are at or under their reorder point.".to_string(),
+            ),
+        };
+        let rows: String = items
+            .iter()
+            .map(|item| {
+                format!(
+                    r#"<tr><td>{sku}</td><td>{name}</td><td>{bin}</td><td class="number">{stock}</td><td class="number">{reorder_point}</td><td class="number">{shortfall}</td></tr>"#,
+                    sku = escape_html(&item.sku),
+                    name = escape_html(&item.name),
+                    bin = escape_html(&item.bin_location),
+                    stock = item.stock_quantity,
+                    reorder_point = item.reorder_point,
+                    shortfall = item.shortfall()
+                )
+            })
+            .collect();
+        let body = format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="UTF-8">
+    <meta name="viewport" content="width=device-width, initial-scale=1.0">
+    <title>Low Stock</title>
+    <style>
+        body {{
+            font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', Roboto, 'Helvetica Neue', Arial, sans-serif;
+            line-height: 1.6;
+            color: #333;
+            max-width: 600px;
+            margin: 0 auto;
+            padding: 20px;
+            background-color: #f5f5f5;
+        }}
+        .email-container {{
+            background-color: #ffffff;
+            border-radius: 8px;
+            padding: 40px;
+            box-shadow: 0 2px 10px rgba(0, 0, 0, 0.1);
+        }}
+        .title {{
+            color: #1f2937;
+            font-size: 24px;
+            font-weight: 600;
+            margin: 0 0 20px 0;
+            text-align: center;
+        }}
+        table {{
+            width: 100%;
+            border-collapse: collapse;
+            margin: 20px 0;
+            font-size: 14px;
+        }}
+        th, td {{
+            border-bottom: 1px solid #e5e7eb;
+            padding: 8px;
+            text-align: left;
+        }}
+        th {{
+            background-color: #f9fafb;
+            color: #374151;
+        }}
+        .number {{
+            text-align: right;
+        }}
+        .cta-button {{
+            display: inline-block;
+            background: #f13848;
+            color: white;
+            text-decoration: none;
+            padding: 16px 32px;
+            border-radius: 8px;
+            font-weight: 600;
+            font-size: 16px;
+            margin: 20px 0;
+        }}
+        .footer {{
+            margin-top: 30px;
+            padding-top: 20px;
+            border-top: 1px solid #e5e7eb;
+            font-size: 14px;
+            color: #6b7280;
+            text-align: center;
+        }}
+    </style>
+</head>
+<body>
+    <div class="email-container">
+        <h1 class="title">Low Stock</h1>
+
+        <p>{intro}</p>
+
+        <table>
+            <tr><th>SKU</th><th>Product</th><th>Bin</th><th class="number">In stock</th><th class="number">Reorder point</th><th class="number">Short by</th></tr>
+            {rows}
+        </table>
+
+        <div style="text-align: center;">
+            <a href="{products_url}" class="cta-button">View Products</a>
+        </div>
+
+        <div class="footer">
+            <p style="font-size: 12px;">
+                This is an automated message from the Mardens Store Portal system.
+            </p>
+        </div>
+    </div>
+</body>
+</html>"#,
+            intro = intro,
+            rows = rows,
+            products_url = products_url
+        );
+
+        let email = Message::builder()
+            .from(self.from_email.parse()?)
+            .to(to_email.parse()?)
+            .subject(subject)
+            .header(ContentType::TEXT_HTML)
+            .body(body)?;
+
+        match self.transport.send(&email) {
+            Ok(_) => {
+                info!("Low-stock alert sent successfully to: {}", to_email);
+                Ok(())
+            }
+            Err(e) => {
+                error!("Failed to send email to {}: {}", to_email, e);
+                Err(anyhow::anyhow!("Failed to send email: {}", e))
+            }
+        }
+    }
 }
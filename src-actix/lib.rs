@@ -20,6 +20,22 @@ pub mod stores;
 pub mod upload;
 
 pub static DEBUG: bool = cfg!(debug_assertions);
+
+/// JSON bodies up to `limit` bytes, rejected with a JSON error otherwise. Scopes taking
+/// larger bodies register their own.
+pub(crate) fn json_config(limit: usize) -> web::JsonConfig {
+    web::JsonConfig::default()
+        .limit(limit)
+        .error_handler(|err, _req| {
+            let error = json!({ "error": format!("{}", err) });
+            actix_web::error::InternalError::from_response(
+                err,
+                HttpResponse::BadRequest().json(error),
+            )
+            .into()
+        })
+}
+
 pub async fn run() -> Result<()> {
     pretty_env_logger::env_logger::builder()
         .filter_level(LevelFilter::Debug)
@@ -49,6 +65,10 @@ pub async fn run() -> Result<()> {
 
     pool.close().await;
 
+    // Background tasks open their own connections once the tables exist
+    products::low_stock_db::start_report_task();
+    orders::order_template_db::start_scheduler();
+
     // Start the Vite server in development mode
     if DEBUG {
         ProxyViteOptions::new().disable_logging().build()?;
@@ -71,18 +91,7 @@ pub async fn run() -> Result<()> {
     let server = HttpServer::new(move || {
         App::new()
             .wrap(middleware::Logger::default())
-            .app_data(
-                web::JsonConfig::default()
-                    .limit(4096)
-                    .error_handler(|err, _req| {
-                        let error = json!({ "error": format!("{}", err) });
-                        actix_web::error::InternalError::from_response(
-                            err,
-                            HttpResponse::BadRequest().json(error),
-                        )
-                        .into()
-                    }),
-            )
+            .app_data(json_config(4096))
             .service(
                 web::scope("/api")
                     .app_data(Data::new(connection_data.clone()))
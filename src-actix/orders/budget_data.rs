@@ -0,0 +1,341 @@
+use chrono::{Datelike, Days, Months, NaiveDate};
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use serde_hash::HashIds;
+use sqlx::mysql::MySqlRow;
+use sqlx::{FromRow, Row};
+use std::collections::{HashMap, HashSet};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BudgetPeriod {
+    Monthly,
+    Quarterly,
+}
+
+impl BudgetPeriod {
+    pub fn as_db_str(&self) -> &'static str {
+        match self {
+            BudgetPeriod::Monthly => "MONTHLY",
+            BudgetPeriod::Quarterly => "QUARTERLY",
+        }
+    }
+
+    pub fn from_str_case_insensitive(s: &str) -> Option<Self> {
+        match s.to_ascii_uppercase().as_str() {
+            "MONTHLY" => Some(BudgetPeriod::Monthly),
+            "QUARTERLY" => Some(BudgetPeriod::Quarterly),
+            _ => None,
+        }
+    }
+
+    /// First day of the period containing `date`, and the first day of the next period
+    pub fn bounds(&self, date: NaiveDate) -> (NaiveDate, NaiveDate) {
+        let (first_month, months) = match self {
+            BudgetPeriod::Monthly => (date.month(), 1),
+            BudgetPeriod::Quarterly => ((date.month() - 1) / 3 * 3 + 1, 3),
+        };
+        let start = NaiveDate::from_ymd_opt(date.year(), first_month, 1)
+            .expect("first day of a month is a valid date");
+        let end = start
+            .checked_add_months(Months::new(months))
+            .expect("budget period ends within the supported date range");
+        (start, end)
+    }
+}
+
+/// A store's spending limit per period
+#[derive(HashIds, Debug, Clone)]
+pub struct StoreBudgetRecord {
+    #[hash]
+    pub store_id: u64,
+    pub period: BudgetPeriod,
+    /// Limit on the store's total spend; `None` when only categories are budgeted
+    pub amount: Option<Decimal>,
+    /// Refuse orders that would go over budget instead of only warning
+    pub block_over_budget: bool,
+    #[hash]
+    pub updated_by: Option<u64>,
+    pub updated_at: chrono::NaiveDateTime,
+}
+
+impl<'r> FromRow<'r, MySqlRow> for StoreBudgetRecord {
+    fn from_row(row: &'r MySqlRow) -> Result<Self, sqlx::Error> {
+        let period_raw: String = row.try_get("period")?;
+        let period = BudgetPeriod::from_str_case_insensitive(&period_raw).ok_or_else(|| {
+            sqlx::Error::Protocol(format!("Unexpected budget period value '{}'", period_raw))
+        })?;
+        Ok(Self {
+            store_id: row.try_get("store_id")?,
+            period,
+            amount: row.try_get("amount")?,
+            block_over_budget: row.try_get("block_over_budget")?,
+            updated_by: row.try_get("updated_by")?,
+            updated_at: row.try_get("updated_at")?,
+        })
+    }
+}
+
+/// The part of a store's budget set aside for one category and its subcategories
+#[derive(HashIds, Debug, Clone, FromRow)]
+pub struct CategoryBudgetRecord {
+    #[hash]
+    pub category_id: u64,
+    pub category_name: String,
+    pub amount: Decimal,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StoreBudgetWithCategoriesDto {
+    #[serde(flatten)]
+    pub budget: StoreBudgetRecord,
+    pub categories: Vec<CategoryBudgetRecord>,
+}
+
+/// Category a product's spend counts towards: its own if budgeted, otherwise the nearest
+/// budgeted ancestor. `None` if neither the category nor any parent has a budget.
+pub fn budgeted_category_for(
+    category_id: Option<u64>,
+    parents: &HashMap<u64, Option<u64>>,
+    budgeted: &HashSet<u64>,
+) -> Option<u64> {
+    let mut current = category_id;
+    // Bounded so a parent loop in the data cannot hang the request
+    for _ in 0..=parents.len() {
+        let id = current?;
+        if budgeted.contains(&id) {
+            return Some(id);
+        }
+        current = parents.get(&id).copied().flatten();
+    }
+    None
+}
+
+/// Spend so far against one budget: the whole store (`category_id` is `None`) or one category
+#[derive(Debug, Clone, PartialEq)]
+pub struct BudgetUsage {
+    pub category_id: Option<u64>,
+    pub category_name: Option<String>,
+    pub budget: Decimal,
+    pub spent: Decimal,
+}
+
+impl BudgetUsage {
+    pub fn remaining(&self) -> Decimal {
+        self.budget - self.spent
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct BudgetLineDto {
+    /// `None` for the store-wide budget
+    pub category_id: Option<String>,
+    pub category_name: Option<String>,
+    pub budget: f64,
+    pub spent: f64,
+    pub remaining: f64,
+    pub over_budget: bool,
+}
+
+impl From<&BudgetUsage> for BudgetLineDto {
+    fn from(u: &BudgetUsage) -> Self {
+        Self {
+            category_id: u.category_id.map(serde_hash::hashids::encode_single),
+            category_name: u.category_name.clone(),
+            budget: u.budget.to_f64().unwrap_or(0.0),
+            spent: u.spent.to_f64().unwrap_or(0.0),
+            remaining: u.remaining().to_f64().unwrap_or(0.0),
+            over_budget: u.spent > u.budget,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct BudgetSpendReport {
+    pub store_id: String,
+    pub period: BudgetPeriod,
+    pub period_start: NaiveDate,
+    /// Last day of the period
+    pub period_end: NaiveDate,
+    pub block_over_budget: bool,
+    /// Spend against the store-wide budget, if one is set
+    pub total: Option<BudgetLineDto>,
+    pub categories: Vec<BudgetLineDto>,
+}
+
+/// One budget a new order would draw on
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BudgetCheckLine {
+    pub category_id: Option<String>,
+    pub category_name: Option<String>,
+    pub budget: f64,
+    pub spent: f64,
+    pub order_amount: f64,
+    /// What is left after the order; negative when it goes over
+    pub remaining: f64,
+    pub exceeded: bool,
+}
+
+/// How a new order fits in the store's current budget period
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BudgetCheck {
+    pub period_start: NaiveDate,
+    /// Last day of the period
+    pub period_end: NaiveDate,
+    /// Store-wide remaining amount after the order, if the store has a total budget
+    pub remaining: Option<f64>,
+    pub exceeded: bool,
+    /// Whether an order that exceeds the budget is refused
+    pub blocking: bool,
+    pub lines: Vec<BudgetCheckLine>,
+}
+
+impl BudgetCheck {
+    /// Compares each budget with the part of the order that counts towards it.
+    ///
+    /// `order_amounts` maps a budgeted category (or `None` for the store total) to the order's
+    /// amount for it; budgets the order does not touch are left out. The period is given as
+    /// returned by [`BudgetPeriod::bounds`].
+    pub fn evaluate(
+        usages: &[BudgetUsage],
+        order_amounts: &HashMap<Option<u64>, Decimal>,
+        blocking: bool,
+        (period_start, next_period_start): (NaiveDate, NaiveDate),
+    ) -> Self {
+        let lines: Vec<BudgetCheckLine> = usages
+            .iter()
+            .filter_map(|usage| {
+                let amount = *order_amounts.get(&usage.category_id)?;
+                let remaining = usage.remaining() - amount;
+                Some(BudgetCheckLine {
+                    category_id: usage.category_id.map(serde_hash::hashids::encode_single),
+                    category_name: usage.category_name.clone(),
+                    budget: usage.budget.to_f64().unwrap_or(0.0),
+                    spent: usage.spent.to_f64().unwrap_or(0.0),
+                    order_amount: amount.to_f64().unwrap_or(0.0),
+                    remaining: remaining.to_f64().unwrap_or(0.0),
+                    exceeded: amount > Decimal::ZERO && remaining < Decimal::ZERO,
+                })
+            })
+            .collect();
+        Self {
+            period_start,
+            period_end: next_period_start - Days::new(1),
+            remaining: lines
+                .iter()
+                .find(|l| l.category_id.is_none())
+                .map(|l| l.remaining),
+            exceeded: lines.iter().any(|l| l.exceeded),
+            blocking,
+            lines,
+        }
+    }
+
+    /// The order must be refused
+    pub fn is_blocked(&self) -> bool {
+        self.blocking && self.exceeded
+    }
+}
+
+/// A validated [`SaveStoreBudgetRequest`] with decoded IDs
+#[derive(Debug)]
+pub struct StoreBudgetDraft {
+    pub period: BudgetPeriod,
+    pub amount: Option<Decimal>,
+    pub block_over_budget: bool,
+    pub categories: Vec<(u64, Decimal)>, // (category_id, amount)
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CategoryBudgetRequest {
+    pub category_id: String, // hashed ID
+    pub amount: Decimal,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SaveStoreBudgetRequest {
+    pub store_id: String, // hashed ID
+    pub period: BudgetPeriod,
+    /// Store-wide limit; omit to budget categories only
+    pub amount: Option<Decimal>,
+    #[serde(default)]
+    pub block_over_budget: bool,
+    #[serde(default)]
+    pub categories: Vec<CategoryBudgetRequest>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BudgetQuery {
+    pub store_id: Option<String>, // hashed ID, required for admins
+    /// Any day in the period to report on, defaults to today
+    pub date: Option<NaiveDate>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn periods_cover_the_month_or_quarter() {
+        assert_eq!(
+            BudgetPeriod::Monthly.bounds(date(2025, 12, 31)),
+            (date(2025, 12, 1), date(2026, 1, 1))
+        );
+        assert_eq!(
+            BudgetPeriod::Quarterly.bounds(date(2025, 5, 15)),
+            (date(2025, 4, 1), date(2025, 7, 1))
+        );
+        assert_eq!(
+            BudgetPeriod::Quarterly.bounds(date(2025, 12, 1)),
+            (date(2025, 10, 1), date(2026, 1, 1))
+        );
+    }
+
+    #[test]
+    fn spend_counts_towards_nearest_budgeted_category() {
+        // 1 <- 2 <- 3, and 4 on its own
+        let parents = HashMap::from([(1, None), (2, Some(1)), (3, Some(2)), (4, None)]);
+        let budgeted = HashSet::from([1]);
+        assert_eq!(budgeted_category_for(Some(3), &parents, &budgeted), Some(1));
+        assert_eq!(budgeted_category_for(Some(4), &parents, &budgeted), None);
+        assert_eq!(budgeted_category_for(None, &parents, &budgeted), None);
+
+        let looped = HashMap::from([(5, Some(6)), (6, Some(5))]);
+        assert_eq!(budgeted_category_for(Some(5), &looped, &budgeted), None);
+    }
+
+    #[test]
+    fn only_budgets_the_order_draws_on_can_be_exceeded() {
+        let usages = vec![
+            BudgetUsage {
+                category_id: None,
+                category_name: None,
+                budget: Decimal::from(1000),
+                spent: Decimal::from(900),
+            },
+            BudgetUsage {
+                category_id: Some(7),
+                category_name: Some("Paper".to_string()),
+                budget: Decimal::from(50),
+                spent: Decimal::from(60),
+            },
+        ];
+        let period = BudgetPeriod::Monthly.bounds(date(2025, 6, 10));
+        let order = HashMap::from([(None, Decimal::from(80))]);
+
+        let check = BudgetCheck::evaluate(&usages, &order, true, period);
+        assert_eq!(check.lines.len(), 1);
+        assert_eq!(check.remaining, Some(20.0));
+        assert!(!check.is_blocked());
+
+        let bigger = HashMap::from([(None, Decimal::from(150)), (Some(7), Decimal::from(5))]);
+        let check = BudgetCheck::evaluate(&usages, &bigger, false, period);
+        assert_eq!(check.remaining, Some(-50.0));
+        assert!(check.exceeded && !check.is_blocked());
+    }
+}
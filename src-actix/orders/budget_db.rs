@@ -0,0 +1,298 @@
+use crate::orders::budget_data::{
+    budgeted_category_for, BudgetCheck, BudgetLineDto, BudgetSpendReport, BudgetUsage,
+    CategoryBudgetRecord, StoreBudgetDraft, StoreBudgetRecord,
+};
+use crate::orders::store_order_status::StoreOrderStatus;
+use chrono::{Days, NaiveDate};
+use rust_decimal::Decimal;
+use sqlx::{Executor, MySql, MySqlConnection, MySqlPool, Row, Transaction};
+use std::collections::{HashMap, HashSet};
+
+pub async fn initialize(pool: &MySqlPool) -> anyhow::Result<()> {
+    pool.execute(
+        r#"
+        CREATE TABLE IF NOT EXISTS `store_budgets` (
+            `store_id` BIGINT UNSIGNED NOT NULL,
+            `period` ENUM('MONTHLY','QUARTERLY') NOT NULL,
+            `amount` DECIMAL(12,2),
+            `block_over_budget` BOOLEAN NOT NULL DEFAULT FALSE,
+            `updated_by` BIGINT UNSIGNED,
+            `updated_at` DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+            PRIMARY KEY (`store_id`)
+        )
+        "#,
+    )
+    .await?;
+
+    pool.execute(
+        r#"
+        CREATE TABLE IF NOT EXISTS `store_category_budgets` (
+            `store_id` BIGINT UNSIGNED NOT NULL,
+            `category_id` BIGINT UNSIGNED NOT NULL,
+            `amount` DECIMAL(12,2) NOT NULL,
+            PRIMARY KEY (`store_id`, `category_id`),
+            FOREIGN KEY (`store_id`) REFERENCES `store_budgets`(`store_id`) ON DELETE CASCADE,
+            FOREIGN KEY (`category_id`) REFERENCES `categories`(`id`) ON DELETE CASCADE
+        )
+        "#,
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Parent of every category, to roll subcategory spend up into budgeted categories
+async fn category_parents(
+    conn: &mut MySqlConnection,
+) -> anyhow::Result<HashMap<u64, Option<u64>>> {
+    let rows: Vec<(u64, Option<u64>)> = sqlx::query_as("SELECT `id`, `parent_id` FROM `categories`")
+        .fetch_all(conn)
+        .await?;
+    Ok(rows.into_iter().collect())
+}
+
+impl StoreBudgetRecord {
+    pub async fn get(pool: &MySqlPool, store_id: u64) -> anyhow::Result<Option<Self>> {
+        let budget = sqlx::query_as::<_, Self>("SELECT * FROM `store_budgets` WHERE `store_id` = ?")
+            .bind(store_id)
+            .fetch_optional(pool)
+            .await?;
+        Ok(budget)
+    }
+
+    pub async fn get_categories<'e, E: Executor<'e, Database = MySql>>(
+        executor: E,
+        store_id: u64,
+    ) -> anyhow::Result<Vec<CategoryBudgetRecord>> {
+        let categories = sqlx::query_as::<_, CategoryBudgetRecord>(
+            r#"
+            SELECT cb.category_id, c.name AS category_name, cb.amount
+            FROM `store_category_budgets` cb
+            JOIN `categories` c ON cb.category_id = c.id
+            WHERE cb.store_id = ?
+            ORDER BY c.name ASC
+            "#,
+        )
+        .bind(store_id)
+        .fetch_all(executor)
+        .await?;
+        Ok(categories)
+    }
+
+    /// Creates or replaces a store's budget, including its category split
+    pub async fn save(
+        pool: &MySqlPool,
+        store_id: u64,
+        draft: &StoreBudgetDraft,
+        updated_by: u64,
+    ) -> anyhow::Result<()> {
+        let mut transaction = pool.begin().await?;
+        sqlx::query(
+            r#"
+            INSERT INTO `store_budgets` (`store_id`, `period`, `amount`, `block_over_budget`, `updated_by`)
+            VALUES (?, ?, ?, ?, ?)
+            ON DUPLICATE KEY UPDATE
+                `period` = VALUES(`period`),
+                `amount` = VALUES(`amount`),
+                `block_over_budget` = VALUES(`block_over_budget`),
+                `updated_by` = VALUES(`updated_by`)
+            "#,
+        )
+        .bind(store_id)
+        .bind(draft.period.as_db_str())
+        .bind(draft.amount)
+        .bind(draft.block_over_budget)
+        .bind(updated_by)
+        .execute(&mut *transaction)
+        .await?;
+
+        sqlx::query("DELETE FROM `store_category_budgets` WHERE `store_id` = ?")
+            .bind(store_id)
+            .execute(&mut *transaction)
+            .await?;
+        for (category_id, amount) in &draft.categories {
+            sqlx::query(
+                "INSERT INTO `store_category_budgets` (`store_id`, `category_id`, `amount`) VALUES (?, ?, ?)",
+            )
+            .bind(store_id)
+            .bind(category_id)
+            .bind(amount)
+            .execute(&mut *transaction)
+            .await?;
+        }
+
+        transaction.commit().await?;
+        Ok(())
+    }
+
+    pub async fn delete(pool: &MySqlPool, store_id: u64) -> anyhow::Result<bool> {
+        let result = sqlx::query("DELETE FROM `store_budgets` WHERE `store_id` = ?")
+            .bind(store_id)
+            .execute(pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Spend against each of the store's budgets between `from` and `until` (exclusive).
+    ///
+    /// Every order that was not cancelled counts, including those still awaiting approval.
+    /// The store-wide budget is measured on order totals and category budgets on order lines.
+    ///
+    /// The orders are read with shared locks, so inside a transaction the spend includes
+    /// orders committed after the transaction's snapshot was taken.
+    pub async fn usage(
+        &self,
+        conn: &mut MySqlConnection,
+        categories: &[CategoryBudgetRecord],
+        (from, until): (NaiveDate, NaiveDate),
+    ) -> anyhow::Result<Vec<BudgetUsage>> {
+        let mut usages = Vec::with_capacity(categories.len() + 1);
+
+        if let Some(amount) = self.amount {
+            let spent: Decimal = sqlx::query_scalar(
+                r#"
+                SELECT COALESCE(SUM(`total_amount`), 0)
+                FROM `orders`
+                WHERE `store_id` = ? AND `status` <> ? AND `created_at` >= ? AND `created_at` < ?
+                LOCK IN SHARE MODE
+                "#,
+            )
+            .bind(self.store_id)
+            .bind(StoreOrderStatus::Cancelled.as_db_str())
+            .bind(from)
+            .bind(until)
+            .fetch_one(&mut *conn)
+            .await?;
+            usages.push(BudgetUsage {
+                category_id: None,
+                category_name: None,
+                budget: amount,
+                spent,
+            });
+        }
+
+        if !categories.is_empty() {
+            let rows = sqlx::query(
+                r#"
+                SELECT p.category_id, SUM(oi.total_price) AS spent
+                FROM `order_items` oi
+                JOIN `orders` o ON oi.order_id = o.id
+                JOIN `products` p ON oi.product_id = p.id
+                WHERE o.store_id = ? AND o.status <> ? AND o.created_at >= ? AND o.created_at < ?
+                GROUP BY p.category_id
+                LOCK IN SHARE MODE
+                "#,
+            )
+            .bind(self.store_id)
+            .bind(StoreOrderStatus::Cancelled.as_db_str())
+            .bind(from)
+            .bind(until)
+            .fetch_all(&mut *conn)
+            .await?;
+
+            let parents = category_parents(conn).await?;
+            let budgeted: HashSet<u64> = categories.iter().map(|c| c.category_id).collect();
+            let mut spent: HashMap<u64, Decimal> = HashMap::new();
+            for row in rows {
+                let category_id: u64 = row.get("category_id");
+                if let Some(budgeted_id) = budgeted_category_for(Some(category_id), &parents, &budgeted) {
+                    *spent.entry(budgeted_id).or_default() += row.get::<Decimal, _>("spent");
+                }
+            }
+            usages.extend(categories.iter().map(|c| BudgetUsage {
+                category_id: Some(c.category_id),
+                category_name: Some(c.category_name.clone()),
+                budget: c.amount,
+                spent: spent.get(&c.category_id).copied().unwrap_or_default(),
+            }));
+        }
+
+        Ok(usages)
+    }
+}
+
+impl BudgetSpendReport {
+    /// Spend to date in the budget period containing `date`; `None` if the store has no budget
+    pub async fn for_store(
+        pool: &MySqlPool,
+        store_id: u64,
+        date: NaiveDate,
+    ) -> anyhow::Result<Option<Self>> {
+        let Some(budget) = StoreBudgetRecord::get(pool, store_id).await? else {
+            return Ok(None);
+        };
+        let categories = StoreBudgetRecord::get_categories(pool, store_id).await?;
+        let (start, end) = budget.period.bounds(date);
+        let mut conn = pool.acquire().await?;
+        let usages = budget.usage(&mut conn, &categories, (start, end)).await?;
+
+        Ok(Some(Self {
+            store_id: serde_hash::hashids::encode_single(store_id),
+            period: budget.period,
+            period_start: start,
+            period_end: end - Days::new(1),
+            block_over_budget: budget.block_over_budget,
+            total: usages
+                .iter()
+                .find(|u| u.category_id.is_none())
+                .map(BudgetLineDto::from),
+            categories: usages
+                .iter()
+                .filter(|u| u.category_id.is_some())
+                .map(BudgetLineDto::from)
+                .collect(),
+        }))
+    }
+}
+
+impl BudgetCheck {
+    /// How an order for `items` at current prices fits in the store's current budget period;
+    /// `None` if the store has no budget.
+    ///
+    /// The budget row stays locked until the transaction ends, so orders for the same store
+    /// are checked one at a time and two of them cannot both fit in what is left.
+    pub async fn for_order(
+        transaction: &mut Transaction<'_, MySql>,
+        store_id: u64,
+        items: &[(u64, i32)], // (product_id, quantity)
+    ) -> anyhow::Result<Option<Self>> {
+        let budget = sqlx::query_as::<_, StoreBudgetRecord>(
+            "SELECT * FROM `store_budgets` WHERE `store_id` = ? FOR UPDATE",
+        )
+        .bind(store_id)
+        .fetch_optional(&mut **transaction)
+        .await?;
+        let Some(budget) = budget else {
+            return Ok(None);
+        };
+        let categories = StoreBudgetRecord::get_categories(&mut **transaction, store_id).await?;
+        let bounds = budget.period.bounds(chrono::Local::now().date_naive());
+        let usages = budget.usage(transaction, &categories, bounds).await?;
+
+        let parents = category_parents(transaction).await?;
+        let budgeted: HashSet<u64> = categories.iter().map(|c| c.category_id).collect();
+        let mut order_amounts: HashMap<Option<u64>, Decimal> = HashMap::new();
+        for (product_id, quantity) in items {
+            let Some(product) = sqlx::query("SELECT `price`, `category_id` FROM `products` WHERE `id` = ?")
+                .bind(product_id)
+                .fetch_optional(&mut **transaction)
+                .await?
+            else {
+                continue;
+            };
+            let line_total = product.get::<Decimal, _>("price") * Decimal::from(*quantity);
+            *order_amounts.entry(None).or_default() += line_total;
+            let category_id: u64 = product.get("category_id");
+            if let Some(budgeted_id) = budgeted_category_for(Some(category_id), &parents, &budgeted) {
+                *order_amounts.entry(Some(budgeted_id)).or_default() += line_total;
+            }
+        }
+
+        Ok(Some(Self::evaluate(
+            &usages,
+            &order_amounts,
+            budget.block_over_budget,
+            bounds,
+        )))
+    }
+}
@@ -0,0 +1,180 @@
+use crate::auth::ClaimsExtractor;
+use crate::categories::categories_data::CategoryRecord;
+use crate::orders::budget_data::{
+    BudgetCheck, BudgetQuery, BudgetSpendReport, SaveStoreBudgetRequest, StoreBudgetDraft,
+    StoreBudgetRecord, StoreBudgetWithCategoriesDto,
+};
+use crate::orders::orders_endpoint::resolve_store_id;
+use crate::stores::stores_data::StoreRecord;
+use actix_web::{delete, get, put, web, HttpRequest, HttpResponse, Responder};
+use database_common_lib::{database_connection::DatabaseConnectionData, http_error::Result};
+use rust_decimal::Decimal;
+use serde_json::json;
+use std::collections::HashSet;
+
+/// Response for an order refused because it would exceed a budget that blocks overspending
+pub(crate) fn over_budget_response(check: &BudgetCheck) -> HttpResponse {
+    HttpResponse::Conflict().json(json!({
+        "success": false,
+        "error": "This order would exceed the store's budget for the period",
+        "budget": check
+    }))
+}
+
+#[get("/budgets")]
+pub async fn get_budget(
+    req: HttpRequest,
+    connection_data: web::Data<DatabaseConnectionData>,
+    query: web::Query<BudgetQuery>,
+) -> Result<impl Responder> {
+    let store_id = match resolve_store_id(&req, query.store_id.as_deref())? {
+        Ok(store_id) => store_id,
+        Err(response) => return Ok(response),
+    };
+    let pool = connection_data.get_pool().await?;
+
+    let Some(budget) = StoreBudgetRecord::get(&pool, store_id).await? else {
+        return Ok(HttpResponse::NotFound().json(json!({
+            "success": false,
+            "error": "No budget set for this store"
+        })));
+    };
+    let categories = StoreBudgetRecord::get_categories(&pool, store_id).await?;
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "data": StoreBudgetWithCategoriesDto { budget, categories }
+    })))
+}
+
+#[put("/budgets")]
+pub async fn save_budget(
+    req: HttpRequest,
+    connection_data: web::Data<DatabaseConnectionData>,
+    request: web::Json<SaveStoreBudgetRequest>,
+) -> Result<impl Responder> {
+    let claims = req
+        .get_claims()
+        .ok_or_else(|| anyhow::anyhow!("Authentication required"))?;
+    if claims.role != "admin" {
+        return Ok(HttpResponse::Forbidden().json(json!({
+            "success": false,
+            "error": "Admin access required"
+        })));
+    }
+    let pool = connection_data.get_pool().await?;
+    let store_id = serde_hash::hashids::decode_single(&request.store_id)?;
+
+    let mut errors = Vec::new();
+    if StoreRecord::get_by_id(&pool, store_id).await?.is_none() {
+        errors.push(format!("Store {} does not exist", request.store_id));
+    }
+    if request.amount.is_some_and(|a| a <= Decimal::ZERO) {
+        errors.push("Budget amount must be greater than zero".to_string());
+    }
+    if request.amount.is_none() && request.categories.is_empty() {
+        errors.push("Set a store-wide amount, category budgets, or both".to_string());
+    }
+    let mut seen = HashSet::new();
+    let mut categories = Vec::with_capacity(request.categories.len());
+    for category in &request.categories {
+        let category_id = serde_hash::hashids::decode_single(&category.category_id)?;
+        if category.amount <= Decimal::ZERO {
+            errors.push(format!(
+                "Budget for category {} must be greater than zero",
+                category.category_id
+            ));
+        }
+        if !seen.insert(category_id) {
+            errors.push(format!("Category {} is listed more than once", category.category_id));
+        }
+        if CategoryRecord::get_by_id(&pool, category_id).await?.is_none() {
+            errors.push(format!("Category {} does not exist", category.category_id));
+        }
+        categories.push((category_id, category.amount));
+    }
+    if !errors.is_empty() {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "success": false,
+            "error": "Invalid budget",
+            "details": errors
+        })));
+    }
+
+    let draft = StoreBudgetDraft {
+        period: request.period,
+        amount: request.amount,
+        block_over_budget: request.block_over_budget,
+        categories,
+    };
+    StoreBudgetRecord::save(&pool, store_id, &draft, claims.sub).await?;
+    let budget = StoreBudgetRecord::get(&pool, store_id)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("Failed to retrieve saved budget"))?;
+    let categories = StoreBudgetRecord::get_categories(&pool, store_id).await?;
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "data": StoreBudgetWithCategoriesDto { budget, categories },
+        "message": "Budget saved"
+    })))
+}
+
+#[delete("/budgets")]
+pub async fn delete_budget(
+    req: HttpRequest,
+    connection_data: web::Data<DatabaseConnectionData>,
+    query: web::Query<BudgetQuery>,
+) -> Result<impl Responder> {
+    let claims = req
+        .get_claims()
+        .ok_or_else(|| anyhow::anyhow!("Authentication required"))?;
+    if claims.role != "admin" {
+        return Ok(HttpResponse::Forbidden().json(json!({
+            "success": false,
+            "error": "Admin access required"
+        })));
+    }
+    let store_id = match resolve_store_id(&req, query.store_id.as_deref())? {
+        Ok(store_id) => store_id,
+        Err(response) => return Ok(response),
+    };
+    let pool = connection_data.get_pool().await?;
+
+    if !StoreBudgetRecord::delete(&pool, store_id).await? {
+        return Ok(HttpResponse::NotFound().json(json!({
+            "success": false,
+            "error": "No budget set for this store"
+        })));
+    }
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "message": "Budget removed"
+    })))
+}
+
+/// Spend to date against the store's budgets for the current period, or the one containing `date`
+#[get("/budgets/spend")]
+pub async fn get_budget_spend(
+    req: HttpRequest,
+    connection_data: web::Data<DatabaseConnectionData>,
+    query: web::Query<BudgetQuery>,
+) -> Result<impl Responder> {
+    let store_id = match resolve_store_id(&req, query.store_id.as_deref())? {
+        Ok(store_id) => store_id,
+        Err(response) => return Ok(response),
+    };
+    let pool = connection_data.get_pool().await?;
+
+    let date = query
+        .date
+        .unwrap_or_else(|| chrono::Local::now().date_naive());
+    match BudgetSpendReport::for_store(&pool, store_id, date).await? {
+        Some(report) => Ok(HttpResponse::Ok().json(json!({
+            "success": true,
+            "data": report
+        }))),
+        None => Ok(HttpResponse::NotFound().json(json!({
+            "success": false,
+            "error": "No budget set for this store"
+        }))),
+    }
+}
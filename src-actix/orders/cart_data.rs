@@ -0,0 +1,154 @@
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use serde_hash::HashIds;
+use sqlx::FromRow;
+
+#[derive(HashIds, Debug, Clone, FromRow)]
+pub struct CartRecord {
+    #[hash]
+    pub id: u64,
+    #[hash]
+    pub user_id: u64,
+    #[hash]
+    pub store_id: Option<u64>,
+    pub created_at: chrono::NaiveDateTime,
+    pub updated_at: chrono::NaiveDateTime,
+}
+
+#[derive(Debug, Clone, FromRow)]
+pub struct CartItemRecord {
+    pub id: u64,
+    pub cart_id: u64,
+    pub product_id: u64,
+    pub quantity: i32,
+    /// Product price captured when the line was added or last re-validated
+    pub unit_price: Decimal,
+    pub created_at: chrono::NaiveDateTime,
+    pub updated_at: chrono::NaiveDateTime,
+}
+
+#[derive(Debug, Clone)]
+pub struct CartItemWithProduct {
+    pub item: CartItemRecord,
+    pub product_name: String,
+    pub product_sku: String,
+    pub product_image_url: Option<String>,
+    pub product_bin_location: String,
+    pub product_unit_type: i32,
+    pub category_name: String,
+    /// Live product state, used to re-validate the line at checkout
+    pub current_price: Decimal,
+    pub is_active: bool,
+    pub in_stock: bool,
+    pub allow_backorder: bool,
+}
+
+#[derive(Debug)]
+pub struct CartWithItems {
+    pub cart: CartRecord,
+    pub items: Vec<CartItemWithProduct>,
+}
+
+// DTOs for API (convert Decimal -> f64)
+#[derive(Debug, Serialize)]
+pub struct CartItemWithProductDto {
+    pub id: String,
+    pub product_id: String,
+    pub quantity: i32,
+    pub unit_price: f64,
+    pub total_price: f64,
+    pub product_name: String,
+    pub product_sku: String,
+    pub product_image_url: Option<String>,
+    pub product_bin_location: String,
+    pub product_unit_type: i32,
+    pub category_name: String,
+    pub current_price: f64,
+    pub is_active: bool,
+    pub in_stock: bool,
+    pub created_at: chrono::NaiveDateTime,
+    pub updated_at: chrono::NaiveDateTime,
+}
+
+impl From<&CartItemWithProduct> for CartItemWithProductDto {
+    fn from(r: &CartItemWithProduct) -> Self {
+        let total_price = r.item.unit_price * Decimal::from(r.item.quantity);
+        Self {
+            id: serde_hash::hashids::encode_single(r.item.id),
+            product_id: serde_hash::hashids::encode_single(r.item.product_id),
+            quantity: r.item.quantity,
+            unit_price: r.item.unit_price.to_f64().unwrap_or(0.0),
+            total_price: total_price.to_f64().unwrap_or(0.0),
+            product_name: r.product_name.clone(),
+            product_sku: r.product_sku.clone(),
+            product_image_url: r.product_image_url.clone(),
+            product_bin_location: r.product_bin_location.clone(),
+            product_unit_type: r.product_unit_type,
+            category_name: r.category_name.clone(),
+            current_price: r.current_price.to_f64().unwrap_or(0.0),
+            is_active: r.is_active,
+            in_stock: r.in_stock,
+            created_at: r.item.created_at,
+            updated_at: r.item.updated_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct CartWithItemsDto {
+    #[serde(flatten)]
+    pub cart: CartRecord,
+    pub items: Vec<CartItemWithProductDto>,
+    pub item_count: i32,
+    pub total_amount: f64,
+}
+
+impl From<&CartWithItems> for CartWithItemsDto {
+    fn from(c: &CartWithItems) -> Self {
+        let total_amount: Decimal = c
+            .items
+            .iter()
+            .map(|i| i.item.unit_price * Decimal::from(i.item.quantity))
+            .sum();
+        Self {
+            cart: c.cart.clone(),
+            items: c.items.iter().map(|i| i.into()).collect(),
+            item_count: c.items.iter().map(|i| i.item.quantity).sum(),
+            total_amount: total_amount.to_f64().unwrap_or(0.0),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdateCartItemRequest {
+    pub quantity: i32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CheckoutCartRequest {
+    pub store_id: Option<String>, // hashed ID, required for admins
+    pub notes: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CartLineChangeReason {
+    PriceChanged,
+    Inactive,
+    OutOfStock,
+}
+
+/// A cart line that no longer matches the product catalog at checkout time
+#[derive(Debug, Serialize)]
+pub struct CartLineChange {
+    pub product_id: String,
+    pub product_name: String,
+    pub product_sku: String,
+    pub reason: CartLineChangeReason,
+    pub quantity: i32,
+    pub previous_price: f64,
+    pub current_price: f64,
+    /// Whether the line was dropped from the cart
+    pub removed: bool,
+}
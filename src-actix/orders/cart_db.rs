@@ -0,0 +1,340 @@
+use crate::orders::cart_data::{
+    CartItemRecord, CartItemWithProduct, CartLineChange, CartLineChangeReason, CartRecord,
+    CartWithItems,
+};
+use crate::orders::orders_data::{OrderCreateResult, StoreOrderRecord, UserContext};
+use crate::products::low_stock_data::LowStockItem;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use sqlx::{Executor, MySqlPool};
+
+// Define a custom struct for the cart items with products query
+#[derive(sqlx::FromRow)]
+struct CartItemWithProductQuery {
+    // CartItem fields
+    id: u64,
+    cart_id: u64,
+    product_id: u64,
+    quantity: i32,
+    unit_price: Decimal,
+    created_at: chrono::NaiveDateTime,
+    updated_at: chrono::NaiveDateTime,
+    // Product fields
+    product_name: String,
+    product_sku: String,
+    product_image_url: Option<String>,
+    product_bin_location: String,
+    product_unit_type: i32,
+    current_price: Decimal,
+    is_active: bool,
+    in_stock: bool,
+    allow_backorder: bool,
+    // Category fields
+    category_name: String,
+}
+
+impl From<CartItemWithProductQuery> for CartItemWithProduct {
+    fn from(query_result: CartItemWithProductQuery) -> Self {
+        Self {
+            item: CartItemRecord {
+                id: query_result.id,
+                cart_id: query_result.cart_id,
+                product_id: query_result.product_id,
+                quantity: query_result.quantity,
+                unit_price: query_result.unit_price,
+                created_at: query_result.created_at,
+                updated_at: query_result.updated_at,
+            },
+            product_name: query_result.product_name,
+            product_sku: query_result.product_sku,
+            product_image_url: query_result.product_image_url,
+            product_bin_location: query_result.product_bin_location,
+            product_unit_type: query_result.product_unit_type,
+            category_name: query_result.category_name,
+            current_price: query_result.current_price,
+            is_active: query_result.is_active,
+            in_stock: query_result.in_stock,
+            allow_backorder: query_result.allow_backorder,
+        }
+    }
+}
+
+pub async fn initialize(pool: &MySqlPool) -> anyhow::Result<()> {
+    // One cart per user, kept until checkout or an explicit clear
+    pool.execute(
+        r#"
+        CREATE TABLE IF NOT EXISTS `carts` (
+            `id` BIGINT UNSIGNED NOT NULL AUTO_INCREMENT,
+            `user_id` BIGINT UNSIGNED NOT NULL UNIQUE,
+            `store_id` BIGINT UNSIGNED,
+            `created_at` DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            `updated_at` DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+            PRIMARY KEY (`id`),
+            FOREIGN KEY (`user_id`) REFERENCES `users`(`id`) ON DELETE CASCADE,
+            INDEX `idx_store_id` (`store_id`)
+        )
+        "#,
+    )
+    .await?;
+
+    pool.execute(
+        r#"
+        CREATE TABLE IF NOT EXISTS `cart_items` (
+            `id` BIGINT UNSIGNED NOT NULL AUTO_INCREMENT,
+            `cart_id` BIGINT UNSIGNED NOT NULL,
+            `product_id` BIGINT UNSIGNED NOT NULL,
+            `quantity` INT NOT NULL,
+            `unit_price` DECIMAL(10,2) NOT NULL,
+            `created_at` DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            `updated_at` DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+            PRIMARY KEY (`id`),
+            UNIQUE KEY `uq_cart_product` (`cart_id`, `product_id`),
+            FOREIGN KEY (`cart_id`) REFERENCES `carts`(`id`) ON DELETE CASCADE,
+            FOREIGN KEY (`product_id`) REFERENCES `products`(`id`) ON DELETE CASCADE,
+            INDEX `idx_cart_id` (`cart_id`)
+        )
+        "#,
+    )
+    .await?;
+
+    Ok(())
+}
+
+impl CartRecord {
+    pub async fn get_for_user(pool: &MySqlPool, user_id: u64) -> anyhow::Result<Option<Self>> {
+        let cart = sqlx::query_as::<_, Self>(
+            r#"
+            SELECT id, user_id, store_id, created_at, updated_at
+            FROM `carts`
+            WHERE `user_id` = ?
+            "#,
+        )
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(cart)
+    }
+
+    /// Returns the user's cart, creating an empty one on first use.
+    pub async fn get_or_create(
+        pool: &MySqlPool,
+        user_id: u64,
+        store_id: Option<u64>,
+    ) -> anyhow::Result<Self> {
+        sqlx::query(
+            r#"
+            INSERT INTO `carts` (`user_id`, `store_id`)
+            VALUES (?, ?)
+            ON DUPLICATE KEY UPDATE `store_id` = COALESCE(VALUES(`store_id`), `store_id`)
+            "#,
+        )
+        .bind(user_id)
+        .bind(store_id)
+        .execute(pool)
+        .await?;
+
+        Self::get_for_user(pool, user_id)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("Failed to retrieve cart"))
+    }
+
+    pub async fn get_with_items(
+        pool: &MySqlPool,
+        user_id: u64,
+        store_id: Option<u64>,
+    ) -> anyhow::Result<CartWithItems> {
+        let cart = Self::get_or_create(pool, user_id, store_id).await?;
+        let items = CartItemRecord::get_items_with_products(pool, cart.id).await?;
+        Ok(CartWithItems { cart, items })
+    }
+
+    /// Adds `quantity` of a product to the cart, merging with an existing line.
+    pub async fn add_item(
+        &self,
+        pool: &MySqlPool,
+        product_id: u64,
+        quantity: i32,
+        unit_price: Decimal,
+    ) -> anyhow::Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO `cart_items` (`cart_id`, `product_id`, `quantity`, `unit_price`)
+            VALUES (?, ?, ?, ?)
+            ON DUPLICATE KEY UPDATE
+                `quantity` = `quantity` + VALUES(`quantity`),
+                `unit_price` = VALUES(`unit_price`)
+            "#,
+        )
+        .bind(self.id)
+        .bind(product_id)
+        .bind(quantity)
+        .bind(unit_price)
+        .execute(pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn set_item_quantity(
+        &self,
+        pool: &MySqlPool,
+        product_id: u64,
+        quantity: i32,
+    ) -> anyhow::Result<bool> {
+        let result = sqlx::query(
+            "UPDATE `cart_items` SET `quantity` = ? WHERE `cart_id` = ? AND `product_id` = ?",
+        )
+        .bind(quantity)
+        .bind(self.id)
+        .bind(product_id)
+        .execute(pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn remove_item(&self, pool: &MySqlPool, product_id: u64) -> anyhow::Result<bool> {
+        let result =
+            sqlx::query("DELETE FROM `cart_items` WHERE `cart_id` = ? AND `product_id` = ?")
+                .bind(self.id)
+                .bind(product_id)
+                .execute(pool)
+                .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Places the cart's `items` as an order and empties the cart in the same transaction,
+    /// so a checkout that fails after the order is written cannot leave the lines behind to
+    /// be ordered twice. A rejected order leaves the cart as it was.
+    pub async fn checkout(
+        &self,
+        pool: &MySqlPool,
+        user_context: &UserContext,
+        store_id: u64,
+        items: &[(u64, i32)], // (product_id, quantity)
+        notes: Option<&str>,
+    ) -> anyhow::Result<OrderCreateResult> {
+        let mut transaction = pool.begin().await?;
+        let result = StoreOrderRecord::create_order_with_transaction(
+            &mut transaction,
+            user_context,
+            store_id,
+            items,
+            notes,
+        )
+        .await?;
+        if let OrderCreateResult::Created { order_id, .. } = result {
+            sqlx::query("DELETE FROM `cart_items` WHERE `cart_id` = ?")
+                .bind(self.id)
+                .execute(&mut *transaction)
+                .await?;
+            transaction.commit().await?;
+            LowStockItem::notify_crossed_by_order(pool, order_id);
+        }
+        Ok(result)
+    }
+
+    pub async fn clear(&self, pool: &MySqlPool) -> anyhow::Result<()> {
+        sqlx::query("DELETE FROM `cart_items` WHERE `cart_id` = ?")
+            .bind(self.id)
+            .execute(pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Re-validates every line against the live product catalog.
+    ///
+    /// Lines whose product is inactive or out of stock are removed and lines whose
+    /// price moved are repriced, so a second checkout attempt goes through with
+    /// exactly what the user was shown. Returns the lines that changed.
+    pub async fn revalidate(
+        &self,
+        pool: &MySqlPool,
+        items: &[CartItemWithProduct],
+    ) -> anyhow::Result<Vec<CartLineChange>> {
+        let mut changes = Vec::new();
+        let mut transaction = pool.begin().await?;
+
+        for line in items {
+            let reason = if !line.is_active {
+                Some(CartLineChangeReason::Inactive)
+            } else if !line.in_stock && !line.allow_backorder {
+                Some(CartLineChangeReason::OutOfStock)
+            } else if line.current_price != line.item.unit_price {
+                Some(CartLineChangeReason::PriceChanged)
+            } else {
+                None
+            };
+            let Some(reason) = reason else {
+                continue;
+            };
+
+            let removed = reason != CartLineChangeReason::PriceChanged;
+            if removed {
+                sqlx::query("DELETE FROM `cart_items` WHERE `id` = ?")
+                    .bind(line.item.id)
+                    .execute(&mut *transaction)
+                    .await?;
+            } else {
+                sqlx::query("UPDATE `cart_items` SET `unit_price` = ? WHERE `id` = ?")
+                    .bind(line.current_price)
+                    .bind(line.item.id)
+                    .execute(&mut *transaction)
+                    .await?;
+            }
+
+            changes.push(CartLineChange {
+                product_id: serde_hash::hashids::encode_single(line.item.product_id),
+                product_name: line.product_name.clone(),
+                product_sku: line.product_sku.clone(),
+                reason,
+                quantity: line.item.quantity,
+                previous_price: line.item.unit_price.to_f64().unwrap_or(0.0),
+                current_price: line.current_price.to_f64().unwrap_or(0.0),
+                removed,
+            });
+        }
+
+        transaction.commit().await?;
+        Ok(changes)
+    }
+}
+
+impl CartItemRecord {
+    pub async fn get_items_with_products(
+        pool: &MySqlPool,
+        cart_id: u64,
+    ) -> anyhow::Result<Vec<CartItemWithProduct>> {
+        let query_results = sqlx::query_as::<_, CartItemWithProductQuery>(
+            r#"
+            SELECT
+                ci.id, ci.cart_id, ci.product_id, ci.quantity, ci.unit_price,
+                ci.created_at, ci.updated_at,
+                p.name as product_name,
+                p.sku as product_sku,
+                p.image_url as product_image_url,
+                p.bin_location as product_bin_location,
+                p.unit_type as product_unit_type,
+                p.price as current_price,
+                p.is_active as is_active,
+                p.in_stock as in_stock,
+                p.allow_backorder as allow_backorder,
+                c.name as category_name
+            FROM `cart_items` ci
+            JOIN `products` p ON ci.product_id = p.id
+            JOIN `categories` c ON p.category_id = c.id
+            WHERE ci.cart_id = ?
+            ORDER BY c.name ASC, p.name ASC
+            "#,
+        )
+        .bind(cart_id)
+        .fetch_all(pool)
+        .await?;
+
+        let items = query_results.into_iter().map(|q| q.into()).collect();
+        Ok(items)
+    }
+}
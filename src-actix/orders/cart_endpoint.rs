@@ -0,0 +1,247 @@
+use crate::auth::{ClaimsExtractor, UserRole};
+use crate::orders::budget_endpoint;
+use crate::orders::cart_data::{CartRecord, CartWithItemsDto, CheckoutCartRequest, UpdateCartItemRequest};
+use crate::orders::orders_data::{
+    AddToCartRequest, OrderCreateResult, OrderWithItemsDto, StoreOrderRecord, UserContext,
+};
+use crate::orders::orders_endpoint::stock_rejected_response;
+use crate::products::ProductRecord;
+use actix_web::{delete, get, post, put, web, HttpRequest, HttpResponse, Responder};
+use database_common_lib::{database_connection::DatabaseConnectionData, http_error::Result};
+use serde_json::json;
+
+#[get("/cart")]
+pub async fn get_cart(
+    req: HttpRequest,
+    connection_data: web::Data<DatabaseConnectionData>,
+) -> Result<impl Responder> {
+    let pool = connection_data.get_pool().await?;
+
+    let claims = req
+        .get_claims()
+        .ok_or_else(|| anyhow::anyhow!("Authentication required"))?;
+
+    let cart = CartRecord::get_with_items(&pool, claims.sub, claims.store_id).await?;
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "data": CartWithItemsDto::from(&cart)
+    })))
+}
+
+#[post("/cart/add")]
+pub async fn add_to_cart(
+    req: HttpRequest,
+    connection_data: web::Data<DatabaseConnectionData>,
+    request: web::Json<AddToCartRequest>,
+) -> Result<impl Responder> {
+    let pool = connection_data.get_pool().await?;
+
+    let claims = req
+        .get_claims()
+        .ok_or_else(|| anyhow::anyhow!("Authentication required"))?;
+
+    if request.quantity <= 0 {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "success": false,
+            "error": "Quantity must be greater than zero"
+        })));
+    }
+
+    let product_id = serde_hash::hashids::decode_single(&request.product_id)?;
+
+    let product = match ProductRecord::get_by_id_simple(&pool, product_id).await? {
+        Some(product) if product.is_active && (product.in_stock || product.allow_backorder) => product,
+        _ => {
+            return Ok(HttpResponse::BadRequest().json(json!({
+                "success": false,
+                "error": "Product not found or out of stock"
+            })))
+        }
+    };
+
+    let cart = CartRecord::get_or_create(&pool, claims.sub, claims.store_id).await?;
+    cart.add_item(&pool, product_id, request.quantity, product.price).await?;
+
+    let cart = CartRecord::get_with_items(&pool, claims.sub, claims.store_id).await?;
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "message": "Product added to cart successfully",
+        "data": CartWithItemsDto::from(&cart)
+    })))
+}
+
+#[put("/cart/items/{product_id}")]
+pub async fn update_cart_item(
+    req: HttpRequest,
+    connection_data: web::Data<DatabaseConnectionData>,
+    path: web::Path<String>,
+    request: web::Json<UpdateCartItemRequest>,
+) -> Result<impl Responder> {
+    let pool = connection_data.get_pool().await?;
+    let product_id = serde_hash::hashids::decode_single(path.as_str())?;
+
+    let claims = req
+        .get_claims()
+        .ok_or_else(|| anyhow::anyhow!("Authentication required"))?;
+
+    let cart = CartRecord::get_or_create(&pool, claims.sub, claims.store_id).await?;
+
+    // A quantity of zero (or less) removes the line
+    let updated = if request.quantity <= 0 {
+        cart.remove_item(&pool, product_id).await?
+    } else {
+        cart.set_item_quantity(&pool, product_id, request.quantity).await?
+    };
+
+    if !updated {
+        return Ok(HttpResponse::NotFound().json(json!({
+            "success": false,
+            "error": "Product is not in the cart"
+        })));
+    }
+
+    let cart = CartRecord::get_with_items(&pool, claims.sub, claims.store_id).await?;
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "data": CartWithItemsDto::from(&cart)
+    })))
+}
+
+#[delete("/cart/items/{product_id}")]
+pub async fn remove_cart_item(
+    req: HttpRequest,
+    connection_data: web::Data<DatabaseConnectionData>,
+    path: web::Path<String>,
+) -> Result<impl Responder> {
+    let pool = connection_data.get_pool().await?;
+    let product_id = serde_hash::hashids::decode_single(path.as_str())?;
+
+    let claims = req
+        .get_claims()
+        .ok_or_else(|| anyhow::anyhow!("Authentication required"))?;
+
+    let cart = CartRecord::get_or_create(&pool, claims.sub, claims.store_id).await?;
+    if !cart.remove_item(&pool, product_id).await? {
+        return Ok(HttpResponse::NotFound().json(json!({
+            "success": false,
+            "error": "Product is not in the cart"
+        })));
+    }
+
+    let cart = CartRecord::get_with_items(&pool, claims.sub, claims.store_id).await?;
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "data": CartWithItemsDto::from(&cart)
+    })))
+}
+
+#[delete("/cart")]
+pub async fn clear_cart(
+    req: HttpRequest,
+    connection_data: web::Data<DatabaseConnectionData>,
+) -> Result<impl Responder> {
+    let pool = connection_data.get_pool().await?;
+
+    let claims = req
+        .get_claims()
+        .ok_or_else(|| anyhow::anyhow!("Authentication required"))?;
+
+    let cart = CartRecord::get_or_create(&pool, claims.sub, claims.store_id).await?;
+    cart.clear(&pool).await?;
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "message": "Cart cleared successfully"
+    })))
+}
+
+#[post("/cart/checkout")]
+pub async fn checkout_cart(
+    req: HttpRequest,
+    connection_data: web::Data<DatabaseConnectionData>,
+    request: web::Json<CheckoutCartRequest>,
+) -> Result<impl Responder> {
+    let pool = connection_data.get_pool().await?;
+
+    let claims = req
+        .get_claims()
+        .ok_or_else(|| anyhow::anyhow!("Authentication required"))?;
+
+    let requested_store_id = match &request.store_id {
+        Some(hash) => Some(serde_hash::hashids::decode_single(hash)?),
+        None => None,
+    };
+
+    // Store users always check out for their own store; admins must name one
+    let role = UserRole::from_str(&claims.role)?;
+    let store_id = match role {
+        UserRole::Store => {
+            if requested_store_id.is_some() && requested_store_id != claims.store_id {
+                return Ok(HttpResponse::Forbidden().json(json!({
+                    "success": false,
+                    "error": "Access denied: You can only create orders for your store"
+                })));
+            }
+            claims.store_id
+        }
+        UserRole::Admin => requested_store_id,
+    };
+    let Some(store_id) = store_id else {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "success": false,
+            "error": "A store is required to check out"
+        })));
+    };
+
+    let cart = CartRecord::get_with_items(&pool, claims.sub, claims.store_id).await?;
+    if cart.items.is_empty() {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "success": false,
+            "error": "Cart is empty"
+        })));
+    }
+
+    // Prices or availability moved since the lines were added: sync the cart and
+    // let the user confirm before placing the order
+    let changes = cart.cart.revalidate(&pool, &cart.items).await?;
+    if !changes.is_empty() {
+        let cart = CartRecord::get_with_items(&pool, claims.sub, claims.store_id).await?;
+        return Ok(HttpResponse::Conflict().json(json!({
+            "success": false,
+            "error": "Some cart items changed since they were added; review the cart and check out again",
+            "changes": changes,
+            "data": CartWithItemsDto::from(&cart)
+        })));
+    }
+
+    let user_context = UserContext::from_claims(claims.sub, claims.store_id, claims.role.clone());
+    let items: Vec<(u64, i32)> = cart
+        .items
+        .iter()
+        .map(|i| (i.item.product_id, i.item.quantity))
+        .collect();
+
+    // The cart is kept so the user can adjust the lines that cannot be filled
+    let (order_id, budget) = match cart
+        .cart
+        .checkout(&pool, &user_context, store_id, &items, request.notes.as_deref())
+        .await?
+    {
+        OrderCreateResult::Created { order_id, budget } => (order_id, budget),
+        OrderCreateResult::Rejected(issues) => return Ok(stock_rejected_response(&issues)),
+        OrderCreateResult::OverBudget(check) => {
+            return Ok(budget_endpoint::over_budget_response(&check));
+        }
+    };
+
+    let order = StoreOrderRecord::get_with_items(&pool, order_id)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("Failed to retrieve created order"))?;
+    let dto = OrderWithItemsDto::from(&order);
+    Ok(HttpResponse::Created().json(json!({
+        "success": true,
+        "data": dto,
+        "budget": budget,
+        "message": order.order.created_message()
+    })))
+}
@@ -0,0 +1,120 @@
+use crate::orders::store_order_status::StoreOrderStatus;
+use crate::stores::stores_data::StoreRecord;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use sqlx::FromRow;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Csv,
+    Xlsx,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OrderExportQuery {
+    #[serde(default)]
+    pub format: ExportFormat,
+    pub store_id: Option<String>, // hashed ID
+    pub status: Option<String>,
+    /// Inclusive date range on the order's creation date
+    pub from: Option<chrono::NaiveDate>,
+    pub to: Option<chrono::NaiveDate>,
+}
+
+/// Decoded and access-checked export filters
+#[derive(Debug, Clone, Default)]
+pub struct OrderExportFilter {
+    pub store_id: Option<u64>,
+    pub status: Option<StoreOrderStatus>,
+    pub from: Option<chrono::NaiveDate>,
+    pub to: Option<chrono::NaiveDate>,
+}
+
+/// One order line joined with its order, product and store
+#[derive(Debug, Clone, FromRow)]
+pub struct OrderExportRow {
+    pub order_number: String,
+    pub status: String,
+    pub store_id: u64,
+    pub store_city: Option<String>,
+    pub store_address: Option<String>,
+    pub created_at: chrono::NaiveDateTime,
+    pub total_amount: Decimal,
+    pub notes: Option<String>,
+    pub product_sku: String,
+    pub product_name: String,
+    pub category_name: String,
+    pub quantity: i32,
+    pub quantity_shipped: i32,
+    pub quantity_backordered: i32,
+    pub unit_price: Decimal,
+    pub total_price: Decimal,
+}
+
+pub enum ExportCell {
+    Text(String),
+    Number(f64),
+}
+
+impl ExportCell {
+    pub fn to_text(&self) -> String {
+        match self {
+            ExportCell::Text(text) => text.clone(),
+            ExportCell::Number(number) => number.to_string(),
+        }
+    }
+}
+
+impl OrderExportRow {
+    pub const HEADERS: [&'static str; 15] = [
+        "Order Number",
+        "Status",
+        "Store",
+        "Order Date",
+        "Order Total",
+        "Order Notes",
+        "SKU",
+        "Product",
+        "Category",
+        "Quantity",
+        "Shipped",
+        "Backordered",
+        "Outstanding",
+        "Unit Price",
+        "Line Total",
+    ];
+
+    /// Cells in [`Self::HEADERS`] order
+    pub fn cells(&self) -> [ExportCell; 15] {
+        let store = StoreRecord {
+            id: self.store_id,
+            city: self.store_city.clone(),
+            address: self.store_address.clone(),
+        };
+        let status = StoreOrderStatus::from_str_case_insensitive(&self.status)
+            .map(|s| s.to_string())
+            .unwrap_or_else(|| self.status.clone());
+        let outstanding = (self.quantity - self.quantity_shipped - self.quantity_backordered).max(0);
+
+        [
+            ExportCell::Text(self.order_number.clone()),
+            ExportCell::Text(status),
+            ExportCell::Text(store.display_name()),
+            ExportCell::Text(self.created_at.format("%Y-%m-%d %H:%M:%S").to_string()),
+            ExportCell::Number(self.total_amount.to_f64().unwrap_or(0.0)),
+            ExportCell::Text(self.notes.clone().unwrap_or_default()),
+            ExportCell::Text(self.product_sku.clone()),
+            ExportCell::Text(self.product_name.clone()),
+            ExportCell::Text(self.category_name.clone()),
+            ExportCell::Number(self.quantity as f64),
+            ExportCell::Number(self.quantity_shipped as f64),
+            ExportCell::Number(self.quantity_backordered as f64),
+            ExportCell::Number(outstanding as f64),
+            ExportCell::Number(self.unit_price.to_f64().unwrap_or(0.0)),
+            ExportCell::Number(self.total_price.to_f64().unwrap_or(0.0)),
+        ]
+    }
+}
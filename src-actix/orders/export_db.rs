@@ -0,0 +1,79 @@
+use crate::orders::export_data::{OrderExportFilter, OrderExportRow};
+use futures_util::TryStreamExt;
+use sqlx::MySqlPool;
+use tokio::sync::mpsc;
+
+impl OrderExportRow {
+    /// Reads matching order lines and hands them to `sender` one at a time.
+    ///
+    /// The bounded channel keeps only a few rows in flight, so an export of any size
+    /// is never held in memory. A query error is forwarded as the last message.
+    pub async fn stream(
+        pool: &MySqlPool,
+        filter: &OrderExportFilter,
+        sender: mpsc::Sender<anyhow::Result<Self>>,
+    ) {
+        let mut query = String::from(
+            r#"
+            SELECT
+                o.order_number, o.status, o.store_id,
+                s.city AS store_city,
+                s.address AS store_address,
+                o.created_at, o.total_amount, o.notes,
+                p.sku AS product_sku,
+                p.name AS product_name,
+                c.name AS category_name,
+                oi.quantity, oi.quantity_shipped, oi.quantity_backordered,
+                oi.unit_price, oi.total_price
+            FROM `orders` o
+            JOIN `order_items` oi ON oi.order_id = o.id
+            JOIN `products` p ON oi.product_id = p.id
+            JOIN `categories` c ON p.category_id = c.id
+            LEFT JOIN `stores` s ON o.store_id = s.id
+            WHERE 1 = 1
+            "#,
+        );
+        if filter.store_id.is_some() {
+            query.push_str(" AND o.store_id = ?");
+        }
+        if filter.status.is_some() {
+            query.push_str(" AND o.status = ?");
+        }
+        if filter.from.is_some() {
+            query.push_str(" AND o.created_at >= ?");
+        }
+        if filter.to.is_some() {
+            query.push_str(" AND o.created_at < ?");
+        }
+        query.push_str(" ORDER BY o.created_at ASC, o.id ASC, oi.id ASC");
+
+        let mut sql_query = sqlx::query_as::<_, Self>(&query);
+        if let Some(store_id) = filter.store_id {
+            sql_query = sql_query.bind(store_id);
+        }
+        if let Some(status) = &filter.status {
+            sql_query = sql_query.bind(status.as_db_str());
+        }
+        if let Some(from) = filter.from {
+            sql_query = sql_query.bind(from.and_hms_opt(0, 0, 0));
+        }
+        if let Some(to) = filter.to {
+            // Inclusive end date: everything before the following midnight
+            sql_query = sql_query.bind(to.succ_opt().and_then(|d| d.and_hms_opt(0, 0, 0)));
+        }
+
+        let mut rows = sql_query.fetch(pool);
+        loop {
+            let message = match rows.try_next().await {
+                Ok(Some(row)) => Ok(row),
+                Ok(None) => break,
+                Err(e) => Err(e.into()),
+            };
+            let failed = message.is_err();
+            // The receiver is gone when the client disconnected
+            if sender.send(message).await.is_err() || failed {
+                break;
+            }
+        }
+    }
+}
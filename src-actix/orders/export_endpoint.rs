@@ -0,0 +1,182 @@
+use crate::auth::ClaimsExtractor;
+use crate::orders::export_data::{
+    ExportCell, ExportFormat, OrderExportFilter, OrderExportQuery, OrderExportRow,
+};
+use crate::orders::store_order_status::StoreOrderStatus;
+use actix_files::NamedFile;
+use actix_web::http::header::{ContentDisposition, DispositionParam, DispositionType};
+use actix_web::{get, web, HttpRequest, HttpResponse, Responder};
+use database_common_lib::{database_connection::DatabaseConnectionData, http_error::Result};
+use futures_util::StreamExt;
+use rust_xlsxwriter::{Format, Workbook, Worksheet, XlsxError};
+use serde_json::json;
+use tokio::sync::mpsc;
+
+/// Rows buffered between the database reader and the response writer
+const EXPORT_BUFFER_ROWS: usize = 256;
+
+#[get("/export")]
+pub async fn export_orders(
+    req: HttpRequest,
+    connection_data: web::Data<DatabaseConnectionData>,
+    query: web::Query<OrderExportQuery>,
+) -> Result<impl Responder> {
+    let claims = req
+        .get_claims()
+        .ok_or_else(|| anyhow::anyhow!("Authentication required"))?;
+
+    let requested_store = match query.store_id.as_deref() {
+        Some(hash) => Some(serde_hash::hashids::decode_single(hash)?),
+        None => None,
+    };
+
+    // Same scoping as listing orders: admins see every store, store users only their own
+    let store_id = if claims.role == "admin" {
+        requested_store
+    } else if let Some(own_store) = claims.store_id {
+        if requested_store.is_some_and(|id| id != own_store) {
+            return Ok(HttpResponse::Forbidden().json(json!({
+                "success": false,
+                "error": "Access denied: You can only export orders for your store"
+            })));
+        }
+        Some(own_store)
+    } else {
+        return Ok(HttpResponse::Forbidden().json(json!({
+            "success": false,
+            "error": "Access denied: You can only export orders for your store"
+        })));
+    };
+
+    let status = match query.status.as_deref() {
+        Some(status) => match StoreOrderStatus::from_str_case_insensitive(status) {
+            Some(status) => Some(status),
+            None => {
+                return Ok(HttpResponse::BadRequest().json(json!({
+                    "success": false,
+                    "error": format!("Invalid status: {}", status)
+                })));
+            }
+        },
+        None => None,
+    };
+
+    if let (Some(from), Some(to)) = (query.from, query.to) {
+        if from > to {
+            return Ok(HttpResponse::BadRequest().json(json!({
+                "success": false,
+                "error": "The 'from' date must not be after the 'to' date"
+            })));
+        }
+    }
+
+    let filter = OrderExportFilter {
+        store_id,
+        status,
+        from: query.from,
+        to: query.to,
+    };
+
+    let pool = connection_data.get_pool().await?;
+    let (sender, receiver) = mpsc::channel(EXPORT_BUFFER_ROWS);
+    actix_web::rt::spawn(async move {
+        OrderExportRow::stream(&pool, &filter, sender).await;
+    });
+
+    let file_name = format!("orders-{}", chrono::Local::now().format("%Y%m%d-%H%M%S"));
+    match query.format {
+        ExportFormat::Csv => Ok(csv_response(&format!("{}.csv", file_name), receiver)),
+        ExportFormat::Xlsx => xlsx_response(&req, &format!("{}.xlsx", file_name), receiver).await,
+    }
+}
+
+/// Streams the CSV body row by row as the database produces them
+fn csv_response(
+    file_name: &str,
+    receiver: mpsc::Receiver<anyhow::Result<OrderExportRow>>,
+) -> HttpResponse {
+    let header = csv_line(OrderExportRow::HEADERS.iter().map(|h| h.to_string()));
+    let rows = futures_util::stream::unfold(receiver, |mut receiver| async move {
+        let line = match receiver.recv().await? {
+            Ok(row) => csv_line(row.cells().iter().map(ExportCell::to_text)),
+            // Aborts the response; the client sees a truncated download
+            Err(e) => Err(actix_web::error::ErrorInternalServerError(e)),
+        };
+        Some((line, receiver))
+    });
+
+    HttpResponse::Ok()
+        .content_type("text/csv; charset=utf-8")
+        .insert_header((
+            "Content-Disposition",
+            format!("attachment; filename=\"{}\"", file_name),
+        ))
+        .streaming(futures_util::stream::once(async move { header }).chain(rows))
+}
+
+fn csv_line(
+    fields: impl IntoIterator<Item = String>,
+) -> std::result::Result<web::Bytes, actix_web::Error> {
+    let mut writer = csv::WriterBuilder::new().from_writer(Vec::new());
+    writer
+        .write_record(fields)
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    let bytes = writer
+        .into_inner()
+        .map_err(|e| actix_web::error::ErrorInternalServerError(e.to_string()))?;
+    Ok(web::Bytes::from(bytes))
+}
+
+/// XLSX is a zip archive and cannot be sent before it is complete, so rows are written
+/// through a constant-memory worksheet to a temporary file which is then served from disk.
+async fn xlsx_response(
+    req: &HttpRequest,
+    file_name: &str,
+    mut receiver: mpsc::Receiver<anyhow::Result<OrderExportRow>>,
+) -> Result<HttpResponse> {
+    let mut workbook = Workbook::new();
+    let worksheet = workbook.add_worksheet_with_constant_memory();
+    worksheet.set_name("Orders")?;
+
+    let bold = Format::new().set_bold();
+    for (column, header) in OrderExportRow::HEADERS.iter().enumerate() {
+        worksheet.write_string_with_format(0, column as u16, *header, &bold)?;
+    }
+
+    let mut row_index = 1;
+    while let Some(row) = receiver.recv().await {
+        write_xlsx_row(worksheet, row_index, &row?)?;
+        row_index += 1;
+    }
+
+    let path = std::env::temp_dir().join(format!("{}.xlsx", uuid::Uuid::new_v4()));
+    let save_path = path.clone();
+    web::block(move || workbook.save(&save_path)).await??;
+
+    let file = NamedFile::open_async(&path).await;
+    // The open handle keeps the contents readable after the directory entry is gone
+    if let Err(e) = tokio::fs::remove_file(&path).await {
+        log::warn!("Failed to remove temporary export {}: {}", path.display(), e);
+    }
+
+    Ok(file?
+        .set_content_disposition(ContentDisposition {
+            disposition: DispositionType::Attachment,
+            parameters: vec![DispositionParam::Filename(file_name.to_string())],
+        })
+        .into_response(req))
+}
+
+fn write_xlsx_row(
+    worksheet: &mut Worksheet,
+    row_index: u32,
+    row: &OrderExportRow,
+) -> std::result::Result<(), XlsxError> {
+    for (column, cell) in row.cells().iter().enumerate() {
+        match cell {
+            ExportCell::Text(text) => worksheet.write_string(row_index, column as u16, text)?,
+            ExportCell::Number(number) => worksheet.write_number(row_index, column as u16, *number)?,
+        };
+    }
+    Ok(())
+}
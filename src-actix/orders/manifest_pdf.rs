@@ -0,0 +1,220 @@
+use crate::orders::orders_data::{OrderItemWithProductDto, OrderWithItemsDto};
+use printpdf::{
+    BuiltinFont, IndirectFontRef, Line, Mm, PdfDocument, PdfDocumentReference, PdfLayerReference,
+    Point,
+};
+use std::collections::BTreeMap;
+
+// US Letter, portrait
+const PAGE_WIDTH: f32 = 215.9;
+const PAGE_HEIGHT: f32 = 279.4;
+const MARGIN: f32 = 12.0;
+const ROW_HEIGHT: f32 = 5.0;
+const BODY_SIZE: f32 = 8.0;
+
+/// (header, x offset in mm, max characters) for each manifest column
+const COLUMNS: [(&str, f32, usize); 8] = [
+    ("Bin Loc", 0.0, 12),
+    ("Item Number", 20.0, 14),
+    ("Description", 43.0, 46),
+    ("MP", 112.0, 10),
+    ("Ordered", 128.0, 8),
+    ("Shipped", 143.0, 8),
+    ("Unit", 158.0, 6),
+    ("Order Total", 170.0, 14),
+];
+
+/// Everything printed on one order's manifest
+pub struct ManifestPdfData {
+    pub store_name: String,
+    pub order: OrderWithItemsDto,
+}
+
+/// Renders one or more manifests into a single PDF, each starting on a new page.
+///
+/// Mirrors `order-manifest-template.html.tera`: lines grouped by category with a
+/// subtotal per group and the order total at the end.
+pub fn render_manifests(title: &str, manifests: &[ManifestPdfData]) -> anyhow::Result<Vec<u8>> {
+    let mut writer = PdfWriter::new(title)?;
+
+    for (index, manifest) in manifests.iter().enumerate() {
+        if index > 0 {
+            writer.new_page();
+        }
+        write_manifest(&mut writer, manifest);
+    }
+
+    Ok(writer.doc.save_to_bytes()?)
+}
+
+fn write_manifest(writer: &mut PdfWriter, manifest: &ManifestPdfData) {
+    let order = &manifest.order.order;
+
+    writer.text(&manifest.store_name.to_uppercase(), 14.0, 0.0, true);
+    writer.advance(7.0);
+    writer.text(
+        &format!(
+            "Store Order Form | Order #: {} | Status: {} | Placed: {}",
+            order.order_number,
+            order.status,
+            order.created_at.format("%Y-%m-%d %H:%M")
+        ),
+        9.0,
+        0.0,
+        false,
+    );
+    writer.advance(5.0);
+    if let Some(notes) = order.notes.as_deref().filter(|n| !n.is_empty()) {
+        writer.text(&truncate(notes, 120), BODY_SIZE, 0.0, false);
+        writer.advance(5.0);
+    }
+    writer.advance(2.0);
+    write_column_headers(writer);
+
+    // Group items by category name (sorted), as the HTML manifest does
+    let mut groups: BTreeMap<&str, Vec<&OrderItemWithProductDto>> = BTreeMap::new();
+    for item in &manifest.order.items {
+        groups.entry(item.category_name.as_str()).or_default().push(item);
+    }
+
+    for (category, items) in groups {
+        if writer.ensure_space(ROW_HEIGHT * 2.0) {
+            write_column_headers(writer);
+        }
+        writer.text(&category.to_uppercase(), BODY_SIZE, 0.0, true);
+        writer.advance(ROW_HEIGHT);
+
+        let mut subtotal = 0.0;
+        for item in items {
+            if writer.ensure_space(ROW_HEIGHT) {
+                write_column_headers(writer);
+            }
+            let unit = match item.product_unit_type {
+                0 => "each",
+                1 => "case",
+                2 => "roll",
+                _ => "?",
+            };
+            let cells = [
+                item.product_bin_location.clone(),
+                item.product_sku.clone(),
+                item.product_name.clone(),
+                format!("${:.2}", item.item.unit_price),
+                item.item.quantity.to_string(),
+                item.item.quantity_shipped.to_string(),
+                unit.to_string(),
+                format!("${:.2}", item.item.total_price),
+            ];
+            for ((_, x, max_chars), cell) in COLUMNS.iter().zip(cells.iter()) {
+                writer.text(&truncate(cell, *max_chars), BODY_SIZE, *x, false);
+            }
+            subtotal += item.item.total_price;
+            writer.advance(ROW_HEIGHT);
+        }
+
+        writer.text(&format!("${:.2}", subtotal), BODY_SIZE, COLUMNS[7].1, true);
+        writer.advance(ROW_HEIGHT + 1.0);
+    }
+
+    writer.ensure_space(ROW_HEIGHT * 2.0);
+    writer.rule();
+    writer.advance(4.0);
+    writer.text("Order Total", 10.0, COLUMNS[5].1, true);
+    writer.text(&format!("${:.2}", order.total_amount), 10.0, COLUMNS[7].1, true);
+}
+
+fn write_column_headers(writer: &mut PdfWriter) {
+    for (header, x, _) in COLUMNS {
+        writer.text(header, BODY_SIZE, x, true);
+    }
+    writer.advance(1.5);
+    writer.rule();
+    writer.advance(ROW_HEIGHT - 1.5);
+}
+
+/// Builtin PDF fonts only cover Latin-1, and cells have fixed widths
+fn truncate(text: &str, max_chars: usize) -> String {
+    let text: String = text
+        .chars()
+        .map(|c| if c.is_ascii() && !c.is_ascii_control() { c } else { '?' })
+        .collect();
+    if text.chars().count() <= max_chars {
+        return text;
+    }
+    let mut truncated: String = text.chars().take(max_chars.saturating_sub(3)).collect();
+    truncated.push_str("...");
+    truncated
+}
+
+/// Top-down text cursor over a printpdf document
+struct PdfWriter {
+    doc: PdfDocumentReference,
+    font: IndirectFontRef,
+    bold: IndirectFontRef,
+    layer: PdfLayerReference,
+    /// Distance from the bottom of the page to the current line, in mm
+    y: f32,
+}
+
+impl PdfWriter {
+    fn new(title: &str) -> anyhow::Result<Self> {
+        let (doc, page, layer) = PdfDocument::new(title, Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "Layer 1");
+        let font = doc.add_builtin_font(BuiltinFont::Helvetica)?;
+        let bold = doc.add_builtin_font(BuiltinFont::HelveticaBold)?;
+        let layer = doc.get_page(page).get_layer(layer);
+        Ok(Self {
+            doc,
+            font,
+            bold,
+            layer,
+            y: PAGE_HEIGHT - MARGIN,
+        })
+    }
+
+    fn new_page(&mut self) {
+        let (page, layer) = self.doc.add_page(Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "Layer 1");
+        self.layer = self.doc.get_page(page).get_layer(layer);
+        self.y = PAGE_HEIGHT - MARGIN;
+    }
+
+    /// Starts a new page if `height` mm do not fit; returns whether it did
+    fn ensure_space(&mut self, height: f32) -> bool {
+        if self.y - height < MARGIN {
+            self.new_page();
+            return true;
+        }
+        false
+    }
+
+    fn advance(&mut self, height: f32) {
+        self.y -= height;
+    }
+
+    fn text(&self, text: &str, size: f32, x: f32, bold: bool) {
+        let font = if bold { &self.bold } else { &self.font };
+        self.layer.use_text(text, size, Mm(MARGIN + x), Mm(self.y), font);
+    }
+
+    fn rule(&self) {
+        self.layer.set_outline_thickness(0.5);
+        self.layer.add_line(Line {
+            points: vec![
+                (Point::new(Mm(MARGIN), Mm(self.y)), false),
+                (Point::new(Mm(PAGE_WIDTH - MARGIN), Mm(self.y)), false),
+            ],
+            is_closed: false,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn truncate_keeps_short_text_and_marks_cut_text() {
+        assert_eq!(truncate("A-12", 12), "A-12");
+        assert_eq!(truncate("Paper Towels, Jumbo Roll", 10), "Paper T...");
+        assert_eq!(truncate("Caf\u{e9}", 10), "Caf?");
+    }
+}
@@ -1,6 +1,44 @@
+pub mod budget_data;
+pub mod budget_db;
+mod budget_endpoint;
+pub mod cart_data;
+pub mod cart_db;
+mod cart_endpoint;
+pub mod export_data;
+pub mod export_db;
+mod export_endpoint;
+pub mod order_template_data;
+pub mod order_template_db;
+mod order_template_endpoint;
+pub mod manifest_pdf;
+pub mod order_number;
 pub mod orders_data;
 pub mod orders_db;
 mod orders_endpoint;
+pub mod par_level_data;
+pub mod par_level_db;
+mod par_level_endpoint;
+pub mod pick_list_data;
+pub mod pick_list_db;
+mod pick_list_endpoint;
+pub mod pick_wave_data;
+pub mod pick_wave_db;
+mod pick_wave_endpoint;
+pub mod receiving_data;
+pub mod receiving_db;
+mod receiving_endpoint;
 pub mod store_order_status;
-pub use orders_db::initialize;
 pub use orders_endpoint::configure;
+
+use sqlx::MySqlPool;
+
+pub async fn initialize(pool: &MySqlPool) -> anyhow::Result<()> {
+    orders_db::initialize(pool).await?;
+    cart_db::initialize(pool).await?;
+    receiving_db::initialize(pool).await?;
+    pick_wave_db::initialize(pool).await?;
+    order_template_db::initialize(pool).await?;
+    par_level_db::initialize(pool).await?;
+    budget_db::initialize(pool).await?;
+    Ok(())
+}
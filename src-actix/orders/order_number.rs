@@ -0,0 +1,146 @@
+use chrono::NaiveDate;
+use log::*;
+use std::sync::OnceLock;
+
+/// Format used when `ORDER_NUMBER_FORMAT` is unset or invalid, e.g. `ORD-20261017-0001`
+pub const DEFAULT_ORDER_NUMBER_FORMAT: &str = "ORD-{date}-{seq:4}";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Literal(String),
+    /// Order date as YYYYMMDD
+    Date,
+    /// Numeric store ID, zero-padded to the given width
+    Store(usize),
+    /// Sequence number, zero-padded to the given width
+    Seq(usize),
+}
+
+/// Layout of order numbers, built from literal text and `{date}`, `{store}` and `{seq}`
+/// placeholders. `{store:N}` and `{seq:N}` pad to N digits.
+///
+/// Sequences are counted per rendered prefix: everything but `{seq}`. A format without
+/// `{date}` therefore never resets, and one with `{store}` counts each store separately.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OrderNumberFormat {
+    parts: Vec<Part>,
+}
+
+impl OrderNumberFormat {
+    pub fn parse(format: &str) -> anyhow::Result<Self> {
+        let mut parts = Vec::new();
+        let mut rest = format;
+        while let Some(start) = rest.find('{') {
+            if start > 0 {
+                parts.push(Part::Literal(rest[..start].to_string()));
+            }
+            let end = rest[start..]
+                .find('}')
+                .map(|end| start + end)
+                .ok_or_else(|| anyhow::anyhow!("Unclosed placeholder in '{}'", format))?;
+            let placeholder = &rest[start + 1..end];
+            let (name, width) = match placeholder.split_once(':') {
+                Some((name, width)) => (
+                    name,
+                    width
+                        .parse::<usize>()
+                        .ok()
+                        .filter(|w| (1..=12).contains(w))
+                        .ok_or_else(|| anyhow::anyhow!("Invalid width in '{{{}}}'", placeholder))?,
+                ),
+                None => (placeholder, 1),
+            };
+            parts.push(match name {
+                "date" => Part::Date,
+                "store" => Part::Store(width),
+                "seq" => Part::Seq(width),
+                _ => anyhow::bail!("Unknown placeholder '{{{}}}'", placeholder),
+            });
+            rest = &rest[end + 1..];
+        }
+        if !rest.is_empty() {
+            parts.push(Part::Literal(rest.to_string()));
+        }
+
+        if parts.iter().filter(|p| matches!(p, Part::Seq(_))).count() != 1 {
+            anyhow::bail!("Order number format '{}' needs exactly one {{seq}}", format);
+        }
+        Ok(Self { parts })
+    }
+
+    /// The configured format, read once from `ORDER_NUMBER_FORMAT`
+    pub fn configured() -> &'static Self {
+        static FORMAT: OnceLock<OrderNumberFormat> = OnceLock::new();
+        FORMAT.get_or_init(|| {
+            let configured = std::env::var("ORDER_NUMBER_FORMAT").ok();
+            match configured.as_deref().map(Self::parse) {
+                Some(Ok(format)) => format,
+                Some(Err(e)) => {
+                    warn!("Ignoring ORDER_NUMBER_FORMAT: {}; using {}", e, DEFAULT_ORDER_NUMBER_FORMAT);
+                    Self::default()
+                }
+                None => Self::default(),
+            }
+        })
+    }
+
+    fn render_with(&self, date: NaiveDate, store_id: u64, seq: Option<u64>) -> String {
+        self.parts
+            .iter()
+            .map(|part| match part {
+                Part::Literal(text) => text.clone(),
+                Part::Date => date.format("%Y%m%d").to_string(),
+                Part::Store(width) => format!("{:0width$}", store_id, width = width),
+                Part::Seq(width) => match seq {
+                    Some(seq) => format!("{:0width$}", seq, width = width),
+                    None => "{seq}".to_string(),
+                },
+            })
+            .collect()
+    }
+
+    /// Key of the sequence an order number is drawn from
+    pub fn sequence_scope(&self, date: NaiveDate, store_id: u64) -> String {
+        self.render_with(date, store_id, None)
+    }
+
+    pub fn render(&self, date: NaiveDate, store_id: u64, seq: u64) -> String {
+        self.render_with(date, store_id, Some(seq))
+    }
+}
+
+impl Default for OrderNumberFormat {
+    fn default() -> Self {
+        Self::parse(DEFAULT_ORDER_NUMBER_FORMAT).expect("default order number format is valid")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 10, 17).unwrap()
+    }
+
+    #[test]
+    fn renders_padded_sequence() {
+        let format = OrderNumberFormat::default();
+        assert_eq!(format.render(date(), 3, 1), "ORD-20261017-0001");
+        assert_eq!(format.render(date(), 3, 12345), "ORD-20261017-12345");
+        assert_eq!(format.sequence_scope(date(), 3), "ORD-20261017-{seq}");
+
+        let per_store = OrderNumberFormat::parse("S{store:3}-{date}-{seq:3}").unwrap();
+        assert_eq!(per_store.render(date(), 12, 7), "S012-20261017-007");
+        assert_ne!(per_store.sequence_scope(date(), 12), per_store.sequence_scope(date(), 13));
+    }
+
+    #[test]
+    fn rejects_formats_without_a_single_sequence() {
+        assert!(OrderNumberFormat::parse("ORD-{date}").is_err());
+        assert!(OrderNumberFormat::parse("{seq}-{seq}").is_err());
+        assert!(OrderNumberFormat::parse("ORD-{day}-{seq}").is_err());
+        assert!(OrderNumberFormat::parse("ORD-{seq:0}").is_err());
+        assert!(OrderNumberFormat::parse("ORD-{seq").is_err());
+    }
+}
@@ -0,0 +1,308 @@
+use chrono::{Datelike, Days, NaiveDate};
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use serde_hash::HashIds;
+use sqlx::mysql::MySqlRow;
+use sqlx::{FromRow, Row};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TemplateFrequency {
+    Weekly,
+    Biweekly,
+}
+
+impl TemplateFrequency {
+    pub fn as_db_str(&self) -> &'static str {
+        match self {
+            TemplateFrequency::Weekly => "WEEKLY",
+            TemplateFrequency::Biweekly => "BIWEEKLY",
+        }
+    }
+
+    pub fn from_str_case_insensitive(s: &str) -> Option<Self> {
+        match s.to_ascii_uppercase().as_str() {
+            "WEEKLY" => Some(TemplateFrequency::Weekly),
+            "BIWEEKLY" => Some(TemplateFrequency::Biweekly),
+            _ => None,
+        }
+    }
+
+    /// The occurrence after the one on `date`
+    pub fn following(&self, date: NaiveDate) -> NaiveDate {
+        let days = match self {
+            TemplateFrequency::Weekly => 7,
+            TemplateFrequency::Biweekly => 14,
+        };
+        date + Days::new(days)
+    }
+
+    /// First occurrence strictly after `today`, stepping on from the one scheduled for `scheduled`.
+    ///
+    /// Missed occurrences (e.g. while the server was down) are skipped rather than replayed.
+    pub fn next_after(&self, scheduled: NaiveDate, today: NaiveDate) -> NaiveDate {
+        let mut next = self.following(scheduled);
+        while next <= today {
+            next = self.following(next);
+        }
+        next
+    }
+}
+
+/// First date on or after `from` that falls on `weekday` (0 = Monday ... 6 = Sunday)
+pub fn first_run_on_or_after(from: NaiveDate, weekday: u8) -> NaiveDate {
+    let current = from.weekday().num_days_from_monday() as u8;
+    let offset = (7 + weekday as u64 - current as u64) % 7;
+    from + Days::new(offset)
+}
+
+#[derive(HashIds, Debug, Clone)]
+pub struct OrderTemplateRecord {
+    #[hash]
+    pub id: u64,
+    #[hash]
+    pub store_id: u64,
+    pub name: String,
+    pub notes: Option<String>,
+    /// `None` for templates that are only ordered by hand
+    pub frequency: Option<TemplateFrequency>,
+    /// 0 = Monday ... 6 = Sunday
+    pub weekday: Option<u8>,
+    pub next_run_date: Option<NaiveDate>,
+    /// Paused templates keep their schedule but generate nothing
+    pub is_active: bool,
+    #[hash]
+    pub created_by: u64,
+    #[hash]
+    pub last_order_id: Option<u64>,
+    pub last_generated_at: Option<chrono::NaiveDateTime>,
+    /// Why the last scheduled occurrence created no order; cleared once one is created
+    pub last_error: Option<String>,
+    pub last_skipped_at: Option<chrono::NaiveDateTime>,
+    pub created_at: chrono::NaiveDateTime,
+    pub updated_at: chrono::NaiveDateTime,
+}
+
+impl<'r> FromRow<'r, MySqlRow> for OrderTemplateRecord {
+    fn from_row(row: &'r MySqlRow) -> Result<Self, sqlx::Error> {
+        let frequency_raw: Option<String> = row.try_get("frequency")?;
+        let frequency = match frequency_raw {
+            Some(raw) => Some(TemplateFrequency::from_str_case_insensitive(&raw).ok_or_else(
+                || sqlx::Error::Protocol(format!("Unexpected frequency value '{}'", raw)),
+            )?),
+            None => None,
+        };
+        Ok(Self {
+            id: row.try_get("id")?,
+            store_id: row.try_get("store_id")?,
+            name: row.try_get("name")?,
+            notes: row.try_get("notes")?,
+            frequency,
+            weekday: row.try_get("weekday")?,
+            next_run_date: row.try_get("next_run_date")?,
+            is_active: row.try_get("is_active")?,
+            created_by: row.try_get("created_by")?,
+            last_order_id: row.try_get("last_order_id")?,
+            last_generated_at: row.try_get("last_generated_at")?,
+            last_error: row.try_get("last_error")?,
+            last_skipped_at: row.try_get("last_skipped_at")?,
+            created_at: row.try_get("created_at")?,
+            updated_at: row.try_get("updated_at")?,
+        })
+    }
+}
+
+/// A template line joined with the live product it refers to
+#[derive(Debug, Clone, FromRow)]
+pub struct OrderTemplateItemRecord {
+    pub product_id: u64,
+    pub quantity: i32,
+    pub product_name: String,
+    pub product_sku: String,
+    pub current_price: Decimal,
+    pub is_active: bool,
+    pub in_stock: bool,
+}
+
+impl OrderTemplateItemRecord {
+    /// Inactive and out-of-stock products are left out of generated orders
+    pub fn is_orderable(&self) -> bool {
+        self.is_active && self.in_stock
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct OrderTemplateItemDto {
+    pub product_id: String,
+    pub quantity: i32,
+    pub product_name: String,
+    pub product_sku: String,
+    pub current_price: f64,
+    pub line_total: f64,
+    pub is_active: bool,
+    pub in_stock: bool,
+}
+
+impl From<&OrderTemplateItemRecord> for OrderTemplateItemDto {
+    fn from(r: &OrderTemplateItemRecord) -> Self {
+        Self {
+            product_id: serde_hash::hashids::encode_single(r.product_id),
+            quantity: r.quantity,
+            product_name: r.product_name.clone(),
+            product_sku: r.product_sku.clone(),
+            current_price: r.current_price.to_f64().unwrap_or(0.0),
+            line_total: (r.current_price * Decimal::from(r.quantity))
+                .to_f64()
+                .unwrap_or(0.0),
+            is_active: r.is_active,
+            in_stock: r.in_stock,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct OrderTemplateWithItemsDto {
+    #[serde(flatten)]
+    pub template: OrderTemplateRecord,
+    pub items: Vec<OrderTemplateItemDto>,
+    /// Whether the next occurrence has its own lines instead of the template's
+    pub next_occurrence_edited: bool,
+}
+
+/// What the next generated order would contain at current prices
+#[derive(Debug, Serialize)]
+pub struct NextOccurrencePreview {
+    pub template_id: String,
+    pub template_name: String,
+    pub store_id: String,
+    pub store_name: String,
+    /// `None` when the template has no schedule or is paused
+    pub date: Option<NaiveDate>,
+    pub edited: bool,
+    pub items: Vec<OrderTemplateItemDto>,
+    /// Lines that would be dropped because the product is inactive or out of stock
+    pub dropped_items: Vec<OrderTemplateItemDto>,
+    pub estimated_total: f64,
+}
+
+impl NextOccurrencePreview {
+    pub fn build(
+        template: &OrderTemplateRecord,
+        store_name: String,
+        items: &[OrderTemplateItemRecord],
+        edited: bool,
+    ) -> Self {
+        let (orderable, dropped): (Vec<_>, Vec<_>) = items.iter().partition(|i| i.is_orderable());
+        let estimated_total: Decimal = orderable
+            .iter()
+            .map(|i| i.current_price * Decimal::from(i.quantity))
+            .sum();
+        Self {
+            template_id: serde_hash::hashids::encode_single(template.id),
+            template_name: template.name.clone(),
+            store_id: serde_hash::hashids::encode_single(template.store_id),
+            store_name,
+            date: if template.is_active {
+                template.next_run_date
+            } else {
+                None
+            },
+            edited,
+            items: orderable.into_iter().map(|i| i.into()).collect(),
+            dropped_items: dropped.into_iter().map(|i| i.into()).collect(),
+            estimated_total: estimated_total.to_f64().unwrap_or(0.0),
+        }
+    }
+}
+
+/// One scheduled order the background task is going to create
+#[derive(Debug, Serialize)]
+pub struct UpcomingTemplateOrder {
+    pub date: NaiveDate,
+    pub template_id: String,
+    pub template_name: String,
+    pub store_id: String,
+    pub store_name: String,
+    pub line_count: usize,
+    pub estimated_total: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TemplateItemRequest {
+    pub product_id: String, // hashed ID
+    pub quantity: i32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TemplateScheduleRequest {
+    pub frequency: TemplateFrequency,
+    /// 0 = Monday ... 6 = Sunday
+    pub weekday: u8,
+}
+
+fn default_active() -> bool {
+    true
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SaveOrderTemplateRequest {
+    pub name: String,
+    pub store_id: Option<String>, // hashed ID, required for admins when creating
+    pub notes: Option<String>,
+    pub items: Vec<TemplateItemRequest>,
+    /// Omit for a template that is only ordered by hand
+    pub schedule: Option<TemplateScheduleRequest>,
+    #[serde(default = "default_active")]
+    pub is_active: bool,
+}
+
+/// A validated [`SaveOrderTemplateRequest`] with decoded IDs
+#[derive(Debug)]
+pub struct OrderTemplateDraft {
+    pub name: String,
+    pub notes: Option<String>,
+    pub items: Vec<(u64, i32)>, // (product_id, quantity)
+    pub schedule: Option<(TemplateFrequency, u8)>,
+    pub is_active: bool,
+}
+
+/// Changes that apply to the next generated order only
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EditNextOccurrenceRequest {
+    pub date: Option<NaiveDate>,
+    pub items: Option<Vec<TemplateItemRequest>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpcomingTemplateOrdersQuery {
+    /// How far ahead to look, defaults to 14 days
+    pub days: Option<u32>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn first_run_lands_on_requested_weekday() {
+        // 2025-06-04 is a Wednesday
+        assert_eq!(first_run_on_or_after(date(2025, 6, 4), 2), date(2025, 6, 4));
+        assert_eq!(first_run_on_or_after(date(2025, 6, 4), 0), date(2025, 6, 9));
+        assert_eq!(first_run_on_or_after(date(2025, 6, 4), 6), date(2025, 6, 8));
+    }
+
+    #[test]
+    fn next_run_skips_missed_occurrences() {
+        let weekly = TemplateFrequency::Weekly;
+        let biweekly = TemplateFrequency::Biweekly;
+        assert_eq!(weekly.next_after(date(2025, 6, 2), date(2025, 6, 2)), date(2025, 6, 9));
+        assert_eq!(weekly.next_after(date(2025, 6, 2), date(2025, 6, 20)), date(2025, 6, 23));
+        assert_eq!(biweekly.next_after(date(2025, 6, 2), date(2025, 6, 2)), date(2025, 6, 16));
+        assert_eq!(biweekly.next_after(date(2025, 6, 2), date(2025, 6, 16)), date(2025, 6, 30));
+    }
+}
@@ -0,0 +1,560 @@
+use crate::orders::order_template_data::{
+    first_run_on_or_after, OrderTemplateDraft, OrderTemplateItemRecord, OrderTemplateRecord,
+    UpcomingTemplateOrder,
+};
+use crate::orders::orders_data::{OrderCreateResult, StoreOrderRecord, UserContext};
+use crate::products::low_stock_data::LowStockItem;
+use crate::stores::stores_data::StoreRecord;
+use chrono::NaiveDate;
+use database_common_lib::database_connection::DatabaseConnectionData;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use sqlx::{Executor, MySql, MySqlPool, Transaction};
+use std::collections::HashMap;
+
+/// How often the background task looks for templates that are due
+const SCHEDULER_INTERVAL_SECS: u64 = 60 * 60;
+
+pub async fn initialize(pool: &MySqlPool) -> anyhow::Result<()> {
+    pool.execute(
+        r#"
+        CREATE TABLE IF NOT EXISTS `order_templates` (
+            `id` BIGINT UNSIGNED NOT NULL AUTO_INCREMENT,
+            `store_id` BIGINT UNSIGNED NOT NULL,
+            `name` VARCHAR(255) NOT NULL,
+            `notes` TEXT,
+            `frequency` ENUM('WEEKLY', 'BIWEEKLY') NULL DEFAULT NULL,
+            `weekday` TINYINT UNSIGNED NULL DEFAULT NULL,
+            `next_run_date` DATE NULL DEFAULT NULL,
+            `is_active` BOOLEAN NOT NULL DEFAULT TRUE,
+            `created_by` BIGINT UNSIGNED NOT NULL,
+            `last_order_id` BIGINT UNSIGNED NULL DEFAULT NULL,
+            `last_generated_at` DATETIME NULL DEFAULT NULL,
+            `last_error` TEXT NULL DEFAULT NULL,
+            `last_skipped_at` DATETIME NULL DEFAULT NULL,
+            `created_at` DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            `updated_at` DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+            PRIMARY KEY (`id`),
+            INDEX `idx_store_id` (`store_id`),
+            INDEX `idx_next_run_date` (`next_run_date`)
+        )
+        "#,
+    )
+    .await?;
+    pool.execute("ALTER TABLE order_templates ADD COLUMN `last_error` TEXT NULL DEFAULT NULL AFTER `last_generated_at`").await.ok();
+    pool.execute("ALTER TABLE order_templates ADD COLUMN `last_skipped_at` DATETIME NULL DEFAULT NULL AFTER `last_error`").await.ok();
+
+    pool.execute(
+        r#"
+        CREATE TABLE IF NOT EXISTS `order_template_items` (
+            `id` BIGINT UNSIGNED NOT NULL AUTO_INCREMENT,
+            `template_id` BIGINT UNSIGNED NOT NULL,
+            `product_id` BIGINT UNSIGNED NOT NULL,
+            `quantity` INT NOT NULL,
+            PRIMARY KEY (`id`),
+            UNIQUE KEY `uq_template_product` (`template_id`, `product_id`),
+            FOREIGN KEY (`template_id`) REFERENCES `order_templates`(`id`) ON DELETE CASCADE,
+            FOREIGN KEY (`product_id`) REFERENCES `products`(`id`) ON DELETE CASCADE
+        )
+        "#,
+    )
+    .await?;
+
+    // Lines for the next occurrence only, replacing the template's lines once
+    pool.execute(
+        r#"
+        CREATE TABLE IF NOT EXISTS `order_template_next_items` (
+            `id` BIGINT UNSIGNED NOT NULL AUTO_INCREMENT,
+            `template_id` BIGINT UNSIGNED NOT NULL,
+            `product_id` BIGINT UNSIGNED NOT NULL,
+            `quantity` INT NOT NULL,
+            PRIMARY KEY (`id`),
+            UNIQUE KEY `uq_template_product` (`template_id`, `product_id`),
+            FOREIGN KEY (`template_id`) REFERENCES `order_templates`(`id`) ON DELETE CASCADE,
+            FOREIGN KEY (`product_id`) REFERENCES `products`(`id`) ON DELETE CASCADE
+        )
+        "#,
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Starts the background task that creates the orders of templates that are due
+pub fn start_scheduler() {
+    tokio::spawn(async move {
+        loop {
+            match DatabaseConnectionData::get().await {
+                Ok(conn_data) => match conn_data.get_pool().await {
+                    Ok(pool) => match OrderTemplateRecord::generate_due(&pool).await {
+                        Ok(0) => {}
+                        Ok(created) => log::info!("Created {} orders from templates", created),
+                        Err(e) => log::error!("Failed to generate template orders: {}", e),
+                    },
+                    Err(e) => log::error!("Database connection failed: {}", e),
+                },
+                Err(e) => log::error!("Database connection failed: {}", e),
+            }
+            tokio::time::sleep(std::time::Duration::from_secs(SCHEDULER_INTERVAL_SECS)).await;
+        }
+    });
+}
+
+fn today() -> NaiveDate {
+    chrono::Local::now().date_naive()
+}
+
+impl OrderTemplateRecord {
+    pub async fn get_by_id(pool: &MySqlPool, id: u64) -> anyhow::Result<Option<Self>> {
+        let template = sqlx::query_as::<_, Self>("SELECT * FROM `order_templates` WHERE `id` = ?")
+            .bind(id)
+            .fetch_optional(pool)
+            .await?;
+        Ok(template)
+    }
+
+    /// All templates, or only those of one store
+    pub async fn get_all(pool: &MySqlPool, store_id: Option<u64>) -> anyhow::Result<Vec<Self>> {
+        let templates = match store_id {
+            Some(store_id) => {
+                sqlx::query_as::<_, Self>(
+                    "SELECT * FROM `order_templates` WHERE `store_id` = ? ORDER BY `name` ASC",
+                )
+                .bind(store_id)
+                .fetch_all(pool)
+                .await?
+            }
+            None => {
+                sqlx::query_as::<_, Self>(
+                    "SELECT * FROM `order_templates` ORDER BY `store_id` ASC, `name` ASC",
+                )
+                .fetch_all(pool)
+                .await?
+            }
+        };
+        Ok(templates)
+    }
+
+    pub async fn get_items(
+        pool: &MySqlPool,
+        template_id: u64,
+    ) -> anyhow::Result<Vec<OrderTemplateItemRecord>> {
+        Ok(sqlx::query_as::<_, OrderTemplateItemRecord>(&items_query("order_template_items"))
+            .bind(template_id)
+            .fetch_all(pool)
+            .await?)
+    }
+
+    /// Lines of the next occurrence: its one-off edit if there is one, else the template's.
+    /// The flag tells which.
+    pub async fn get_next_items(
+        pool: &MySqlPool,
+        template_id: u64,
+    ) -> anyhow::Result<(Vec<OrderTemplateItemRecord>, bool)> {
+        let edited =
+            sqlx::query_as::<_, OrderTemplateItemRecord>(&items_query("order_template_next_items"))
+                .bind(template_id)
+                .fetch_all(pool)
+                .await?;
+        if !edited.is_empty() {
+            return Ok((edited, true));
+        }
+        Ok((Self::get_items(pool, template_id).await?, false))
+    }
+
+    pub async fn create(
+        pool: &MySqlPool,
+        store_id: u64,
+        created_by: u64,
+        draft: &OrderTemplateDraft,
+    ) -> anyhow::Result<u64> {
+        let mut transaction = pool.begin().await?;
+
+        let result = sqlx::query(
+            r#"
+            INSERT INTO `order_templates`
+                (`store_id`, `name`, `notes`, `frequency`, `weekday`, `next_run_date`, `is_active`, `created_by`)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(store_id)
+        .bind(&draft.name)
+        .bind(&draft.notes)
+        .bind(draft.schedule.map(|(frequency, _)| frequency.as_db_str()))
+        .bind(draft.schedule.map(|(_, weekday)| weekday))
+        .bind(draft.schedule.map(|(_, weekday)| first_run_on_or_after(today(), weekday)))
+        .bind(draft.is_active)
+        .bind(created_by)
+        .execute(&mut *transaction)
+        .await?;
+        let template_id = result.last_insert_id();
+
+        replace_lines(&mut transaction, "order_template_items", template_id, &draft.items).await?;
+
+        transaction.commit().await?;
+        Ok(template_id)
+    }
+
+    /// Replaces the template's details and lines. A changed schedule restarts from the next
+    /// matching weekday and discards any edit of the next occurrence.
+    pub async fn update(&self, pool: &MySqlPool, draft: &OrderTemplateDraft) -> anyhow::Result<()> {
+        let mut transaction = pool.begin().await?;
+
+        let schedule = draft.schedule;
+        let next_run_date = if schedule == self.frequency.zip(self.weekday) {
+            self.next_run_date
+        } else {
+            replace_lines(&mut transaction, "order_template_next_items", self.id, &[]).await?;
+            schedule.map(|(_, weekday)| first_run_on_or_after(today(), weekday))
+        };
+
+        sqlx::query(
+            r#"
+            UPDATE `order_templates`
+            SET `name` = ?, `notes` = ?, `frequency` = ?, `weekday` = ?, `next_run_date` = ?, `is_active` = ?
+            WHERE `id` = ?
+            "#,
+        )
+        .bind(&draft.name)
+        .bind(&draft.notes)
+        .bind(schedule.map(|(frequency, _)| frequency.as_db_str()))
+        .bind(schedule.map(|(_, weekday)| weekday))
+        .bind(next_run_date)
+        .bind(draft.is_active)
+        .bind(self.id)
+        .execute(&mut *transaction)
+        .await?;
+
+        replace_lines(&mut transaction, "order_template_items", self.id, &draft.items).await?;
+
+        transaction.commit().await?;
+        Ok(())
+    }
+
+    pub async fn delete(pool: &MySqlPool, id: u64) -> anyhow::Result<bool> {
+        let result = sqlx::query("DELETE FROM `order_templates` WHERE `id` = ?")
+            .bind(id)
+            .execute(pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Moves the next occurrence and/or gives it its own lines. `None` leaves that part as is.
+    pub async fn edit_next_occurrence(
+        &self,
+        pool: &MySqlPool,
+        date: Option<NaiveDate>,
+        items: Option<&[(u64, i32)]>,
+    ) -> anyhow::Result<()> {
+        let mut transaction = pool.begin().await?;
+
+        if let Some(date) = date {
+            sqlx::query("UPDATE `order_templates` SET `next_run_date` = ? WHERE `id` = ?")
+                .bind(date)
+                .bind(self.id)
+                .execute(&mut *transaction)
+                .await?;
+        }
+        if let Some(items) = items {
+            replace_lines(&mut transaction, "order_template_next_items", self.id, items).await?;
+        }
+
+        transaction.commit().await?;
+        Ok(())
+    }
+
+    /// Skips the next occurrence, dropping its edit. Returns the new next date.
+    pub async fn skip_next_occurrence(&self, pool: &MySqlPool) -> anyhow::Result<Option<NaiveDate>> {
+        let (Some(frequency), Some(scheduled)) = (self.frequency, self.next_run_date) else {
+            return Ok(None);
+        };
+        // Never land on a date that has already passed
+        let next_run_date = frequency.next_after(scheduled, today().pred_opt().unwrap_or(scheduled));
+
+        let mut transaction = pool.begin().await?;
+        sqlx::query("UPDATE `order_templates` SET `next_run_date` = ? WHERE `id` = ?")
+            .bind(next_run_date)
+            .bind(self.id)
+            .execute(&mut *transaction)
+            .await?;
+        replace_lines(&mut transaction, "order_template_next_items", self.id, &[]).await?;
+        transaction.commit().await?;
+
+        Ok(Some(next_run_date))
+    }
+
+    /// Creates the Pending orders of every active template whose next date has come.
+    /// Returns how many orders were created.
+    pub async fn generate_due(pool: &MySqlPool) -> anyhow::Result<usize> {
+        let due: Vec<u64> = sqlx::query_scalar(
+            r#"
+            SELECT `id` FROM `order_templates`
+            WHERE `is_active` = TRUE AND `frequency` IS NOT NULL
+              AND `next_run_date` IS NOT NULL AND `next_run_date` <= ?
+            "#,
+        )
+        .bind(today())
+        .fetch_all(pool)
+        .await?;
+
+        let mut created = 0;
+        for template_id in due {
+            // One failing template must not hold back the others
+            match Self::generate_occurrence(pool, template_id).await {
+                Ok(Some(_)) => created += 1,
+                Ok(None) => {}
+                Err(e) => log::error!("Failed to generate order from template {}: {}", template_id, e),
+            }
+        }
+        Ok(created)
+    }
+
+    /// Creates the order for a due template and schedules its next occurrence.
+    ///
+    /// The template row stays locked for the whole transaction, so a second server running the
+    /// same task skips it once the date has moved on.
+    async fn generate_occurrence(pool: &MySqlPool, template_id: u64) -> anyhow::Result<Option<u64>> {
+        let mut transaction = pool.begin().await?;
+
+        let template = sqlx::query_as::<_, Self>(
+            "SELECT * FROM `order_templates` WHERE `id` = ? FOR UPDATE",
+        )
+        .bind(template_id)
+        .fetch_optional(&mut *transaction)
+        .await?;
+        let Some(template) = template else {
+            return Ok(None);
+        };
+        let today = today();
+        let (Some(frequency), Some(scheduled), true) =
+            (template.frequency, template.next_run_date, template.is_active)
+        else {
+            return Ok(None);
+        };
+        if scheduled > today {
+            return Ok(None);
+        }
+
+        // An edited next occurrence replaces the template's lines this once
+        let edited: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM `order_template_next_items` WHERE `template_id` = ?",
+        )
+        .bind(template_id)
+        .fetch_one(&mut *transaction)
+        .await?;
+        let table = if edited > 0 {
+            "order_template_next_items"
+        } else {
+            "order_template_items"
+        };
+        let items: Vec<(u64, i32)> = sqlx::query_as(&format!(
+            r#"
+            SELECT ti.product_id, ti.quantity
+            FROM `{}` ti
+            JOIN `products` p ON ti.product_id = p.id
+            WHERE ti.template_id = ? AND p.is_active = TRUE AND (p.in_stock = TRUE OR p.allow_backorder = TRUE)
+            "#,
+            table
+        ))
+        .bind(template_id)
+        .fetch_all(&mut *transaction)
+        .await?;
+
+        // Why the occurrence was skipped, kept on the template so the store can see it
+        let mut skipped: Option<String> = None;
+        let order_id = if items.is_empty() {
+            log::warn!(
+                "Template {} had nothing orderable on {}, no order created",
+                template_id,
+                scheduled
+            );
+            skipped = Some(format!("Nothing on the template could be ordered on {}", scheduled));
+            None
+        } else {
+            let user_context = UserContext::from_claims(
+                template.created_by,
+                Some(template.store_id),
+                "store".to_string(),
+            );
+            let notes = match template.notes.as_deref().filter(|n| !n.is_empty()) {
+                Some(notes) => format!("From template \"{}\": {}", template.name, notes),
+                None => format!("From template \"{}\"", template.name),
+            };
+            match StoreOrderRecord::create_order_with_transaction(
+                &mut transaction,
+                &user_context,
+                template.store_id,
+                &items,
+                Some(&notes),
+            )
+            .await?
+            {
+                OrderCreateResult::Created { order_id, .. } => Some(order_id),
+                OrderCreateResult::OverBudget(_) => {
+                    log::warn!(
+                        "Template {} would exceed the budget of store {} on {}, no order created",
+                        template_id,
+                        template.store_id,
+                        scheduled
+                    );
+                    skipped = Some(format!("The order for {} would exceed the store's budget", scheduled));
+                    None
+                }
+                OrderCreateResult::Rejected(issues) => {
+                    let messages: Vec<&str> = issues.iter().map(|i| i.message.as_str()).collect();
+                    log::warn!(
+                        "Template {} could not be filled from stock on {}, no order created: {}",
+                        template_id,
+                        scheduled,
+                        messages.join("; ")
+                    );
+                    skipped = Some(format!(
+                        "The order for {} could not be filled from stock: {}",
+                        scheduled,
+                        messages.join("; ")
+                    ));
+                    None
+                }
+            }
+        };
+
+        sqlx::query(
+            r#"
+            UPDATE `order_templates`
+            SET `next_run_date` = ?,
+                `last_order_id` = COALESCE(?, `last_order_id`),
+                `last_generated_at` = NOW(),
+                `last_error` = ?,
+                `last_skipped_at` = IF(? IS NULL, `last_skipped_at`, NOW())
+            WHERE `id` = ?
+            "#,
+        )
+        .bind(frequency.next_after(scheduled, today))
+        .bind(order_id)
+        .bind(&skipped)
+        .bind(&skipped)
+        .bind(template_id)
+        .execute(&mut *transaction)
+        .await?;
+        replace_lines(&mut transaction, "order_template_next_items", template_id, &[]).await?;
+
+        transaction.commit().await?;
+        if let Some(order_id) = order_id {
+            LowStockItem::notify_crossed_by_order(pool, order_id);
+        }
+        Ok(order_id)
+    }
+
+    /// Orders the background task will create up to and including `until`, soonest first.
+    pub async fn get_upcoming(
+        pool: &MySqlPool,
+        until: NaiveDate,
+    ) -> anyhow::Result<Vec<UpcomingTemplateOrder>> {
+        let templates = sqlx::query_as::<_, Self>(
+            r#"
+            SELECT * FROM `order_templates`
+            WHERE `is_active` = TRUE AND `frequency` IS NOT NULL
+              AND `next_run_date` IS NOT NULL AND `next_run_date` <= ?
+            "#,
+        )
+        .bind(until)
+        .fetch_all(pool)
+        .await?;
+
+        let stores: HashMap<u64, StoreRecord> = StoreRecord::get_all(pool)
+            .await?
+            .into_iter()
+            .map(|s| (s.id, s))
+            .collect();
+
+        let mut upcoming = Vec::new();
+        for template in templates {
+            let (Some(frequency), Some(mut date)) = (template.frequency, template.next_run_date)
+            else {
+                continue;
+            };
+            let store_name = stores
+                .get(&template.store_id)
+                .map(|s| s.display_name())
+                .unwrap_or_default();
+
+            // Only the first occurrence can have its own lines
+            let (mut items, edited) = Self::get_next_items(pool, template.id).await?;
+            let regular_items = if edited {
+                Self::get_items(pool, template.id).await?
+            } else {
+                items.clone()
+            };
+            loop {
+                let orderable: Vec<_> = items.iter().filter(|i| i.is_orderable()).collect();
+                let estimated_total: Decimal = orderable
+                    .iter()
+                    .map(|i| i.current_price * Decimal::from(i.quantity))
+                    .sum();
+                upcoming.push(UpcomingTemplateOrder {
+                    date,
+                    template_id: serde_hash::hashids::encode_single(template.id),
+                    template_name: template.name.clone(),
+                    store_id: serde_hash::hashids::encode_single(template.store_id),
+                    store_name: store_name.clone(),
+                    line_count: orderable.len(),
+                    estimated_total: estimated_total.to_f64().unwrap_or(0.0),
+                });
+
+                date = frequency.following(date);
+                if date > until {
+                    break;
+                }
+                items = regular_items.clone();
+            }
+        }
+
+        upcoming.sort_by(|a, b| a.date.cmp(&b.date).then_with(|| a.store_name.cmp(&b.store_name)));
+        Ok(upcoming)
+    }
+}
+
+/// Template lines joined with live product data, from either line table
+fn items_query(table: &str) -> String {
+    format!(
+        r#"
+        SELECT ti.product_id, ti.quantity,
+               p.name AS product_name,
+               p.sku AS product_sku,
+               p.price AS current_price,
+               p.is_active, p.in_stock
+        FROM `{}` ti
+        JOIN `products` p ON ti.product_id = p.id
+        WHERE ti.template_id = ?
+        ORDER BY p.name ASC
+        "#,
+        table
+    )
+}
+
+/// Replaces every line of a template in `table` (`order_template_items` or
+/// `order_template_next_items`) with `items`
+async fn replace_lines(
+    transaction: &mut Transaction<'_, MySql>,
+    table: &str,
+    template_id: u64,
+    items: &[(u64, i32)],
+) -> anyhow::Result<()> {
+    sqlx::query(&format!("DELETE FROM `{}` WHERE `template_id` = ?", table))
+        .bind(template_id)
+        .execute(&mut **transaction)
+        .await?;
+
+    for (product_id, quantity) in items {
+        sqlx::query(&format!(
+            "INSERT INTO `{}` (`template_id`, `product_id`, `quantity`) VALUES (?, ?, ?)",
+            table
+        ))
+        .bind(template_id)
+        .bind(product_id)
+        .bind(quantity)
+        .execute(&mut **transaction)
+        .await?;
+    }
+    Ok(())
+}
@@ -0,0 +1,397 @@
+use crate::auth::{ClaimsExtractor, UserRole};
+use crate::orders::order_template_data::{
+    EditNextOccurrenceRequest, NextOccurrencePreview, OrderTemplateDraft, OrderTemplateItemDto,
+    OrderTemplateRecord, OrderTemplateWithItemsDto, SaveOrderTemplateRequest, TemplateItemRequest,
+    UpcomingTemplateOrdersQuery,
+};
+use crate::products::ProductRecord;
+use crate::stores::stores_data::StoreRecord;
+use actix_web::{delete, get, post, put, web, HttpRequest, HttpResponse, Responder};
+use database_common_lib::{database_connection::DatabaseConnectionData, http_error::Result};
+use serde_json::json;
+use sqlx::MySqlPool;
+use std::collections::HashSet;
+
+/// Furthest ahead the upcoming-orders view will project
+const MAX_UPCOMING_DAYS: u32 = 90;
+
+/// Loads a template the caller may manage: store users only see their own store's templates.
+async fn load_template(
+    req: &HttpRequest,
+    pool: &MySqlPool,
+    id: &str,
+) -> Result<std::result::Result<OrderTemplateRecord, HttpResponse>> {
+    let claims = req
+        .get_claims()
+        .ok_or_else(|| anyhow::anyhow!("Authentication required"))?;
+    let role = UserRole::from_str(&claims.role)?;
+    let template_id = serde_hash::hashids::decode_single(id)?;
+
+    let Some(template) = OrderTemplateRecord::get_by_id(pool, template_id).await? else {
+        return Ok(Err(HttpResponse::NotFound().json(json!({
+            "success": false,
+            "error": "Order template not found"
+        }))));
+    };
+    if matches!(role, UserRole::Store) && claims.store_id != Some(template.store_id) {
+        return Ok(Err(HttpResponse::Forbidden().json(json!({
+            "success": false,
+            "error": "Access denied: You can only manage order templates for your store"
+        }))));
+    }
+    Ok(Ok(template))
+}
+
+/// Decodes and checks template lines, collecting every problem into `errors`
+async fn validate_items(
+    pool: &MySqlPool,
+    items: &[TemplateItemRequest],
+    errors: &mut Vec<String>,
+) -> Result<Vec<(u64, i32)>> {
+    if items.is_empty() {
+        errors.push("At least one item is required".to_string());
+    }
+
+    let mut seen = HashSet::new();
+    let mut lines = Vec::with_capacity(items.len());
+    for item in items {
+        let product_id = serde_hash::hashids::decode_single(&item.product_id)?;
+        if item.quantity <= 0 {
+            errors.push(format!("Quantity for product {} must be greater than zero", item.product_id));
+        }
+        if !seen.insert(product_id) {
+            errors.push(format!("Product {} is listed more than once", item.product_id));
+        }
+        if ProductRecord::get_by_id_simple(pool, product_id).await?.is_none() {
+            errors.push(format!("Product {} does not exist", item.product_id));
+        }
+        lines.push((product_id, item.quantity));
+    }
+    Ok(lines)
+}
+
+async fn validate_draft(
+    pool: &MySqlPool,
+    request: &SaveOrderTemplateRequest,
+) -> Result<std::result::Result<OrderTemplateDraft, Vec<String>>> {
+    let mut errors = Vec::new();
+    let name = request.name.trim();
+    if name.is_empty() {
+        errors.push("Name is required".to_string());
+    }
+    if let Some(schedule) = &request.schedule {
+        if schedule.weekday > 6 {
+            errors.push("Weekday must be between 0 (Monday) and 6 (Sunday)".to_string());
+        }
+    }
+    let items = validate_items(pool, &request.items, &mut errors).await?;
+    if !errors.is_empty() {
+        return Ok(Err(errors));
+    }
+
+    Ok(Ok(OrderTemplateDraft {
+        name: name.to_string(),
+        notes: request.notes.clone(),
+        items,
+        schedule: request.schedule.as_ref().map(|s| (s.frequency, s.weekday)),
+        is_active: request.is_active,
+    }))
+}
+
+fn invalid_template(errors: Vec<String>) -> HttpResponse {
+    HttpResponse::BadRequest().json(json!({
+        "success": false,
+        "error": "Invalid order template",
+        "details": errors
+    }))
+}
+
+async fn template_with_items(
+    pool: &MySqlPool,
+    template: OrderTemplateRecord,
+) -> Result<OrderTemplateWithItemsDto> {
+    let items = OrderTemplateRecord::get_items(pool, template.id).await?;
+    let (_, next_occurrence_edited) = OrderTemplateRecord::get_next_items(pool, template.id).await?;
+    Ok(OrderTemplateWithItemsDto {
+        template,
+        items: items.iter().map(OrderTemplateItemDto::from).collect(),
+        next_occurrence_edited,
+    })
+}
+
+async fn next_occurrence_preview(
+    pool: &MySqlPool,
+    template: &OrderTemplateRecord,
+) -> Result<NextOccurrencePreview> {
+    let (items, edited) = OrderTemplateRecord::get_next_items(pool, template.id).await?;
+    let store_name = StoreRecord::get_by_id(pool, template.store_id)
+        .await?
+        .map(|s| s.display_name())
+        .unwrap_or_default();
+    Ok(NextOccurrencePreview::build(template, store_name, &items, edited))
+}
+
+#[get("/templates")]
+pub async fn get_templates(
+    req: HttpRequest,
+    connection_data: web::Data<DatabaseConnectionData>,
+) -> Result<impl Responder> {
+    let pool = connection_data.get_pool().await?;
+    let claims = req
+        .get_claims()
+        .ok_or_else(|| anyhow::anyhow!("Authentication required"))?;
+
+    let templates = if claims.role == "admin" {
+        OrderTemplateRecord::get_all(&pool, None).await?
+    } else if let Some(store_id) = claims.store_id {
+        OrderTemplateRecord::get_all(&pool, Some(store_id)).await?
+    } else {
+        return Ok(HttpResponse::Forbidden().json(json!({
+            "success": false,
+            "error": "Access denied: You can only view order templates for your store"
+        })));
+    };
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "data": templates
+    })))
+}
+
+#[get("/templates/upcoming")]
+pub async fn get_upcoming_template_orders(
+    req: HttpRequest,
+    connection_data: web::Data<DatabaseConnectionData>,
+    query: web::Query<UpcomingTemplateOrdersQuery>,
+) -> Result<impl Responder> {
+    let claims = req
+        .get_claims()
+        .ok_or_else(|| anyhow::anyhow!("Authentication required"))?;
+    if claims.role != "admin" {
+        return Ok(HttpResponse::Forbidden().json(json!({
+            "success": false,
+            "error": "Admin access required"
+        })));
+    }
+    let pool = connection_data.get_pool().await?;
+
+    let days = query.days.unwrap_or(14).min(MAX_UPCOMING_DAYS);
+    let until = chrono::Local::now().date_naive() + chrono::Days::new(days as u64);
+    let upcoming = OrderTemplateRecord::get_upcoming(&pool, until).await?;
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "data": upcoming
+    })))
+}
+
+#[get("/templates/{id}")]
+pub async fn get_template(
+    req: HttpRequest,
+    connection_data: web::Data<DatabaseConnectionData>,
+    path: web::Path<String>,
+) -> Result<impl Responder> {
+    let pool = connection_data.get_pool().await?;
+    let template = match load_template(&req, &pool, path.as_str()).await? {
+        Ok(template) => template,
+        Err(response) => return Ok(response),
+    };
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "data": template_with_items(&pool, template).await?
+    })))
+}
+
+#[post("/templates")]
+pub async fn create_template(
+    req: HttpRequest,
+    connection_data: web::Data<DatabaseConnectionData>,
+    request: web::Json<SaveOrderTemplateRequest>,
+) -> Result<impl Responder> {
+    let pool = connection_data.get_pool().await?;
+    let claims = req
+        .get_claims()
+        .ok_or_else(|| anyhow::anyhow!("Authentication required"))?;
+
+    let requested_store_id = match &request.store_id {
+        Some(hash) => Some(serde_hash::hashids::decode_single(hash)?),
+        None => None,
+    };
+
+    // Store users always save templates for their own store; admins must name one
+    let role = UserRole::from_str(&claims.role)?;
+    let store_id = match role {
+        UserRole::Store => {
+            if requested_store_id.is_some() && requested_store_id != claims.store_id {
+                return Ok(HttpResponse::Forbidden().json(json!({
+                    "success": false,
+                    "error": "Access denied: You can only manage order templates for your store"
+                })));
+            }
+            claims.store_id
+        }
+        UserRole::Admin => requested_store_id,
+    };
+    let Some(store_id) = store_id else {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "success": false,
+            "error": "A store is required for an order template"
+        })));
+    };
+
+    let draft = match validate_draft(&pool, &request).await? {
+        Ok(draft) => draft,
+        Err(errors) => return Ok(invalid_template(errors)),
+    };
+
+    let template_id = OrderTemplateRecord::create(&pool, store_id, claims.sub, &draft).await?;
+    let template = OrderTemplateRecord::get_by_id(&pool, template_id)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("Failed to retrieve created order template"))?;
+    Ok(HttpResponse::Created().json(json!({
+        "success": true,
+        "data": template_with_items(&pool, template).await?,
+        "message": "Order template created successfully"
+    })))
+}
+
+#[put("/templates/{id}")]
+pub async fn update_template(
+    req: HttpRequest,
+    connection_data: web::Data<DatabaseConnectionData>,
+    path: web::Path<String>,
+    request: web::Json<SaveOrderTemplateRequest>,
+) -> Result<impl Responder> {
+    let pool = connection_data.get_pool().await?;
+    let template = match load_template(&req, &pool, path.as_str()).await? {
+        Ok(template) => template,
+        Err(response) => return Ok(response),
+    };
+
+    let draft = match validate_draft(&pool, &request).await? {
+        Ok(draft) => draft,
+        Err(errors) => return Ok(invalid_template(errors)),
+    };
+
+    template.update(&pool, &draft).await?;
+    let template = OrderTemplateRecord::get_by_id(&pool, template.id)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("Order template not found after update"))?;
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "data": template_with_items(&pool, template).await?,
+        "message": "Order template updated successfully"
+    })))
+}
+
+#[delete("/templates/{id}")]
+pub async fn delete_template(
+    req: HttpRequest,
+    connection_data: web::Data<DatabaseConnectionData>,
+    path: web::Path<String>,
+) -> Result<impl Responder> {
+    let pool = connection_data.get_pool().await?;
+    let template = match load_template(&req, &pool, path.as_str()).await? {
+        Ok(template) => template,
+        Err(response) => return Ok(response),
+    };
+
+    OrderTemplateRecord::delete(&pool, template.id).await?;
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "message": "Order template deleted successfully"
+    })))
+}
+
+#[get("/templates/{id}/next")]
+pub async fn get_next_occurrence(
+    req: HttpRequest,
+    connection_data: web::Data<DatabaseConnectionData>,
+    path: web::Path<String>,
+) -> Result<impl Responder> {
+    let pool = connection_data.get_pool().await?;
+    let template = match load_template(&req, &pool, path.as_str()).await? {
+        Ok(template) => template,
+        Err(response) => return Ok(response),
+    };
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "data": next_occurrence_preview(&pool, &template).await?
+    })))
+}
+
+#[put("/templates/{id}/next")]
+pub async fn edit_next_occurrence(
+    req: HttpRequest,
+    connection_data: web::Data<DatabaseConnectionData>,
+    path: web::Path<String>,
+    request: web::Json<EditNextOccurrenceRequest>,
+) -> Result<impl Responder> {
+    let pool = connection_data.get_pool().await?;
+    let template = match load_template(&req, &pool, path.as_str()).await? {
+        Ok(template) => template,
+        Err(response) => return Ok(response),
+    };
+
+    if template.frequency.is_none() {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "success": false,
+            "error": "Order template has no schedule"
+        })));
+    }
+
+    let mut errors = Vec::new();
+    if let Some(date) = request.date {
+        if date < chrono::Local::now().date_naive() {
+            errors.push("The next occurrence cannot be moved into the past".to_string());
+        }
+    }
+    let items = match &request.items {
+        Some(items) => Some(validate_items(&pool, items, &mut errors).await?),
+        None => None,
+    };
+    if !errors.is_empty() {
+        return Ok(invalid_template(errors));
+    }
+
+    template
+        .edit_next_occurrence(&pool, request.date, items.as_deref())
+        .await?;
+    let template = OrderTemplateRecord::get_by_id(&pool, template.id)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("Order template not found after update"))?;
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "data": next_occurrence_preview(&pool, &template).await?,
+        "message": "Next occurrence updated"
+    })))
+}
+
+#[post("/templates/{id}/skip")]
+pub async fn skip_next_occurrence(
+    req: HttpRequest,
+    connection_data: web::Data<DatabaseConnectionData>,
+    path: web::Path<String>,
+) -> Result<impl Responder> {
+    let pool = connection_data.get_pool().await?;
+    let template = match load_template(&req, &pool, path.as_str()).await? {
+        Ok(template) => template,
+        Err(response) => return Ok(response),
+    };
+
+    let Some(next_run_date) = template.skip_next_occurrence(&pool).await? else {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "success": false,
+            "error": "Order template has no schedule"
+        })));
+    };
+    let template = OrderTemplateRecord::get_by_id(&pool, template.id)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("Order template not found after update"))?;
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "data": next_occurrence_preview(&pool, &template).await?,
+        "message": format!("Next occurrence skipped, next order on {}", next_run_date)
+    })))
+}
@@ -1,3 +1,4 @@
+use crate::orders::budget_data::BudgetCheck;
 use crate::orders::store_order_status::StoreOrderStatus;
 use rust_decimal::Decimal;
 use rust_decimal::prelude::ToPrimitive;
@@ -5,6 +6,7 @@ use serde::{Deserialize, Serialize};
 use serde_hash::HashIds;
 use sqlx::{FromRow, Row};
 use sqlx::mysql::MySqlRow;
+use std::collections::HashMap;
 
 #[derive(HashIds, Debug, Clone)]
 pub struct StoreOrderRecord {
@@ -21,7 +23,15 @@ pub struct StoreOrderRecord {
     pub created_at: chrono::NaiveDateTime,
     pub updated_at: chrono::NaiveDateTime,
     pub status_changed_to_pending: Option<chrono::NaiveDateTime>,
+    pub status_changed_to_shipped: Option<chrono::NaiveDateTime>,
     pub status_changed_to_completed: Option<chrono::NaiveDateTime>,
+    pub cancelled_at: Option<chrono::NaiveDateTime>,
+    #[hash]
+    pub cancelled_by: Option<u64>,
+    pub cancellation_reason: Option<String>,
+    /// Set on follow-up orders created for the backordered remainder of another order
+    #[hash]
+    pub backorder_of_order_id: Option<u64>,
 }
 
 impl<'r> FromRow<'r, MySqlRow> for StoreOrderRecord {
@@ -40,7 +50,61 @@ impl<'r> FromRow<'r, MySqlRow> for StoreOrderRecord {
             created_at: row.try_get("created_at")?,
             updated_at: row.try_get("updated_at")?,
             status_changed_to_pending: row.try_get("status_changed_to_pending")?,
+            status_changed_to_shipped: row.try_get("status_changed_to_shipped")?,
             status_changed_to_completed: row.try_get("status_changed_to_completed")?,
+            cancelled_at: row.try_get("cancelled_at")?,
+            cancelled_by: row.try_get("cancelled_by")?,
+            cancellation_reason: row.try_get("cancellation_reason")?,
+            backorder_of_order_id: row.try_get("backorder_of_order_id")?,
+        })
+    }
+}
+
+impl StoreOrderRecord {
+    /// Response message for a newly placed order, which may be held for approval
+    pub fn created_message(&self) -> &'static str {
+        match self.status {
+            StoreOrderStatus::AwaitingApproval => {
+                "Order created and sent for approval: its total is above the store's approval limit"
+            }
+            _ => "Order created successfully",
+        }
+    }
+}
+
+#[derive(HashIds, Debug, Clone)]
+pub struct OrderStatusHistoryRecord {
+    #[hash]
+    pub id: u64,
+    #[hash]
+    pub order_id: u64,
+    /// `None` for the entry written when the order is created
+    pub previous_status: Option<StoreOrderStatus>,
+    pub new_status: StoreOrderStatus,
+    #[hash]
+    pub changed_by: Option<u64>,
+    pub changed_by_email: Option<String>,
+    pub notes: Option<String>,
+    pub created_at: chrono::NaiveDateTime,
+}
+
+impl<'r> FromRow<'r, MySqlRow> for OrderStatusHistoryRecord {
+    fn from_row(row: &'r MySqlRow) -> Result<Self, sqlx::Error> {
+        let parse_status = |raw: &str| {
+            StoreOrderStatus::from_str_case_insensitive(raw)
+                .ok_or_else(|| sqlx::Error::Protocol(format!("Unexpected status value '{}'", raw)))
+        };
+        let previous_raw: Option<String> = row.try_get("previous_status")?;
+        let new_raw: String = row.try_get("new_status")?;
+        Ok(Self {
+            id: row.try_get("id")?,
+            order_id: row.try_get("order_id")?,
+            previous_status: previous_raw.as_deref().map(parse_status).transpose()?,
+            new_status: parse_status(&new_raw)?,
+            changed_by: row.try_get("changed_by")?,
+            changed_by_email: row.try_get("changed_by_email")?,
+            notes: row.try_get("notes")?,
+            created_at: row.try_get("created_at")?,
         })
     }
 }
@@ -52,11 +116,21 @@ pub struct OrderItemRecord {
     pub order_id: u64,
     pub product_id: u64,
     pub quantity: i32,
+    pub quantity_shipped: i32,
+    /// Unshipped quantity the warehouse will no longer ship on this order
+    pub quantity_backordered: i32,
     pub unit_price: Decimal,
     pub total_price: Decimal,
     pub created_at: chrono::NaiveDateTime,
 }
 
+impl OrderItemRecord {
+    /// Quantity still expected to ship on this order
+    pub fn quantity_outstanding(&self) -> i32 {
+        (self.quantity - self.quantity_shipped - self.quantity_backordered).max(0)
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct OrderWithItems {
     #[serde(flatten)]
@@ -74,6 +148,11 @@ pub struct OrderItemWithProduct {
     pub category_name: String,
     pub product_bin_location: String,
     pub product_unit_type: i32,
+    /// Live product state, used when the order is copied into a new one
+    pub current_price: Decimal,
+    pub stock_quantity: f32,
+    pub is_active: bool,
+    pub in_stock: bool,
 }
 
 // DTOs for API (convert Decimal -> f64)
@@ -91,7 +170,14 @@ pub struct StoreOrderRecordDto {
     pub created_at: chrono::NaiveDateTime,
     pub updated_at: chrono::NaiveDateTime,
     pub status_changed_to_pending: Option<chrono::NaiveDateTime>,
+    pub status_changed_to_shipped: Option<chrono::NaiveDateTime>,
     pub status_changed_to_completed: Option<chrono::NaiveDateTime>,
+    pub cancelled_at: Option<chrono::NaiveDateTime>,
+    #[hash]
+    pub cancelled_by: Option<u64>,
+    pub cancellation_reason: Option<String>,
+    #[hash]
+    pub backorder_of_order_id: Option<u64>,
 }
 
 impl From<&StoreOrderRecord> for StoreOrderRecordDto {
@@ -107,7 +193,12 @@ impl From<&StoreOrderRecord> for StoreOrderRecordDto {
             created_at: r.created_at,
             updated_at: r.updated_at,
             status_changed_to_pending: r.status_changed_to_pending,
+            status_changed_to_shipped: r.status_changed_to_shipped,
             status_changed_to_completed: r.status_changed_to_completed,
+            cancelled_at: r.cancelled_at,
+            cancelled_by: r.cancelled_by,
+            cancellation_reason: r.cancellation_reason.clone(),
+            backorder_of_order_id: r.backorder_of_order_id,
         }
     }
 }
@@ -118,6 +209,9 @@ pub struct OrderItemRecordDto {
     pub order_id: String,
     pub product_id: String,
     pub quantity: i32,
+    pub quantity_shipped: i32,
+    pub quantity_backordered: i32,
+    pub quantity_outstanding: i32,
     pub unit_price: f64,
     pub total_price: f64,
     pub created_at: chrono::NaiveDateTime,
@@ -130,6 +224,9 @@ impl From<&OrderItemRecord> for OrderItemRecordDto {
             order_id: serde_hash::hashids::encode_single(r.order_id),
             product_id: serde_hash::hashids::encode_single(r.product_id),
             quantity: r.quantity,
+            quantity_shipped: r.quantity_shipped,
+            quantity_backordered: r.quantity_backordered,
+            quantity_outstanding: r.quantity_outstanding(),
             unit_price: r.unit_price.to_f64().unwrap_or(0.0),
             total_price: r.total_price.to_f64().unwrap_or(0.0),
             created_at: r.created_at,
@@ -192,6 +289,277 @@ pub struct CreateOrderItemRequest {
     pub quantity: i32,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdateOrderItemRequest {
+    pub quantity: i32,
+}
+
+/// Outcome of editing the lines of a pending order
+#[derive(Debug, Clone, PartialEq)]
+pub enum OrderEditResult {
+    Updated,
+    OrderNotFound,
+    /// The order has moved past Pending and its lines are locked
+    NotEditable(StoreOrderStatus),
+    ItemNotFound,
+    /// The extra quantity cannot be filled; nothing was changed
+    Rejected(Vec<StockIssue>),
+    /// The extra quantity would exceed a budget that blocks overspending; nothing was changed
+    OverBudget(BudgetCheck),
+}
+
+/// Outcome of moving an order to a new status
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StatusChangeResult {
+    /// Carries the status the order had when its row was locked
+    Changed(StoreOrderStatus),
+    OrderNotFound,
+    /// The order moved to this status since it was read and the change is no longer allowed
+    NotAllowed(StoreOrderStatus),
+}
+
+/// Outcome of creating an order
+#[derive(Debug, Clone, PartialEq)]
+pub enum OrderCreateResult {
+    /// `budget` is how the order fits in the store's budget, `None` when the store has none
+    Created { order_id: u64, budget: Option<BudgetCheck> },
+    /// Some lines cannot be filled; nothing was created
+    Rejected(Vec<StockIssue>),
+    /// The order would exceed a budget that blocks overspending; nothing was created
+    OverBudget(BudgetCheck),
+}
+
+/// Outcome of creating an order under a client-supplied idempotency key
+#[derive(Debug, Clone, PartialEq)]
+pub enum IdempotentOrderResult {
+    Created { order_id: u64, budget: Option<BudgetCheck> },
+    /// The key was already used within the replay window; this is the order it created
+    Replayed(u64),
+    Rejected(Vec<StockIssue>),
+    OverBudget(BudgetCheck),
+}
+
+/// Stock state of a product, read with its row locked while an order is created
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProductAvailability {
+    pub sku: String,
+    pub stock_quantity: f32,
+    pub is_active: bool,
+    pub allow_backorder: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StockIssueReason {
+    NotFound,
+    Inactive,
+    InsufficientStock,
+}
+
+/// Why one product of a new order cannot be filled
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StockIssue {
+    pub product_id: String,
+    pub sku: Option<String>,
+    pub reason: StockIssueReason,
+    /// Total ordered across the order's lines for this product
+    pub requested: i32,
+    pub available: f32,
+    pub message: String,
+}
+
+impl StockIssue {
+    /// Checks every product of an order against its stock, one issue per product that cannot
+    /// be filled. Products that allow backorders may be ordered beyond what is in stock.
+    pub fn find_all(
+        items: &[(u64, i32)], // (product_id, quantity)
+        products: &HashMap<u64, ProductAvailability>,
+    ) -> Vec<Self> {
+        let mut requested: Vec<(u64, i32)> = Vec::new();
+        for (product_id, quantity) in items {
+            match requested.iter_mut().find(|(id, _)| id == product_id) {
+                Some((_, total)) => *total += quantity,
+                None => requested.push((*product_id, *quantity)),
+            }
+        }
+
+        requested
+            .into_iter()
+            .filter_map(|(product_id, requested)| {
+                let product = products.get(&product_id);
+                let reason = match product {
+                    None => StockIssueReason::NotFound,
+                    Some(p) if !p.is_active => StockIssueReason::Inactive,
+                    Some(p) if !p.allow_backorder && requested as f32 > p.stock_quantity => {
+                        StockIssueReason::InsufficientStock
+                    }
+                    Some(_) => return None,
+                };
+                let product_id = serde_hash::hashids::encode_single(product_id);
+                let label = product.map_or_else(|| product_id.clone(), |p| p.sku.clone());
+                let available = product.map_or(0.0, |p| p.stock_quantity.max(0.0));
+                let message = match reason {
+                    StockIssueReason::NotFound => format!("Product {} does not exist", label),
+                    StockIssueReason::Inactive => format!("{} is no longer available", label),
+                    StockIssueReason::InsufficientStock => format!(
+                        "{}: {} requested but only {} in stock",
+                        label, requested, available
+                    ),
+                };
+                Some(Self {
+                    product_id,
+                    sku: product.map(|p| p.sku.clone()),
+                    reason,
+                    requested,
+                    available,
+                    message,
+                })
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RecordShipmentRequest {
+    pub items: Vec<ShipmentLineRequest>,
+    /// Mark whatever is still unshipped on the order as backordered
+    #[serde(default)]
+    pub backorder_remaining: bool,
+    /// Also open a follow-up Pending order for the backordered quantities
+    #[serde(default)]
+    pub create_backorder_order: bool,
+    pub notes: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ShipmentLineRequest {
+    pub item_id: String, // hashed ID
+    /// Quantity shipped in this shipment, added to what already shipped
+    pub quantity: i32,
+}
+
+/// Outcome of recording a shipment against an order
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ShipmentResult {
+    Recorded { backorder_order_id: Option<u64> },
+    OrderNotFound,
+    /// The order is on hold, delivered or cancelled
+    NotShippable(StoreOrderStatus),
+    /// Per-line validation errors; nothing was recorded
+    InvalidLines(Vec<String>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReorderTarget {
+    /// Place a new Pending order right away
+    #[default]
+    Order,
+    /// Add the lines to the caller's cart for review
+    Cart,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ReorderRequest {
+    #[serde(default)]
+    pub target: ReorderTarget,
+    pub notes: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReorderAdjustmentReason {
+    Inactive,
+    OutOfStock,
+    /// Less stock is on hand than the original order asked for
+    QuantityReduced,
+    PriceChanged,
+}
+
+/// A line of the original order that could not be copied as it was
+#[derive(Debug, Serialize)]
+pub struct ReorderLineAdjustment {
+    pub product_id: String,
+    pub product_name: String,
+    pub product_sku: String,
+    pub reasons: Vec<ReorderAdjustmentReason>,
+    pub previous_quantity: i32,
+    pub quantity: i32,
+    pub previous_price: f64,
+    pub current_price: f64,
+    /// Whether the line was left out of the new order
+    pub removed: bool,
+}
+
+/// Lines to copy into the new order or cart, at current prices
+#[derive(Debug, Default)]
+pub struct ReorderPlan {
+    pub lines: Vec<(u64, i32, Decimal)>, // (product_id, quantity, unit_price)
+    pub adjustments: Vec<ReorderLineAdjustment>,
+}
+
+impl ReorderPlan {
+    /// Drops inactive and out-of-stock products, caps quantities at the stock on hand and
+    /// reprices at the current product price, recording every line that changed.
+    pub fn build(items: &[OrderItemWithProduct]) -> Self {
+        let mut plan = Self::default();
+        for line in items {
+            let mut reasons = Vec::new();
+            let mut quantity = line.item.quantity;
+            if !line.is_active {
+                reasons.push(ReorderAdjustmentReason::Inactive);
+                quantity = 0;
+            } else if !line.in_stock || line.stock_quantity < 1.0 {
+                reasons.push(ReorderAdjustmentReason::OutOfStock);
+                quantity = 0;
+            } else if quantity as f32 > line.stock_quantity {
+                // Stock is tracked as a float; only whole units can be ordered
+                reasons.push(ReorderAdjustmentReason::QuantityReduced);
+                quantity = line.stock_quantity.floor() as i32;
+            }
+            if quantity > 0 && line.current_price != line.item.unit_price {
+                reasons.push(ReorderAdjustmentReason::PriceChanged);
+            }
+
+            if quantity > 0 {
+                plan.lines.push((line.item.product_id, quantity, line.current_price));
+            }
+            if !reasons.is_empty() {
+                plan.adjustments.push(ReorderLineAdjustment {
+                    product_id: serde_hash::hashids::encode_single(line.item.product_id),
+                    product_name: line.product_name.clone(),
+                    product_sku: line.product_sku.clone(),
+                    reasons,
+                    previous_quantity: line.item.quantity,
+                    quantity,
+                    previous_price: line.item.unit_price.to_f64().unwrap_or(0.0),
+                    current_price: line.current_price.to_f64().unwrap_or(0.0),
+                    removed: quantity == 0,
+                });
+            }
+        }
+        plan
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OrderIdListQuery {
+    /// Comma separated hashed order ids
+    pub orders: String,
+}
+
+/// Decodes a comma separated list of hashed order ids, dropping blanks and duplicates
+pub fn decode_order_id_list(orders: &str) -> anyhow::Result<Vec<u64>> {
+    let mut order_ids = Vec::new();
+    for hash in orders.split(',').map(str::trim).filter(|h| !h.is_empty()) {
+        let order_id = serde_hash::hashids::decode_single(hash)?;
+        if !order_ids.contains(&order_id) {
+            order_ids.push(order_id);
+        }
+    }
+    Ok(order_ids)
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct AddToCartRequest {
     pub product_id: String, // hashed ID
@@ -202,6 +570,8 @@ pub struct AddToCartRequest {
 pub struct UpdateOrderStatusRequest {
     pub status: StoreOrderStatus,
     pub notes: Option<String>,
+    /// Required when cancelling an order
+    pub reason: Option<String>,
 }
 
 // Stub UserContext for authentication
@@ -230,4 +600,97 @@ impl UserContext {
             role: "store".to_string(),
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(
+        quantity: i32,
+        unit_price: i64,
+        current_price: i64,
+        stock: f32,
+        is_active: bool,
+    ) -> OrderItemWithProduct {
+        OrderItemWithProduct {
+            item: OrderItemRecord {
+                id: 1,
+                order_id: 1,
+                product_id: 7,
+                quantity,
+                quantity_shipped: 0,
+                quantity_backordered: 0,
+                unit_price: Decimal::from(unit_price),
+                total_price: Decimal::from(unit_price * quantity as i64),
+                created_at: chrono::NaiveDateTime::default(),
+            },
+            product_name: "Paper Towels".to_string(),
+            product_sku: "PT-1".to_string(),
+            product_image_url: None,
+            category_name: "Janitorial".to_string(),
+            product_bin_location: "A-1".to_string(),
+            product_unit_type: 1,
+            current_price: Decimal::from(current_price),
+            stock_quantity: stock,
+            is_active,
+            in_stock: stock > 0.0,
+        }
+    }
+
+    #[test]
+    fn reorder_plan_copies_unchanged_lines_silently() {
+        let plan = ReorderPlan::build(&[line(4, 10, 10, 50.0, true)]);
+        assert_eq!(plan.lines, vec![(7, 4, Decimal::from(10))]);
+        assert!(plan.adjustments.is_empty());
+    }
+
+    #[test]
+    fn reorder_plan_reports_dropped_capped_and_repriced_lines() {
+        let plan = ReorderPlan::build(&[
+            line(4, 10, 10, 50.0, false),
+            line(4, 10, 10, 0.0, true),
+            line(4, 10, 12, 3.5, true),
+        ]);
+        assert_eq!(plan.lines, vec![(7, 3, Decimal::from(12))]);
+        let reasons: Vec<_> = plan.adjustments.iter().map(|a| a.reasons.clone()).collect();
+        assert_eq!(
+            reasons,
+            vec![
+                vec![ReorderAdjustmentReason::Inactive],
+                vec![ReorderAdjustmentReason::OutOfStock],
+                vec![ReorderAdjustmentReason::QuantityReduced, ReorderAdjustmentReason::PriceChanged],
+            ]
+        );
+        assert!(plan.adjustments[0].removed && plan.adjustments[1].removed);
+        assert!(!plan.adjustments[2].removed);
+    }
+
+    #[test]
+    fn stock_issues_sum_lines_and_honour_backorders() {
+        let availability = |stock_quantity, is_active, allow_backorder| ProductAvailability {
+            sku: "PT-1".to_string(),
+            stock_quantity,
+            is_active,
+            allow_backorder,
+        };
+        let products = HashMap::from([
+            (1, availability(5.0, true, false)),
+            (2, availability(0.0, true, true)),
+            (3, availability(50.0, false, false)),
+        ]);
+
+        assert!(StockIssue::find_all(&[(1, 5), (2, 10)], &products).is_empty());
+
+        let issues = StockIssue::find_all(&[(1, 3), (1, 3), (3, 1), (4, 1)], &products);
+        let reasons: Vec<_> = issues.iter().map(|i| i.reason).collect();
+        assert_eq!(
+            reasons,
+            vec![
+                StockIssueReason::InsufficientStock,
+                StockIssueReason::Inactive,
+                StockIssueReason::NotFound,
+            ]
+        );
+        assert_eq!((issues[0].requested, issues[0].available), (6, 5.0));
+    }
+}
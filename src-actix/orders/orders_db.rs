@@ -1,10 +1,19 @@
+use crate::auth::UserRole;
+use crate::orders::budget_data::BudgetCheck;
 use crate::orders::orders_data::{
-    OrderItemRecord, OrderItemWithProduct, OrderWithItems, StoreOrderRecord, UserContext,
+    IdempotentOrderResult, OrderCreateResult, OrderEditResult, OrderItemRecord, OrderItemWithProduct,
+    OrderStatusHistoryRecord, OrderWithItems, ProductAvailability, ShipmentResult,
+    StatusChangeResult, StockIssue, StoreOrderRecord, UserContext,
 };
+use crate::orders::order_number::OrderNumberFormat;
 use crate::orders::store_order_status::StoreOrderStatus;
+use crate::products::inventory_data::{InventoryMovementRecord, MovementType, StockMovement};
+use crate::products::low_stock_data::LowStockItem;
+use log::*;
 use rust_decimal::prelude::FromPrimitive;
 use rust_decimal::Decimal;
-use sqlx::{Executor, MySqlPool, Row};
+use sqlx::{Executor, MySql, MySqlPool, Row, Transaction};
+use std::collections::HashMap;
 
 // Define a custom struct for the order items with products query
 #[derive(sqlx::FromRow)]
@@ -14,6 +23,8 @@ struct OrderItemWithProductQuery {
     order_id: u64,
     product_id: u64,
     quantity: i32,
+    quantity_shipped: i32,
+    quantity_backordered: i32,
     unit_price: Decimal,
     total_price: Decimal,
     created_at: chrono::NaiveDateTime,
@@ -23,6 +34,10 @@ struct OrderItemWithProductQuery {
     product_image_url: Option<String>,
     product_bin_location: String,
     product_unit_type: i32,
+    current_price: Decimal,
+    stock_quantity: f32,
+    is_active: bool,
+    in_stock: bool,
     // Category fields
     category_name: String,
 }
@@ -35,6 +50,8 @@ impl From<OrderItemWithProductQuery> for OrderItemWithProduct {
                 order_id: query_result.order_id,
                 product_id: query_result.product_id,
                 quantity: query_result.quantity,
+                quantity_shipped: query_result.quantity_shipped,
+                quantity_backordered: query_result.quantity_backordered,
                 unit_price: query_result.unit_price,
                 total_price: query_result.total_price,
                 created_at: query_result.created_at,
@@ -45,33 +62,49 @@ impl From<OrderItemWithProductQuery> for OrderItemWithProduct {
             category_name: query_result.category_name,
             product_bin_location: query_result.product_bin_location,
             product_unit_type: query_result.product_unit_type,
+            current_price: query_result.current_price,
+            stock_quantity: query_result.stock_quantity,
+            is_active: query_result.is_active,
+            in_stock: query_result.in_stock,
         }
     }
 }
 
 pub async fn initialize(pool: &MySqlPool) -> anyhow::Result<()> {
+    let status_values = StoreOrderStatus::sql_enum_values();
+
     // Create orders table
     pool.execute(
-        r#"
+        format!(
+            r#"
         CREATE TABLE IF NOT EXISTS `orders` (
             `id` BIGINT UNSIGNED NOT NULL AUTO_INCREMENT,
             `order_number` VARCHAR(50) NOT NULL UNIQUE,
             `user_id` BIGINT UNSIGNED NOT NULL,
             `store_id` BIGINT UNSIGNED NOT NULL,
-            `status` ENUM('PENDING','SHIPPED','DELIVERED') NOT NULL DEFAULT 'PENDING',
+            `status` ENUM({status_values}) NOT NULL DEFAULT 'PENDING',
             `total_amount` DECIMAL(10,2) NOT NULL DEFAULT 0.00,
             `notes` TEXT,
             `created_at` DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
             `updated_at` DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
             `status_changed_to_pending` DATETIME,
+            `status_changed_to_shipped` DATETIME,
             `status_changed_to_completed` DATETIME,
+            `cancelled_at` DATETIME,
+            `cancelled_by` BIGINT UNSIGNED,
+            `cancellation_reason` TEXT,
+            `backorder_of_order_id` BIGINT UNSIGNED,
+            `idempotency_key` VARCHAR(255),
             PRIMARY KEY (`id`),
+            UNIQUE KEY `uq_user_idempotency_key` (`user_id`, `idempotency_key`),
             INDEX `idx_user_id` (`user_id`),
             INDEX `idx_store_id` (`store_id`),
             INDEX `idx_status` (`status`),
             INDEX `idx_order_number` (`order_number`)
         )
-        "#,
+        "#
+        )
+        .as_str(),
     )
     .await?;
 
@@ -79,8 +112,30 @@ pub async fn initialize(pool: &MySqlPool) -> anyhow::Result<()> {
     pool.execute("UPDATE orders SET status = UPPER(status)")
         .await
         .ok();
-    pool.execute("UPDATE orders SET status = 'PENDING' WHERE status NOT IN ('PENDING','SHIPPED','DELIVERED')").await.ok();
-    pool.execute("ALTER TABLE orders MODIFY COLUMN `status` ENUM('PENDING','SHIPPED','DELIVERED') NOT NULL DEFAULT 'PENDING'").await.ok();
+    pool.execute(format!("UPDATE orders SET status = 'PENDING' WHERE status NOT IN ({status_values})").as_str()).await.ok();
+    pool.execute(format!("ALTER TABLE orders MODIFY COLUMN `status` ENUM({status_values}) NOT NULL DEFAULT 'PENDING'").as_str()).await.ok();
+
+    // Columns added after the initial schema; these fail harmlessly once they exist
+    pool.execute("ALTER TABLE orders ADD COLUMN `status_changed_to_shipped` DATETIME AFTER `status_changed_to_pending`").await.ok();
+    pool.execute("ALTER TABLE orders ADD COLUMN `cancelled_at` DATETIME").await.ok();
+    pool.execute("ALTER TABLE orders ADD COLUMN `cancelled_by` BIGINT UNSIGNED").await.ok();
+    pool.execute("ALTER TABLE orders ADD COLUMN `cancellation_reason` TEXT").await.ok();
+    pool.execute("ALTER TABLE orders ADD COLUMN `backorder_of_order_id` BIGINT UNSIGNED").await.ok();
+    pool.execute("ALTER TABLE orders ADD COLUMN `idempotency_key` VARCHAR(255)").await.ok();
+    pool.execute("ALTER TABLE orders ADD UNIQUE KEY `uq_user_idempotency_key` (`user_id`, `idempotency_key`)").await.ok();
+
+    // Last number handed out per order number prefix (see OrderNumberFormat) and per day of pick waves
+    pool.execute(
+        r#"
+        CREATE TABLE IF NOT EXISTS `order_number_sequences` (
+            `scope` VARCHAR(100) NOT NULL,
+            `last_value` BIGINT UNSIGNED NOT NULL,
+            `updated_at` DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+            PRIMARY KEY (`scope`)
+        )
+        "#,
+    )
+    .await?;
 
     // Create order_items table
     pool.execute(
@@ -90,6 +145,8 @@ pub async fn initialize(pool: &MySqlPool) -> anyhow::Result<()> {
             `order_id` BIGINT UNSIGNED NOT NULL,
             `product_id` BIGINT UNSIGNED NOT NULL,
             `quantity` INT NOT NULL,
+            `quantity_shipped` INT NOT NULL DEFAULT 0,
+            `quantity_backordered` INT NOT NULL DEFAULT 0,
             `unit_price` DECIMAL(10,2) NOT NULL,
             `total_price` DECIMAL(10,2) NOT NULL,
             `created_at` DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
@@ -103,9 +160,35 @@ pub async fn initialize(pool: &MySqlPool) -> anyhow::Result<()> {
     )
     .await?;
 
+    pool.execute("ALTER TABLE order_items ADD COLUMN `quantity_shipped` INT NOT NULL DEFAULT 0 AFTER `quantity`").await.ok();
+    pool.execute("ALTER TABLE order_items ADD COLUMN `quantity_backordered` INT NOT NULL DEFAULT 0 AFTER `quantity_shipped`").await.ok();
+
+    // Audit trail of every status change. Statuses are stored as plain strings so
+    // history rows survive changes to the `orders.status` ENUM.
+    pool.execute(
+        r#"
+        CREATE TABLE IF NOT EXISTS `order_status_history` (
+            `id` BIGINT UNSIGNED NOT NULL AUTO_INCREMENT,
+            `order_id` BIGINT UNSIGNED NOT NULL,
+            `previous_status` VARCHAR(32),
+            `new_status` VARCHAR(32) NOT NULL,
+            `changed_by` BIGINT UNSIGNED,
+            `notes` TEXT,
+            `created_at` DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            PRIMARY KEY (`id`),
+            FOREIGN KEY (`order_id`) REFERENCES `orders`(`id`) ON DELETE CASCADE,
+            INDEX `idx_order_id` (`order_id`)
+        )
+        "#,
+    )
+    .await?;
+
     Ok(())
 }
 
+/// How long a repeated `Idempotency-Key` returns the order it first created
+pub const IDEMPOTENCY_KEY_WINDOW_HOURS: u32 = 24;
+
 impl StoreOrderRecord {
     pub async fn get_all(pool: &MySqlPool) -> anyhow::Result<Vec<Self>> {
         let orders = sqlx::query_as::<_, Self>(
@@ -121,8 +204,7 @@ impl StoreOrderRecord {
     pub async fn get_orders_for_user(pool: &MySqlPool, user_id: u64) -> anyhow::Result<Vec<Self>> {
         let orders = sqlx::query_as::<_, Self>(
             r#"
-            SELECT id, order_number, user_id, store_id, status, total_amount,
-                   notes, created_at, updated_at, status_changed_to_pending, status_changed_to_completed
+            SELECT *
             FROM `orders`
             WHERE `user_id` = ?
             ORDER BY `created_at` DESC
@@ -141,8 +223,7 @@ impl StoreOrderRecord {
     ) -> anyhow::Result<Vec<Self>> {
         let orders = sqlx::query_as::<_, Self>(
             r#"
-            SELECT id, order_number, user_id, store_id, status, total_amount,
-                   notes, created_at, updated_at, status_changed_to_pending, status_changed_to_completed
+            SELECT *
             FROM `orders`
             WHERE `store_id` = ?
             ORDER BY `created_at` DESC
@@ -158,8 +239,7 @@ impl StoreOrderRecord {
     pub async fn get_by_id(pool: &MySqlPool, id: u64) -> anyhow::Result<Option<Self>> {
         let order = sqlx::query_as::<_, Self>(
             r#"
-            SELECT id, order_number, user_id, store_id, status, total_amount,
-                   notes, created_at, updated_at, status_changed_to_pending, status_changed_to_completed
+            SELECT *
             FROM `orders`
             WHERE `id` = ?
             "#
@@ -185,52 +265,254 @@ impl StoreOrderRecord {
         }
     }
 
+    /// Creates an order and takes its stock, unless a line cannot be filled or the order
+    /// would go over a blocking budget
     pub async fn create_order(
         pool: &MySqlPool,
         user_context: &UserContext,
         store_id: u64,
         items: &[(u64, i32)], // (product_id, quantity)
         notes: Option<&str>,
-    ) -> anyhow::Result<u64> {
+    ) -> anyhow::Result<OrderCreateResult> {
         let mut transaction = pool.begin().await?;
+        let result =
+            Self::create_order_with_transaction(&mut transaction, user_context, store_id, items, notes)
+                .await?;
+        if let OrderCreateResult::Created { order_id, .. } = result {
+            transaction.commit().await?;
+            LowStockItem::notify_crossed_by_order(pool, order_id);
+        }
+        Ok(result)
+    }
 
-        // Generate order number
-        let order_number = Self::generate_order_number().await;
+    /// Order `user_id` created with `idempotency_key` within the replay window, if any
+    pub async fn find_by_idempotency_key(
+        pool: &MySqlPool,
+        user_id: u64,
+        idempotency_key: &str,
+    ) -> anyhow::Result<Option<u64>> {
+        let order_id = sqlx::query_scalar(
+            r#"
+            SELECT `id` FROM `orders`
+            WHERE `user_id` = ? AND `idempotency_key` = ?
+              AND `created_at` >= NOW() - INTERVAL ? HOUR
+            "#,
+        )
+        .bind(user_id)
+        .bind(idempotency_key)
+        .bind(IDEMPOTENCY_KEY_WINDOW_HOURS)
+        .fetch_optional(pool)
+        .await?;
+        Ok(order_id)
+    }
 
-        // Calculate total amount
-        let mut total_amount: Decimal = Decimal::from_i32(0).unwrap();
-        for (product_id, quantity) in items {
-            let price_row = sqlx::query("SELECT price FROM products WHERE id = ?")
-                .bind(product_id)
-                .fetch_one(&mut *transaction)
-                .await?;
-            let unit_price: Decimal = price_row.get("price");
-            let qty = Decimal::from_i32(*quantity).unwrap_or_else(|| Decimal::from_i32(0).unwrap());
-            total_amount += unit_price * qty;
+    /// Same as [`Self::create_order`], recording `idempotency_key` with the order.
+    ///
+    /// When a concurrent request with the same key wins the race, this one is rolled back
+    /// (stock included) and the other request's order is returned. A key older than the replay
+    /// window is released so it can be used again.
+    pub async fn create_order_idempotent(
+        pool: &MySqlPool,
+        user_context: &UserContext,
+        store_id: u64,
+        items: &[(u64, i32)], // (product_id, quantity)
+        notes: Option<&str>,
+        idempotency_key: &str,
+    ) -> anyhow::Result<IdempotentOrderResult> {
+        let mut transaction = pool.begin().await?;
+
+        sqlx::query(
+            r#"
+            UPDATE `orders` SET `idempotency_key` = NULL
+            WHERE `user_id` = ? AND `idempotency_key` = ?
+              AND `created_at` < NOW() - INTERVAL ? HOUR
+            "#,
+        )
+        .bind(user_context.user_id)
+        .bind(idempotency_key)
+        .bind(IDEMPOTENCY_KEY_WINDOW_HOURS)
+        .execute(&mut *transaction)
+        .await?;
+
+        let (order_id, budget) = match Self::create_order_with_transaction(
+            &mut transaction,
+            user_context,
+            store_id,
+            items,
+            notes,
+        )
+        .await?
+        {
+            OrderCreateResult::Created { order_id, budget } => (order_id, budget),
+            OrderCreateResult::Rejected(issues) => {
+                transaction.rollback().await?;
+                return Self::replayed_or(
+                    pool,
+                    user_context.user_id,
+                    idempotency_key,
+                    IdempotentOrderResult::Rejected(issues),
+                )
+                .await;
+            }
+            OrderCreateResult::OverBudget(check) => {
+                transaction.rollback().await?;
+                return Self::replayed_or(
+                    pool,
+                    user_context.user_id,
+                    idempotency_key,
+                    IdempotentOrderResult::OverBudget(check),
+                )
+                .await;
+            }
+        };
+
+        let claimed = sqlx::query("UPDATE `orders` SET `idempotency_key` = ? WHERE `id` = ?")
+            .bind(idempotency_key)
+            .bind(order_id)
+            .execute(&mut *transaction)
+            .await;
+        match claimed {
+            Ok(_) => {}
+            Err(sqlx::Error::Database(e)) if e.is_unique_violation() => {
+                transaction.rollback().await?;
+                let existing =
+                    Self::find_by_idempotency_key(pool, user_context.user_id, idempotency_key)
+                        .await?
+                        .ok_or_else(|| anyhow::anyhow!("Order for idempotency key disappeared"))?;
+                info!(
+                    "Idempotency key {} raced for user {}, returning order {}",
+                    idempotency_key, user_context.user_id, existing
+                );
+                return Ok(IdempotentOrderResult::Replayed(existing));
+            }
+            Err(e) => return Err(e.into()),
+        }
+
+        transaction.commit().await?;
+        LowStockItem::notify_crossed_by_order(pool, order_id);
+        Ok(IdempotentOrderResult::Created { order_id, budget })
+    }
+
+    /// The order a concurrent request with `idempotency_key` created, which may have taken the
+    /// stock or budget while this one waited, otherwise `refused`
+    async fn replayed_or(
+        pool: &MySqlPool,
+        user_id: u64,
+        idempotency_key: &str,
+        refused: IdempotentOrderResult,
+    ) -> anyhow::Result<IdempotentOrderResult> {
+        match Self::find_by_idempotency_key(pool, user_id, idempotency_key).await? {
+            Some(existing) => Ok(IdempotentOrderResult::Replayed(existing)),
+            None => Ok(refused),
         }
+    }
+
+    /// Same as [`Self::create_order`] but inside a transaction owned by the caller,
+    /// for flows that create an order as one step of a larger change.
+    ///
+    /// The store's budget and the products stay locked until the transaction ends, so
+    /// concurrent orders cannot both take the last of a product or of the budget. On rejection
+    /// nothing is written; the caller decides whether to roll back the rest of its change.
+    pub async fn create_order_with_transaction(
+        transaction: &mut Transaction<'_, MySql>,
+        user_context: &UserContext,
+        store_id: u64,
+        items: &[(u64, i32)], // (product_id, quantity)
+        notes: Option<&str>,
+    ) -> anyhow::Result<OrderCreateResult> {
+        // The budget is locked before the products, the same order as every other order
+        let budget = BudgetCheck::for_order(transaction, store_id, items).await?;
+        if let Some(check) = budget.as_ref().filter(|check| check.is_blocked()) {
+            info!("Order for store {} rejected, it would exceed the budget", store_id);
+            return Ok(OrderCreateResult::OverBudget(check.clone()));
+        }
+
+        let availability = lock_products(transaction, items).await?;
+        let issues = StockIssue::find_all(items, &availability);
+        if !issues.is_empty() {
+            info!(
+                "Order for store {} rejected, {} product(s) cannot be filled",
+                store_id,
+                issues.len()
+            );
+            return Ok(OrderCreateResult::Rejected(issues));
+        }
+
+        let total_amount = order_total(transaction, items).await?;
+
+        // Orders over the store's threshold wait for sign-off; admins placing an order approve it by doing so
+        let status = match approval_threshold(transaction, store_id).await? {
+            Some(threshold) if total_amount > threshold && user_context.role != "admin" => {
+                StoreOrderStatus::AwaitingApproval
+            }
+            _ => StoreOrderStatus::Pending,
+        };
+
+        Self::insert_order(
+            transaction,
+            user_context.user_id,
+            store_id,
+            items,
+            notes,
+            total_amount,
+            status,
+        )
+        .await
+        .map(|order_id| OrderCreateResult::Created { order_id, budget })
+    }
+
+    /// Writes an order in `status` with its lines and takes their stock, without checking
+    /// availability, approval or budget
+    async fn insert_order(
+        transaction: &mut Transaction<'_, MySql>,
+        user_id: u64,
+        store_id: u64,
+        items: &[(u64, i32)], // (product_id, quantity)
+        notes: Option<&str>,
+        total_amount: Decimal,
+        status: StoreOrderStatus,
+    ) -> anyhow::Result<u64> {
+        let order_number = Self::generate_order_number(transaction, store_id).await?;
 
         // Create order
         let order_result = sqlx::query(
             r#"
-            INSERT INTO `orders` (`order_number`, `user_id`, `store_id`, `total_amount`, `notes`, `status_changed_to_pending`)
-            VALUES (?, ?, ?, ?, ?, NOW())
+            INSERT INTO `orders` (`order_number`, `user_id`, `store_id`, `status`, `total_amount`, `notes`, `status_changed_to_pending`)
+            VALUES (?, ?, ?, ?, ?, ?, IF(?, NOW(), NULL))
             "#
         )
         .bind(&order_number)
-        .bind(user_context.user_id)
+        .bind(user_id)
         .bind(store_id)
+        .bind(status.as_db_str())
         .bind(total_amount)
         .bind(notes)
-        .execute(&mut *transaction)
+        .bind(status == StoreOrderStatus::Pending)
+        .execute(&mut **transaction)
         .await?;
 
         let order_id = order_result.last_insert_id();
+        record_status_change(
+            transaction,
+            order_id,
+            None,
+            &status,
+            user_id,
+            None,
+        )
+        .await?;
+        if status == StoreOrderStatus::AwaitingApproval {
+            info!(
+                "Order {} ({}) totals {} and needs approval for store {}",
+                order_id, order_number, total_amount, store_id
+            );
+        }
 
         // Create order items
         for (product_id, quantity) in items {
             let price_row = sqlx::query("SELECT price FROM products WHERE id = ?")
                 .bind(product_id)
-                .fetch_one(&mut *transaction)
+                .fetch_one(&mut **transaction)
                 .await?;
             let unit_price: Decimal = price_row.get("price");
             let qty = Decimal::from_i32(*quantity).unwrap_or_else(|| Decimal::from_i32(0).unwrap());
@@ -247,26 +529,13 @@ impl StoreOrderRecord {
             .bind(quantity)
             .bind(unit_price)
             .bind(total_price)
-            .execute(&mut *transaction)
+            .execute(&mut **transaction)
             .await?;
 
-            // Update product stock
-            sqlx::query(
-                r#"
-                UPDATE `products`
-                SET `stock_quantity` = `stock_quantity` - ?,
-                    `in_stock` = (`stock_quantity` - ?) > 0
-                WHERE `id` = ?
-                "#,
-            )
-            .bind(quantity)
-            .bind(quantity)
-            .bind(product_id)
-            .execute(&mut *transaction)
-            .await?;
+            adjust_product_stock(transaction, *product_id, -quantity, order_id, user_id, None)
+                .await?;
         }
 
-        transaction.commit().await?;
         Ok(order_id)
     }
 
@@ -279,12 +548,25 @@ impl StoreOrderRecord {
         )
     }
 
+    /// Moves an order to `status`, checking that `role` may do so from the status the
+    /// order has once its row is locked, not the one the caller read before.
     pub async fn update_status(
         pool: &MySqlPool,
         id: u64,
         status: StoreOrderStatus,
         notes: Option<&str>,
-    ) -> anyhow::Result<bool> {
+        changed_by: u64,
+        role: &UserRole,
+    ) -> anyhow::Result<StatusChangeResult> {
+        let mut transaction = pool.begin().await?;
+
+        let Some(previous_status) = lock_order_status(&mut transaction, id).await? else {
+            return Ok(StatusChangeResult::OrderNotFound);
+        };
+        if !previous_status.can_transition_to(&status, role) {
+            return Ok(StatusChangeResult::NotAllowed(previous_status));
+        }
+
         let mut query = String::from("UPDATE `orders` SET `status` = ?");
         let mut params: Vec<String> = vec![status.as_db_str().to_string()];
 
@@ -298,6 +580,9 @@ impl StoreOrderRecord {
             StoreOrderStatus::Pending => {
                 query.push_str(", `status_changed_to_pending` = NOW()");
             }
+            StoreOrderStatus::Shipped => {
+                query.push_str(", `status_changed_to_shipped` = NOW()");
+            }
             StoreOrderStatus::Delivered => {
                 query.push_str(", `status_changed_to_completed` = NOW()");
             }
@@ -312,19 +597,837 @@ impl StoreOrderRecord {
             sql_query = sql_query.bind(param);
         }
 
-        let result = sql_query.execute(pool).await?;
-        Ok(result.rows_affected() > 0)
+        sql_query.execute(&mut *transaction).await?;
+
+        match status {
+            StoreOrderStatus::Shipped => ship_outstanding(&mut transaction, id).await?,
+            StoreOrderStatus::Delivered => {
+                settle_delivered_lines(&mut transaction, id, changed_by).await?
+            }
+            _ => {}
+        }
+
+        record_status_change(
+            &mut transaction,
+            id,
+            Some(&previous_status),
+            &status,
+            changed_by,
+            notes,
+        )
+        .await?;
+
+        transaction.commit().await?;
+        Ok(StatusChangeResult::Changed(previous_status))
     }
 
-    async fn generate_order_number() -> String {
-        use chrono::Utc;
-        let now = Utc::now();
-        format!(
-            "ORD-{}-{:06}",
-            now.format("%Y%m%d"),
-            rand::random::<u32>() % 1000000
+    pub async fn get_status_history(
+        pool: &MySqlPool,
+        id: u64,
+    ) -> anyhow::Result<Vec<OrderStatusHistoryRecord>> {
+        let history = sqlx::query_as::<_, OrderStatusHistoryRecord>(
+            r#"
+            SELECT h.id, h.order_id, h.previous_status, h.new_status, h.changed_by,
+                   u.email AS changed_by_email, h.notes, h.created_at
+            FROM `order_status_history` h
+            LEFT JOIN `users` u ON h.changed_by = u.id
+            WHERE h.order_id = ?
+            ORDER BY h.created_at ASC, h.id ASC
+            "#,
+        )
+        .bind(id)
+        .fetch_all(pool)
+        .await?;
+
+        Ok(history)
+    }
+
+    /// Cancels an order and returns the stock its lines reserved.
+    ///
+    /// Runs in one transaction with the order row locked, so a concurrent cancel
+    /// cannot restock twice. Whether `role` may cancel is checked against the locked
+    /// status, not the one the caller read before.
+    pub async fn cancel(
+        pool: &MySqlPool,
+        id: u64,
+        cancelled_by: u64,
+        role: &UserRole,
+        reason: &str,
+    ) -> anyhow::Result<StatusChangeResult> {
+        let mut transaction = pool.begin().await?;
+
+        let Some(previous_status) = lock_order_status(&mut transaction, id).await? else {
+            return Ok(StatusChangeResult::OrderNotFound);
+        };
+        if !previous_status.can_transition_to(&StoreOrderStatus::Cancelled, role) {
+            return Ok(StatusChangeResult::NotAllowed(previous_status));
+        }
+
+        let lines = sqlx::query(
+            "SELECT `product_id`, `quantity`, `quantity_shipped`, `quantity_backordered` FROM `order_items` WHERE `order_id` = ?",
+        )
+        .bind(id)
+        .fetch_all(&mut *transaction)
+        .await?;
+
+        for line in lines {
+            let product_id: u64 = line.get("product_id");
+            // Backordered quantities gave their stock back when they were backordered
+            let quantity: i32 = line.get::<i32, _>("quantity")
+                - line.get::<i32, _>("quantity_shipped")
+                - line.get::<i32, _>("quantity_backordered");
+            if quantity <= 0 {
+                continue;
+            }
+            adjust_product_stock(&mut transaction, product_id, quantity, id, cancelled_by, Some(reason))
+                .await?;
+        }
+
+        sqlx::query(
+            r#"
+            UPDATE `orders`
+            SET `status` = ?,
+                `cancelled_at` = NOW(),
+                `cancelled_by` = ?,
+                `cancellation_reason` = ?
+            WHERE `id` = ?
+            "#,
+        )
+        .bind(StoreOrderStatus::Cancelled.as_db_str())
+        .bind(cancelled_by)
+        .bind(reason)
+        .bind(id)
+        .execute(&mut *transaction)
+        .await?;
+
+        record_status_change(
+            &mut transaction,
+            id,
+            Some(&previous_status),
+            &StoreOrderStatus::Cancelled,
+            cancelled_by,
+            Some(reason),
+        )
+        .await?;
+
+        transaction.commit().await?;
+        info!("Order {} cancelled by user {}: {}", id, cancelled_by, reason);
+        Ok(StatusChangeResult::Changed(previous_status))
+    }
+
+    /// Adds a product to a pending order, merging with an existing line for the same product.
+    ///
+    /// The product is locked and checked against its stock, and the store's budget, as when an
+    /// order is created. A store user taking the total over the approval threshold sends the
+    /// order back for approval.
+    pub async fn add_item(
+        pool: &MySqlPool,
+        order_id: u64,
+        product_id: u64,
+        quantity: i32,
+        edited_by: u64,
+        role: &UserRole,
+    ) -> anyhow::Result<OrderEditResult> {
+        let mut transaction = pool.begin().await?;
+
+        if let Some(rejected) = lock_pending_order(&mut transaction, order_id).await? {
+            return Ok(rejected);
+        }
+
+        let added = [(product_id, quantity)];
+        if let Some(rejected) = check_edit_budget(&mut transaction, order_id, &added).await? {
+            return Ok(rejected);
+        }
+        let availability = lock_products(&mut transaction, &added).await?;
+        let issues = StockIssue::find_all(&added, &availability);
+        if !issues.is_empty() {
+            return Ok(OrderEditResult::Rejected(issues));
+        }
+
+        let existing_line = sqlx::query(
+            "SELECT `id`, `quantity`, `unit_price` FROM `order_items` WHERE `order_id` = ? AND `product_id` = ? FOR UPDATE",
+        )
+        .bind(order_id)
+        .bind(product_id)
+        .fetch_optional(&mut *transaction)
+        .await?;
+
+        if let Some(line) = existing_line {
+            // Keep the price the line was originally ordered at
+            let item_id: u64 = line.get("id");
+            let current_quantity: i32 = line.get("quantity");
+            let unit_price: Decimal = line.get("unit_price");
+            let new_quantity = current_quantity + quantity;
+            sqlx::query("UPDATE `order_items` SET `quantity` = ?, `total_price` = ? WHERE `id` = ?")
+                .bind(new_quantity)
+                .bind(unit_price * Decimal::from(new_quantity))
+                .bind(item_id)
+                .execute(&mut *transaction)
+                .await?;
+        } else {
+            let unit_price: Decimal = sqlx::query_scalar("SELECT `price` FROM `products` WHERE `id` = ?")
+                .bind(product_id)
+                .fetch_one(&mut *transaction)
+                .await?;
+            sqlx::query(
+                r#"
+                INSERT INTO `order_items` (`order_id`, `product_id`, `quantity`, `unit_price`, `total_price`)
+                VALUES (?, ?, ?, ?, ?)
+                "#,
+            )
+            .bind(order_id)
+            .bind(product_id)
+            .bind(quantity)
+            .bind(unit_price)
+            .bind(unit_price * Decimal::from(quantity))
+            .execute(&mut *transaction)
+            .await?;
+        }
+
+        adjust_product_stock(&mut transaction, product_id, -quantity, order_id, edited_by, None).await?;
+        recalculate_order_total(&mut transaction, order_id).await?;
+        if !matches!(role, UserRole::Admin) {
+            require_approval_over_threshold(&mut transaction, order_id, edited_by).await?;
+        }
+
+        transaction.commit().await?;
+        Ok(OrderEditResult::Updated)
+    }
+
+    /// Changes the quantity of a line on a pending order; a quantity of zero removes it.
+    ///
+    /// Raising the quantity locks the product and checks the extra against its stock, and the
+    /// store's budget, as when an order is created. A store user taking the total over the
+    /// approval threshold sends the order back for approval.
+    pub async fn set_item_quantity(
+        pool: &MySqlPool,
+        order_id: u64,
+        item_id: u64,
+        quantity: i32,
+        edited_by: u64,
+        role: &UserRole,
+    ) -> anyhow::Result<OrderEditResult> {
+        if quantity <= 0 {
+            return Self::remove_item(pool, order_id, item_id, edited_by).await;
+        }
+
+        let mut transaction = pool.begin().await?;
+
+        if let Some(rejected) = lock_pending_order(&mut transaction, order_id).await? {
+            return Ok(rejected);
+        }
+
+        let line = sqlx::query(
+            "SELECT `product_id`, `quantity`, `unit_price` FROM `order_items` WHERE `id` = ? AND `order_id` = ? FOR UPDATE",
+        )
+        .bind(item_id)
+        .bind(order_id)
+        .fetch_optional(&mut *transaction)
+        .await?;
+        let Some(line) = line else {
+            return Ok(OrderEditResult::ItemNotFound);
+        };
+
+        let product_id: u64 = line.get("product_id");
+        let current_quantity: i32 = line.get("quantity");
+        let unit_price: Decimal = line.get("unit_price");
+
+        if quantity > current_quantity {
+            let added = [(product_id, quantity - current_quantity)];
+            if let Some(rejected) = check_edit_budget(&mut transaction, order_id, &added).await? {
+                return Ok(rejected);
+            }
+            let availability = lock_products(&mut transaction, &added).await?;
+            let issues = StockIssue::find_all(&added, &availability);
+            if !issues.is_empty() {
+                return Ok(OrderEditResult::Rejected(issues));
+            }
+        }
+
+        sqlx::query("UPDATE `order_items` SET `quantity` = ?, `total_price` = ? WHERE `id` = ?")
+            .bind(quantity)
+            .bind(unit_price * Decimal::from(quantity))
+            .bind(item_id)
+            .execute(&mut *transaction)
+            .await?;
+
+        // Ordering more takes stock, ordering less gives it back
+        adjust_product_stock(
+            &mut transaction,
+            product_id,
+            current_quantity - quantity,
+            order_id,
+            edited_by,
+            None,
+        )
+        .await?;
+        recalculate_order_total(&mut transaction, order_id).await?;
+        if quantity > current_quantity && !matches!(role, UserRole::Admin) {
+            require_approval_over_threshold(&mut transaction, order_id, edited_by).await?;
+        }
+
+        transaction.commit().await?;
+        Ok(OrderEditResult::Updated)
+    }
+
+    /// Removes a line from a pending order and restocks its quantity.
+    pub async fn remove_item(
+        pool: &MySqlPool,
+        order_id: u64,
+        item_id: u64,
+        edited_by: u64,
+    ) -> anyhow::Result<OrderEditResult> {
+        let mut transaction = pool.begin().await?;
+
+        if let Some(rejected) = lock_pending_order(&mut transaction, order_id).await? {
+            return Ok(rejected);
+        }
+
+        let line = sqlx::query(
+            "SELECT `product_id`, `quantity` FROM `order_items` WHERE `id` = ? AND `order_id` = ? FOR UPDATE",
+        )
+        .bind(item_id)
+        .bind(order_id)
+        .fetch_optional(&mut *transaction)
+        .await?;
+        let Some(line) = line else {
+            return Ok(OrderEditResult::ItemNotFound);
+        };
+
+        let product_id: u64 = line.get("product_id");
+        let quantity: i32 = line.get("quantity");
+
+        sqlx::query("DELETE FROM `order_items` WHERE `id` = ?")
+            .bind(item_id)
+            .execute(&mut *transaction)
+            .await?;
+
+        adjust_product_stock(&mut transaction, product_id, quantity, order_id, edited_by, None).await?;
+        recalculate_order_total(&mut transaction, order_id).await?;
+
+        transaction.commit().await?;
+        Ok(OrderEditResult::Updated)
+    }
+
+    /// Records a (possibly partial) shipment against an order's lines.
+    ///
+    /// `lines` holds `(item_id, quantity shipped now)`. The order moves to Shipped
+    /// once anything has shipped. With `backorder_remaining` every quantity still
+    /// outstanding is marked backordered and can no longer ship on this order; with
+    /// `create_backorder_order` those quantities are also moved onto a new Pending
+    /// order for the same store, which takes over their stock reservation; otherwise
+    /// their stock goes back on the shelf. Without either, the rest stays reserved for a
+    /// later shipment and is released when the order is delivered, see
+    /// [`settle_delivered_lines`]. Everything happens in one transaction.
+    pub async fn record_shipment(
+        pool: &MySqlPool,
+        order_id: u64,
+        lines: &[(u64, i32)],
+        backorder_remaining: bool,
+        create_backorder_order: bool,
+        shipped_by: u64,
+        notes: Option<&str>,
+    ) -> anyhow::Result<ShipmentResult> {
+        let mut transaction = pool.begin().await?;
+
+        let Some(status) = lock_order_status(&mut transaction, order_id).await? else {
+            return Ok(ShipmentResult::OrderNotFound);
+        };
+        if status != StoreOrderStatus::Shipped
+            && !status.can_transition_to(&StoreOrderStatus::Shipped, &UserRole::Admin)
+        {
+            return Ok(ShipmentResult::NotShippable(status));
+        }
+
+        let rows = sqlx::query(
+            "SELECT `id`, `product_id`, `quantity`, `quantity_shipped`, `quantity_backordered` FROM `order_items` WHERE `order_id` = ? FOR UPDATE",
+        )
+        .bind(order_id)
+        .fetch_all(&mut *transaction)
+        .await?;
+        // item_id -> (product_id, quantity still expected to ship, quantity_shipped)
+        let mut order_lines: HashMap<u64, (u64, i32, i32)> = rows
+            .iter()
+            .map(|row| {
+                let quantity: i32 = row.get("quantity");
+                let shipped: i32 = row.get("quantity_shipped");
+                let backordered: i32 = row.get("quantity_backordered");
+                (row.get("id"), (row.get("product_id"), quantity - shipped - backordered, shipped))
+            })
+            .collect();
+
+        let mut errors = Vec::new();
+        for (item_id, shipped_now) in lines {
+            let item_hash = serde_hash::hashids::encode_single(*item_id);
+            let Some((_, outstanding, quantity_shipped)) = order_lines.get_mut(item_id) else {
+                errors.push(format!("Item {} is not on this order", item_hash));
+                continue;
+            };
+            if *shipped_now < 0 {
+                errors.push(format!("Item {}: shipped quantity cannot be negative", item_hash));
+            } else if *shipped_now > *outstanding {
+                errors.push(format!(
+                    "Item {}: cannot ship {}, only {} outstanding",
+                    item_hash, shipped_now, outstanding
+                ));
+            } else {
+                *outstanding -= shipped_now;
+                *quantity_shipped += shipped_now;
+            }
+        }
+        if !errors.is_empty() {
+            return Ok(ShipmentResult::InvalidLines(errors));
+        }
+
+        for (item_id, shipped_now) in lines {
+            sqlx::query("UPDATE `order_items` SET `quantity_shipped` = `quantity_shipped` + ? WHERE `id` = ?")
+                .bind(shipped_now)
+                .bind(item_id)
+                .execute(&mut *transaction)
+                .await?;
+        }
+
+        let mut backorder_items: Vec<(u64, i32)> = Vec::new();
+        if backorder_remaining || create_backorder_order {
+            for (item_id, (product_id, outstanding, _)) in &order_lines {
+                if *outstanding <= 0 {
+                    continue;
+                }
+                sqlx::query("UPDATE `order_items` SET `quantity_backordered` = `quantity_backordered` + ? WHERE `id` = ?")
+                    .bind(outstanding)
+                    .bind(item_id)
+                    .execute(&mut *transaction)
+                    .await?;
+                backorder_items.push((*product_id, *outstanding));
+            }
+        }
+
+        // Release what this order reserved for the quantities it will no longer ship;
+        // a backorder order takes the stock again below
+        let restock_reason = if create_backorder_order {
+            "Moved to backorder order"
+        } else {
+            "Backordered, not shipped"
+        };
+        for (product_id, remaining) in &backorder_items {
+            adjust_product_stock(
+                &mut transaction,
+                *product_id,
+                *remaining,
+                order_id,
+                shipped_by,
+                Some(restock_reason),
+            )
+            .await?;
+        }
+
+        let mut backorder_order_id = None;
+        if create_backorder_order && !backorder_items.is_empty() {
+            let order_row = sqlx::query("SELECT `order_number`, `user_id`, `store_id` FROM `orders` WHERE `id` = ?")
+                .bind(order_id)
+                .fetch_one(&mut *transaction)
+                .await?;
+            let order_number: String = order_row.get("order_number");
+            let user_id: u64 = order_row.get("user_id");
+            let store_id: u64 = order_row.get("store_id");
+
+            // Backorders are for stock that is not there, so availability is not checked.
+            // The lines were already ordered: the original order went through approval and
+            // the budget check, and its total stays in the budget's spend, so the backorder
+            // starts Pending and is not checked against the budget again.
+            let backorder_notes = format!("Backorder of {}", order_number);
+            let total_amount = order_total(&mut transaction, &backorder_items).await?;
+            let new_order_id = Self::insert_order(
+                &mut transaction,
+                user_id,
+                store_id,
+                &backorder_items,
+                Some(&backorder_notes),
+                total_amount,
+                StoreOrderStatus::Pending,
+            )
+            .await?;
+            sqlx::query("UPDATE `orders` SET `backorder_of_order_id` = ? WHERE `id` = ?")
+                .bind(order_id)
+                .bind(new_order_id)
+                .execute(&mut *transaction)
+                .await?;
+            backorder_order_id = Some(new_order_id);
+        }
+
+        let anything_shipped = order_lines.values().any(|(_, _, shipped)| *shipped > 0);
+        if anything_shipped && status != StoreOrderStatus::Shipped {
+            sqlx::query("UPDATE `orders` SET `status` = ?, `status_changed_to_shipped` = NOW() WHERE `id` = ?")
+                .bind(StoreOrderStatus::Shipped.as_db_str())
+                .bind(order_id)
+                .execute(&mut *transaction)
+                .await?;
+            record_status_change(
+                &mut transaction,
+                order_id,
+                Some(&status),
+                &StoreOrderStatus::Shipped,
+                shipped_by,
+                notes,
+            )
+            .await?;
+        }
+
+        transaction.commit().await?;
+        info!(
+            "Shipment recorded for order {} by user {} (backorder order: {:?})",
+            order_id, shipped_by, backorder_order_id
+        );
+        Ok(ShipmentResult::Recorded { backorder_order_id })
+    }
+
+    /// Draws the next number from the order's sequence, see [`OrderNumberFormat`].
+    ///
+    /// The sequence row stays locked until the transaction ends, so concurrent orders in the
+    /// same sequence wait for each other and a rolled-back order gives its number back.
+    async fn generate_order_number(
+        transaction: &mut Transaction<'_, MySql>,
+        store_id: u64,
+    ) -> anyhow::Result<String> {
+        let format = OrderNumberFormat::configured();
+        let today = chrono::Local::now().date_naive();
+        let scope = format.sequence_scope(today, store_id);
+
+        // Numbers left by an earlier format can already be taken; skip past them
+        loop {
+            let seq = next_sequence_value(transaction, &scope).await?;
+            let order_number = format.render(today, store_id, seq);
+            let taken: Option<u64> =
+                sqlx::query_scalar("SELECT `id` FROM `orders` WHERE `order_number` = ?")
+                    .bind(&order_number)
+                    .fetch_optional(&mut **transaction)
+                    .await?;
+            if taken.is_none() {
+                return Ok(order_number);
+            }
+        }
+    }
+}
+
+/// Advances the `order_number_sequences` counter for `scope` and returns its new value.
+///
+/// The sequence row stays locked until the transaction ends, so concurrent callers in the
+/// same scope wait for each other and a rolled-back caller gives its value back.
+pub(crate) async fn next_sequence_value(
+    transaction: &mut Transaction<'_, MySql>,
+    scope: &str,
+) -> anyhow::Result<u64> {
+    sqlx::query(
+        r#"
+        INSERT INTO `order_number_sequences` (`scope`, `last_value`) VALUES (?, 1)
+        ON DUPLICATE KEY UPDATE `last_value` = `last_value` + 1
+        "#,
+    )
+    .bind(scope)
+    .execute(&mut **transaction)
+    .await?;
+    let value = sqlx::query_scalar(
+        "SELECT `last_value` FROM `order_number_sequences` WHERE `scope` = ?",
+    )
+    .bind(scope)
+    .fetch_one(&mut **transaction)
+    .await?;
+    Ok(value)
+}
+
+/// Locks the order row for the rest of the transaction and checks its lines can still change.
+///
+/// Returns the rejection to report when the order is missing or no longer Pending.
+async fn lock_pending_order(
+    transaction: &mut Transaction<'_, MySql>,
+    order_id: u64,
+) -> anyhow::Result<Option<OrderEditResult>> {
+    match lock_order_status(transaction, order_id).await? {
+        None => Ok(Some(OrderEditResult::OrderNotFound)),
+        Some(StoreOrderStatus::Pending) => Ok(None),
+        Some(status) => Ok(Some(OrderEditResult::NotEditable(status))),
+    }
+}
+
+/// The store's approval threshold, if it has one
+async fn approval_threshold(
+    transaction: &mut Transaction<'_, MySql>,
+    store_id: u64,
+) -> anyhow::Result<Option<Decimal>> {
+    Ok(sqlx::query("SELECT `approval_threshold` FROM `stores` WHERE `id` = ?")
+        .bind(store_id)
+        .fetch_optional(&mut **transaction)
+        .await?
+        .and_then(|row| row.get("approval_threshold")))
+}
+
+/// Sends a pending order a store user just edited back for approval when its total is now
+/// over the store's threshold, as if it had been placed at that total
+async fn require_approval_over_threshold(
+    transaction: &mut Transaction<'_, MySql>,
+    order_id: u64,
+    edited_by: u64,
+) -> anyhow::Result<()> {
+    let row = sqlx::query("SELECT `store_id`, `total_amount` FROM `orders` WHERE `id` = ?")
+        .bind(order_id)
+        .fetch_one(&mut **transaction)
+        .await?;
+    let store_id: u64 = row.get("store_id");
+    let total_amount: Decimal = row.get("total_amount");
+    let Some(threshold) = approval_threshold(transaction, store_id).await? else {
+        return Ok(());
+    };
+    if total_amount <= threshold {
+        return Ok(());
+    }
+
+    sqlx::query("UPDATE `orders` SET `status` = ? WHERE `id` = ?")
+        .bind(StoreOrderStatus::AwaitingApproval.as_db_str())
+        .bind(order_id)
+        .execute(&mut **transaction)
+        .await?;
+    record_status_change(
+        transaction,
+        order_id,
+        Some(&StoreOrderStatus::Pending),
+        &StoreOrderStatus::AwaitingApproval,
+        edited_by,
+        Some("Edited above the store's approval limit"),
+    )
+    .await?;
+    info!(
+        "Order {} edited to {} and needs approval for store {}",
+        order_id, total_amount, store_id
+    );
+    Ok(())
+}
+
+/// Refuses adding `added` to an order when it would exceed the store's blocking budget.
+///
+/// The budget row stays locked as when an order is created. The extra quantity is priced at
+/// the current price, like a new order, even on a line kept at its original price.
+async fn check_edit_budget(
+    transaction: &mut Transaction<'_, MySql>,
+    order_id: u64,
+    added: &[(u64, i32)], // (product_id, quantity)
+) -> anyhow::Result<Option<OrderEditResult>> {
+    let store_id: u64 = sqlx::query_scalar("SELECT `store_id` FROM `orders` WHERE `id` = ?")
+        .bind(order_id)
+        .fetch_one(&mut **transaction)
+        .await?;
+    match BudgetCheck::for_order(transaction, store_id, added).await? {
+        Some(check) if check.is_blocked() => Ok(Some(OrderEditResult::OverBudget(check))),
+        _ => Ok(None),
+    }
+}
+
+/// Locks the order row for the rest of the transaction and returns its current status.
+pub(crate) async fn lock_order_status(
+    transaction: &mut Transaction<'_, MySql>,
+    order_id: u64,
+) -> anyhow::Result<Option<StoreOrderStatus>> {
+    let status_row = sqlx::query("SELECT `status` FROM `orders` WHERE `id` = ? FOR UPDATE")
+        .bind(order_id)
+        .fetch_optional(&mut **transaction)
+        .await?;
+    let Some(status_row) = status_row else {
+        return Ok(None);
+    };
+
+    let status_raw: String = status_row.get("status");
+    let status = StoreOrderStatus::from_str_case_insensitive(&status_raw)
+        .ok_or_else(|| anyhow::anyhow!("Unexpected status value '{}'", status_raw))?;
+    Ok(Some(status))
+}
+
+/// Appends a row to `order_status_history`
+pub(crate) async fn record_status_change(
+    transaction: &mut Transaction<'_, MySql>,
+    order_id: u64,
+    previous_status: Option<&StoreOrderStatus>,
+    new_status: &StoreOrderStatus,
+    changed_by: u64,
+    notes: Option<&str>,
+) -> anyhow::Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO `order_status_history` (`order_id`, `previous_status`, `new_status`, `changed_by`, `notes`)
+        VALUES (?, ?, ?, ?, ?)
+        "#,
+    )
+    .bind(order_id)
+    .bind(previous_status.map(|s| s.as_db_str()))
+    .bind(new_status.as_db_str())
+    .bind(changed_by)
+    .bind(notes)
+    .execute(&mut **transaction)
+    .await?;
+
+    Ok(())
+}
+
+/// Locks the products of a new order and reads their stock; missing products are left out.
+///
+/// Rows are locked in ID order so two orders sharing products cannot deadlock.
+async fn lock_products(
+    transaction: &mut Transaction<'_, MySql>,
+    items: &[(u64, i32)], // (product_id, quantity)
+) -> anyhow::Result<HashMap<u64, ProductAvailability>> {
+    if items.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let placeholders = vec!["?"; items.len()].join(", ");
+    let sql = format!(
+        r#"
+        SELECT `id`, `sku`, `stock_quantity`, `is_active`, `allow_backorder`
+        FROM `products`
+        WHERE `id` IN ({placeholders})
+        ORDER BY `id`
+        FOR UPDATE
+        "#
+    );
+    let mut query = sqlx::query(&sql);
+    for (product_id, _) in items {
+        query = query.bind(product_id);
+    }
+    let rows = query.fetch_all(&mut **transaction).await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| {
+            (
+                row.get("id"),
+                ProductAvailability {
+                    sku: row.get("sku"),
+                    stock_quantity: row.get("stock_quantity"),
+                    is_active: row.get("is_active"),
+                    allow_backorder: row.get("allow_backorder"),
+                },
+            )
+        })
+        .collect())
+}
+
+/// Applies an order's stock change to a product and records it in the inventory ledger;
+/// a positive delta restocks, a negative one consumes.
+async fn adjust_product_stock(
+    transaction: &mut Transaction<'_, MySql>,
+    product_id: u64,
+    delta: i32,
+    order_id: u64,
+    user_id: u64,
+    reason: Option<&str>,
+) -> anyhow::Result<()> {
+    let movement_type = if delta < 0 {
+        MovementType::OrderDecrement
+    } else {
+        MovementType::CancellationRestock
+    };
+    let movement = StockMovement {
+        reason,
+        ..StockMovement::for_order(movement_type, order_id, user_id)
+    };
+    InventoryMovementRecord::apply(transaction, product_id, delta as f32, &movement).await?;
+    Ok(())
+}
+
+/// Marks every line of an order that ships without [`StoreOrderRecord::record_shipment`]
+/// as shipped in full, less anything already backordered
+pub(crate) async fn ship_outstanding(
+    transaction: &mut Transaction<'_, MySql>,
+    order_id: u64,
+) -> anyhow::Result<()> {
+    sqlx::query("UPDATE `order_items` SET `quantity_shipped` = `quantity` - `quantity_backordered` WHERE `order_id` = ?")
+        .bind(order_id)
+        .execute(&mut **transaction)
+        .await?;
+    Ok(())
+}
+
+/// Settles an order's lines as it is delivered.
+///
+/// An order delivered without any shipment recorded shipped in full. Once shipments were
+/// recorded, whatever never shipped will not arrive on this order: it is marked backordered
+/// and its stock goes back on the shelf, so the ledger only keeps what actually left.
+pub(crate) async fn settle_delivered_lines(
+    transaction: &mut Transaction<'_, MySql>,
+    order_id: u64,
+    delivered_by: u64,
+) -> anyhow::Result<()> {
+    let rows = sqlx::query(
+        "SELECT `id`, `product_id`, `quantity`, `quantity_shipped`, `quantity_backordered` FROM `order_items` WHERE `order_id` = ? FOR UPDATE",
+    )
+    .bind(order_id)
+    .fetch_all(&mut **transaction)
+    .await?;
+    if !rows.iter().any(|row| row.get::<i32, _>("quantity_shipped") > 0) {
+        return ship_outstanding(transaction, order_id).await;
+    }
+
+    for row in rows {
+        let outstanding = row.get::<i32, _>("quantity")
+            - row.get::<i32, _>("quantity_shipped")
+            - row.get::<i32, _>("quantity_backordered");
+        if outstanding <= 0 {
+            continue;
+        }
+        sqlx::query("UPDATE `order_items` SET `quantity_backordered` = `quantity_backordered` + ? WHERE `id` = ?")
+            .bind(outstanding)
+            .bind(row.get::<u64, _>("id"))
+            .execute(&mut **transaction)
+            .await?;
+        adjust_product_stock(
+            transaction,
+            row.get("product_id"),
+            outstanding,
+            order_id,
+            delivered_by,
+            Some("Not shipped before delivery"),
         )
+        .await?;
     }
+    Ok(())
+}
+
+/// Total of `items` at current prices
+async fn order_total(
+    transaction: &mut Transaction<'_, MySql>,
+    items: &[(u64, i32)], // (product_id, quantity)
+) -> anyhow::Result<Decimal> {
+    let mut total_amount = Decimal::ZERO;
+    for (product_id, quantity) in items {
+        let price_row = sqlx::query("SELECT price FROM products WHERE id = ?")
+            .bind(product_id)
+            .fetch_one(&mut **transaction)
+            .await?;
+        let unit_price: Decimal = price_row.get("price");
+        let qty = Decimal::from_i32(*quantity).unwrap_or_else(|| Decimal::from_i32(0).unwrap());
+        total_amount += unit_price * qty;
+    }
+    Ok(total_amount)
+}
+
+/// Recomputes `orders.total_amount` from its lines
+async fn recalculate_order_total(
+    transaction: &mut Transaction<'_, MySql>,
+    order_id: u64,
+) -> anyhow::Result<()> {
+    sqlx::query(
+        r#"
+        UPDATE `orders`
+        SET `total_amount` = (
+            SELECT COALESCE(SUM(`total_price`), 0) FROM `order_items` WHERE `order_id` = ?
+        )
+        WHERE `id` = ?
+        "#,
+    )
+    .bind(order_id)
+    .bind(order_id)
+    .execute(&mut **transaction)
+    .await?;
+
+    Ok(())
 }
 
 impl OrderItemRecord {
@@ -336,6 +1439,7 @@ impl OrderItemRecord {
             r#"
             SELECT
                 oi.id, oi.order_id, oi.product_id, oi.quantity,
+                oi.quantity_shipped, oi.quantity_backordered,
                 oi.unit_price AS unit_price,
                 oi.total_price AS total_price,
                 oi.created_at,
@@ -344,6 +1448,10 @@ impl OrderItemRecord {
                 p.image_url as product_image_url,
                 p.bin_location as product_bin_location,
                 p.unit_type as product_unit_type,
+                p.price as current_price,
+                p.stock_quantity,
+                p.is_active,
+                p.in_stock,
                 c.name as category_name
             FROM `order_items` oi
             JOIN `products` p ON oi.product_id = p.id
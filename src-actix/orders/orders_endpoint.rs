@@ -1,14 +1,30 @@
-use crate::auth::{jwt_validator, ClaimsExtractor, UserRole};
+use crate::auth::{find_user_by_id, jwt_validator, ClaimsExtractor, EmailService, UserRole};
+use crate::orders::cart_data::{CartRecord, CartWithItemsDto};
+use crate::orders::manifest_pdf::{self, ManifestPdfData};
+use crate::orders::{
+    budget_endpoint, cart_endpoint, export_endpoint, order_template_endpoint, par_level_endpoint,
+    pick_list_endpoint, pick_wave_endpoint, receiving_endpoint,
+};
 use crate::orders::orders_data::{
-    AddToCartRequest, CreateOrderRequest, OrderWithItemsDto, StoreOrderRecord, StoreOrderRecordDto,
-    UpdateOrderStatusRequest, UserContext,
+    decode_order_id_list, CreateOrderItemRequest, CreateOrderRequest, IdempotentOrderResult,
+    OrderCreateResult, OrderEditResult, OrderIdListQuery, OrderWithItemsDto, RecordShipmentRequest,
+    ReorderPlan, ReorderRequest, ReorderTarget, ShipmentResult, StatusChangeResult, StockIssue,
+    StoreOrderRecord, StoreOrderRecordDto, UpdateOrderItemRequest, UpdateOrderStatusRequest,
+    UserContext,
 };
+use crate::orders::store_order_status::StoreOrderStatus;
+use crate::products::barcode_svg;
 use crate::stores::stores_data::StoreRecord;
-use actix_web::{get, post, put, web, HttpRequest, HttpResponse, Responder};
+use actix_web::{delete, get, post, put, web, HttpRequest, HttpResponse, Responder};
 use actix_web_httpauth::middleware::HttpAuthentication;
 use database_common_lib::{database_connection::DatabaseConnectionData, http_error::Result};
+use log::*;
 use serde_json::json;
 use std::collections::BTreeMap;
+/// Header a client sets to make retries of `POST /orders` safe
+const IDEMPOTENCY_KEY_HEADER: &str = "Idempotency-Key";
+/// Set on responses that return an order created by an earlier request
+const IDEMPOTENT_REPLAY_HEADER: &str = "Idempotent-Replayed";
 static MANIFEST_TEMPLATE: &str = include_str!("../../templates/order-manifest-template.html.tera");
 #[get("")]
 pub async fn get_orders(
@@ -121,6 +137,27 @@ pub async fn create_order(
         })));
     }
 
+    // Retried submissions carrying the same key get the order the first one created
+    let idempotency_key = match req.headers().get(IDEMPOTENCY_KEY_HEADER) {
+        Some(value) => match value.to_str().ok().filter(|k| is_valid_idempotency_key(k)) {
+            Some(key) => Some(key.to_string()),
+            None => {
+                return Ok(HttpResponse::BadRequest().json(json!({
+                    "success": false,
+                    "error": "Idempotency-Key must be 1 to 255 visible ASCII characters"
+                })));
+            }
+        },
+        None => None,
+    };
+    if let Some(key) = &idempotency_key {
+        if let Some(order_id) =
+            StoreOrderRecord::find_by_idempotency_key(&pool, claims.sub, key).await?
+        {
+            return replayed_order_response(&pool, order_id).await;
+        }
+    }
+
     // Create user context from JWT claims
     let user_context = UserContext::from_claims(claims.sub, claims.store_id, claims.role.clone());
 
@@ -131,14 +168,42 @@ pub async fn create_order(
         items.push((product_id, item.quantity));
     }
 
-    let order_id = StoreOrderRecord::create_order(
-        &pool,
-        &user_context,
-        store_id,
-        &items,
-        request.notes.as_deref(),
-    )
-    .await?;
+    let (order_id, budget) = match &idempotency_key {
+        Some(key) => match StoreOrderRecord::create_order_idempotent(
+            &pool,
+            &user_context,
+            store_id,
+            &items,
+            request.notes.as_deref(),
+            key,
+        )
+        .await?
+        {
+            IdempotentOrderResult::Created { order_id, budget } => (order_id, budget),
+            IdempotentOrderResult::Replayed(order_id) => {
+                return replayed_order_response(&pool, order_id).await;
+            }
+            IdempotentOrderResult::Rejected(issues) => return Ok(stock_rejected_response(&issues)),
+            IdempotentOrderResult::OverBudget(check) => {
+                return Ok(budget_endpoint::over_budget_response(&check));
+            }
+        },
+        None => match StoreOrderRecord::create_order(
+            &pool,
+            &user_context,
+            store_id,
+            &items,
+            request.notes.as_deref(),
+        )
+        .await?
+        {
+            OrderCreateResult::Created { order_id, budget } => (order_id, budget),
+            OrderCreateResult::Rejected(issues) => return Ok(stock_rejected_response(&issues)),
+            OrderCreateResult::OverBudget(check) => {
+                return Ok(budget_endpoint::over_budget_response(&check));
+            }
+        },
+    };
 
     let order = StoreOrderRecord::get_with_items(&pool, order_id)
         .await?
@@ -147,10 +212,39 @@ pub async fn create_order(
     Ok(HttpResponse::Created().json(json!({
         "success": true,
         "data": dto,
-        "message": "Order created successfully"
+        "budget": budget,
+        "message": order.order.created_message()
     })))
 }
 
+/// Keys are opaque to the server; clients typically send a UUID
+fn is_valid_idempotency_key(key: &str) -> bool {
+    !key.is_empty() && key.len() <= 255 && key.bytes().all(|b| b.is_ascii_graphic())
+}
+
+/// The order an earlier request with the same idempotency key created
+async fn replayed_order_response(pool: &sqlx::MySqlPool, order_id: u64) -> Result<HttpResponse> {
+    let order = StoreOrderRecord::get_with_items(pool, order_id)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("Failed to retrieve original order"))?;
+    Ok(HttpResponse::Ok()
+        .insert_header((IDEMPOTENT_REPLAY_HEADER, "true"))
+        .json(json!({
+            "success": true,
+            "data": OrderWithItemsDto::from(&order),
+            "message": "Order was already created for this Idempotency-Key"
+        })))
+}
+
+/// Response for an order refused because some of its products cannot be filled from stock
+pub(crate) fn stock_rejected_response(issues: &[StockIssue]) -> HttpResponse {
+    HttpResponse::Conflict().json(json!({
+        "success": false,
+        "error": "Some items cannot be ordered",
+        "details": issues
+    }))
+}
+
 #[put("/{id}/status")]
 pub async fn update_order_status(
     req: HttpRequest,
@@ -190,144 +284,555 @@ pub async fn update_order_status(
     }
 
     // Enforce allowed status transitions per role
-    use crate::orders::store_order_status::StoreOrderStatus;
     let target_status = request.status.clone();
 
-    match role {
-        UserRole::Admin => {
-            // Admins can change orders from Pending to Shipped or Delivered only
-            if existing_order.status != StoreOrderStatus::Pending {
-                return Ok(HttpResponse::BadRequest().json(json!({
-                    "success": false,
-                    "error": "Only orders in Pending status can be updated by admin"
-                })));
-            }
-            if !(matches!(
+    // Re-applying the current status is a no-op
+    if existing_order.status == target_status {
+        let current = StoreOrderRecord::get_with_items(&pool, order_id).await?;
+        if let Some(o) = current {
+            let dto = OrderWithItemsDto::from(&o);
+            return Ok(HttpResponse::Ok().json(json!({
+                "success": true,
+                "data": dto,
+                "message": format!("Order already {}", target_status)
+            })));
+        }
+    }
+
+    if existing_order.status.is_terminal() {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "success": false,
+            "error": format!("Order is {} and can no longer change status", existing_order.status)
+        })));
+    }
+
+    if !existing_order.status.can_transition_to(&target_status, &role) {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "success": false,
+            "error": format!(
+                "Cannot change order from {} to {} as {}",
+                existing_order.status,
                 target_status,
-                StoreOrderStatus::Shipped | StoreOrderStatus::Delivered
-            )) {
-                return Ok(HttpResponse::BadRequest().json(json!({
-                    "success": false,
-                    "error": "Admin can only set status to Shipped or Delivered"
-                })));
-            }
+                role.as_str()
+            )
+        })));
+    }
+
+    let result = if target_status == StoreOrderStatus::Cancelled {
+        let reason = request
+            .reason
+            .as_deref()
+            .map(str::trim)
+            .filter(|r| !r.is_empty());
+        let Some(reason) = reason else {
+            return Ok(HttpResponse::BadRequest().json(json!({
+                "success": false,
+                "error": "A reason is required to cancel an order"
+            })));
+        };
+        let result = StoreOrderRecord::cancel(&pool, order_id, claims.sub, &role, reason).await?;
+        // An admin cancelling an order that awaits approval is rejecting it
+        if result == StatusChangeResult::Changed(StoreOrderStatus::AwaitingApproval)
+            && matches!(role, UserRole::Admin)
+        {
+            notify_order_rejected(&pool, &existing_order, reason);
         }
-        UserRole::Store => {
-            // Store can only mark as Delivered
-            if target_status != StoreOrderStatus::Delivered {
-                return Ok(HttpResponse::BadRequest().json(json!({
-                    "success": false,
-                    "error": "Store can only set status to Delivered"
-                })));
-            }
-            // Optional: prevent redundant updates
-            if existing_order.status == StoreOrderStatus::Delivered {
-                let current = StoreOrderRecord::get_with_items(&pool, order_id).await?;
-                if let Some(o) = current {
-                    let dto = OrderWithItemsDto::from(&o);
-                    return Ok(HttpResponse::Ok().json(json!({
-                        "success": true,
-                        "data": dto,
-                        "message": "Order already delivered"
-                    })));
+        result
+    } else {
+        StoreOrderRecord::update_status(
+            &pool,
+            order_id,
+            target_status.clone(),
+            request.notes.as_deref(),
+            claims.sub,
+            &role,
+        )
+        .await?
+    };
+
+    match result {
+        StatusChangeResult::Changed(previous_status) => {
+            let order = StoreOrderRecord::get_with_items(&pool, order_id)
+                .await?
+                .ok_or_else(|| anyhow::anyhow!("Order not found after update"))?;
+            let dto = OrderWithItemsDto::from(&order);
+            Ok(HttpResponse::Ok().json(json!({
+                "success": true,
+                "data": dto,
+                "message": match (&previous_status, &order.order.status) {
+                    (StoreOrderStatus::AwaitingApproval, StoreOrderStatus::Pending) => "Order approved",
+                    (StoreOrderStatus::AwaitingApproval, StoreOrderStatus::Cancelled) => "Order rejected",
+                    _ => "Order status updated successfully",
                 }
-            }
+            })))
+        }
+        StatusChangeResult::OrderNotFound => Ok(HttpResponse::NotFound().json(json!({
+            "success": false,
+            "error": "Order not found"
+        }))),
+        StatusChangeResult::NotAllowed(current_status) => {
+            Ok(status_conflict_response(&current_status, &target_status, &role))
         }
     }
+}
 
-    let updated =
-        StoreOrderRecord::update_status(&pool, order_id, target_status, request.notes.as_deref())
-            .await?;
+/// The order changed status between the read and the locked update and the change no longer applies
+fn status_conflict_response(
+    current_status: &StoreOrderStatus,
+    target_status: &StoreOrderStatus,
+    role: &UserRole,
+) -> HttpResponse {
+    HttpResponse::Conflict().json(json!({
+        "success": false,
+        "error": format!(
+            "Order is now {} and cannot change to {} as {}",
+            current_status,
+            target_status,
+            role.as_str()
+        ),
+        "current_status": current_status,
+    }))
+}
 
-    if updated {
-        let order = StoreOrderRecord::get_with_items(&pool, order_id)
-            .await?
-            .ok_or_else(|| anyhow::anyhow!("Order not found after update"))?;
-        let dto = OrderWithItemsDto::from(&order);
-        Ok(HttpResponse::Ok().json(json!({
-            "success": true,
-            "data": dto,
-            "message": "Order status updated successfully"
-        })))
-    } else {
-        Ok(HttpResponse::NotFound().json(json!({
+/// Emails the user who placed `order` that it was rejected, without holding up the response
+fn notify_order_rejected(pool: &sqlx::MySqlPool, order: &StoreOrderRecord, reason: &str) {
+    let pool = pool.clone();
+    let user_id = order.user_id;
+    let order_number = order.order_number.clone();
+    let reason = reason.to_string();
+    actix_web::rt::spawn(async move {
+        let result: anyhow::Result<()> = async {
+            let Some(user) = find_user_by_id(&pool, user_id).await? else {
+                warn!("User {} who placed order {} no longer exists", user_id, order_number);
+                return Ok(());
+            };
+            EmailService::new()?
+                .send_order_rejected_email(&user.email, &order_number, &reason)
+                .await
+        }
+        .await;
+        if let Err(e) = result {
+            error!("Failed to notify user {} of rejected order {}: {}", user_id, order_number, e);
+        }
+    });
+}
+
+#[get("/{id}/history")]
+pub async fn get_order_history(
+    req: HttpRequest,
+    connection_data: web::Data<DatabaseConnectionData>,
+    path: web::Path<String>,
+) -> Result<impl Responder> {
+    let pool = connection_data.get_pool().await?;
+    let order_id = serde_hash::hashids::decode_single(path.as_str())?;
+
+    let claims = req
+        .get_claims()
+        .ok_or_else(|| anyhow::anyhow!("Authentication required"))?;
+    let role = UserRole::from_str(&claims.role)?;
+
+    let Some(order) = StoreOrderRecord::get_by_id(&pool, order_id).await? else {
+        return Ok(HttpResponse::NotFound().json(json!({
             "success": false,
             "error": "Order not found"
-        })))
+        })));
+    };
+
+    if matches!(role, UserRole::Store) && claims.store_id != Some(order.store_id) {
+        return Ok(HttpResponse::Forbidden().json(json!({
+            "success": false,
+            "error": "Access denied: You can only view orders for your store"
+        })));
     }
+
+    // The manifest is public: who changed the status and why stays on the authenticated
+    // `/{id}/history` endpoint
+    let history = StoreOrderRecord::get_status_history(&pool, order_id).await?;
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "data": history
+    })))
 }
 
-#[post("/cart/add")]
-pub async fn add_to_cart(
+#[post("/{id}/items")]
+pub async fn add_order_item(
     req: HttpRequest,
     connection_data: web::Data<DatabaseConnectionData>,
-    request: web::Json<AddToCartRequest>,
+    path: web::Path<String>,
+    request: web::Json<CreateOrderItemRequest>,
 ) -> Result<impl Responder> {
     let pool = connection_data.get_pool().await?;
+    let order_id = serde_hash::hashids::decode_single(path.as_str())?;
+    let product_id = serde_hash::hashids::decode_single(&request.product_id)?;
+
+    if request.quantity <= 0 {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "success": false,
+            "error": "Quantity must be greater than zero"
+        })));
+    }
+
+    if let Some(denied) = check_order_edit_access(&req, &pool, order_id).await? {
+        return Ok(denied);
+    }
 
     let claims = req
         .get_claims()
         .ok_or_else(|| anyhow::anyhow!("Authentication required"))?;
+    let role = UserRole::from_str(&claims.role)?;
+    let result = StoreOrderRecord::add_item(
+        &pool,
+        order_id,
+        product_id,
+        request.quantity,
+        claims.sub,
+        &role,
+    )
+    .await?;
+    order_edit_response(&pool, order_id, result).await
+}
 
-    let product_id = serde_hash::hashids::decode_single(&request.product_id)?;
+#[put("/{id}/items/{item_id}")]
+pub async fn update_order_item(
+    req: HttpRequest,
+    connection_data: web::Data<DatabaseConnectionData>,
+    path: web::Path<(String, String)>,
+    request: web::Json<UpdateOrderItemRequest>,
+) -> Result<impl Responder> {
+    let pool = connection_data.get_pool().await?;
+    let (order_hash, item_hash) = path.into_inner();
+    let order_id = serde_hash::hashids::decode_single(&order_hash)?;
+    let item_id = serde_hash::hashids::decode_single(&item_hash)?;
+
+    if let Some(denied) = check_order_edit_access(&req, &pool, order_id).await? {
+        return Ok(denied);
+    }
 
-    // In a real implementation, you might have a separate cart table
-    // For now, we'll just validate the product exists and return success
-    let product_exists = sqlx::query(
-        "SELECT id FROM products WHERE id = ? AND is_active = TRUE AND in_stock = TRUE",
+    let claims = req
+        .get_claims()
+        .ok_or_else(|| anyhow::anyhow!("Authentication required"))?;
+    let role = UserRole::from_str(&claims.role)?;
+    let result = StoreOrderRecord::set_item_quantity(
+        &pool,
+        order_id,
+        item_id,
+        request.quantity,
+        claims.sub,
+        &role,
     )
-    .bind(product_id)
-    .fetch_optional(&pool)
     .await?;
+    order_edit_response(&pool, order_id, result).await
+}
 
-    if product_exists.is_some() {
-        Ok(HttpResponse::Ok().json(json!({
-            "success": true,
-            "message": "Product added to cart successfully",
-            "data": {
-                "product_id": request.product_id,
-                "quantity": request.quantity,
-                "user_id": serde_hash::hashids::encode_single(claims.sub)
-            }
-        })))
-    } else {
-        Ok(HttpResponse::BadRequest().json(json!({
+#[delete("/{id}/items/{item_id}")]
+pub async fn remove_order_item(
+    req: HttpRequest,
+    connection_data: web::Data<DatabaseConnectionData>,
+    path: web::Path<(String, String)>,
+) -> Result<impl Responder> {
+    let pool = connection_data.get_pool().await?;
+    let (order_hash, item_hash) = path.into_inner();
+    let order_id = serde_hash::hashids::decode_single(&order_hash)?;
+    let item_id = serde_hash::hashids::decode_single(&item_hash)?;
+
+    if let Some(denied) = check_order_edit_access(&req, &pool, order_id).await? {
+        return Ok(denied);
+    }
+
+    let claims = req
+        .get_claims()
+        .ok_or_else(|| anyhow::anyhow!("Authentication required"))?;
+    let result = StoreOrderRecord::remove_item(&pool, order_id, item_id, claims.sub).await?;
+    order_edit_response(&pool, order_id, result).await
+}
+
+#[post("/{id}/shipments")]
+pub async fn record_shipment(
+    req: HttpRequest,
+    connection_data: web::Data<DatabaseConnectionData>,
+    path: web::Path<String>,
+    request: web::Json<RecordShipmentRequest>,
+) -> Result<impl Responder> {
+    let pool = connection_data.get_pool().await?;
+    let order_id = serde_hash::hashids::decode_single(path.as_str())?;
+
+    let claims = req
+        .get_claims()
+        .ok_or_else(|| anyhow::anyhow!("Authentication required"))?;
+    if claims.role != "admin" {
+        return Ok(HttpResponse::Forbidden().json(json!({
             "success": false,
-            "error": "Product not found or out of stock"
-        })))
+            "error": "Admin access required"
+        })));
+    }
+
+    let mut lines = Vec::with_capacity(request.items.len());
+    for line in &request.items {
+        let item_id = serde_hash::hashids::decode_single(&line.item_id)?;
+        lines.push((item_id, line.quantity));
+    }
+
+    let result = StoreOrderRecord::record_shipment(
+        &pool,
+        order_id,
+        &lines,
+        request.backorder_remaining,
+        request.create_backorder_order,
+        claims.sub,
+        request.notes.as_deref(),
+    )
+    .await?;
+
+    match result {
+        ShipmentResult::Recorded { backorder_order_id } => {
+            let order = StoreOrderRecord::get_with_items(&pool, order_id)
+                .await?
+                .ok_or_else(|| anyhow::anyhow!("Order not found after update"))?;
+            let backorder = match backorder_order_id {
+                Some(id) => StoreOrderRecord::get_with_items(&pool, id)
+                    .await?
+                    .map(|o| OrderWithItemsDto::from(&o)),
+                None => None,
+            };
+            Ok(HttpResponse::Ok().json(json!({
+                "success": true,
+                "data": OrderWithItemsDto::from(&order),
+                "backorder": backorder,
+                "message": "Shipment recorded successfully"
+            })))
+        }
+        ShipmentResult::OrderNotFound => Ok(HttpResponse::NotFound().json(json!({
+            "success": false,
+            "error": "Order not found"
+        }))),
+        ShipmentResult::NotShippable(status) => Ok(HttpResponse::Conflict().json(json!({
+            "success": false,
+            "error": format!("Order is {} and cannot be shipped", status)
+        }))),
+        ShipmentResult::InvalidLines(errors) => Ok(HttpResponse::BadRequest().json(json!({
+            "success": false,
+            "error": "Shipment could not be recorded",
+            "details": errors
+        }))),
     }
 }
 
-#[get("/{id}/manifest")]
-pub async fn get_order_manifest(
+/// Copies an order's lines into a new Pending order, or into the caller's cart, at current prices
+#[post("/{id}/reorder")]
+pub async fn reorder(
+    req: HttpRequest,
     connection_data: web::Data<DatabaseConnectionData>,
     path: web::Path<String>,
+    request: Option<web::Json<ReorderRequest>>,
 ) -> Result<impl Responder> {
     let pool = connection_data.get_pool().await?;
     let order_id = serde_hash::hashids::decode_single(path.as_str())?;
+    let request = request.map(|r| r.into_inner()).unwrap_or_default();
 
-    let order = match StoreOrderRecord::get_with_items(&pool, order_id).await? {
-        Some(order) => OrderWithItemsDto::from(&order),
-        None => {
-            return Ok(HttpResponse::NotFound().json(json!({
-                "success": false,
-                "error": "Order not found"
+    if let Some(denied) = check_order_edit_access(&req, &pool, order_id).await? {
+        return Ok(denied);
+    }
+    let claims = req
+        .get_claims()
+        .ok_or_else(|| anyhow::anyhow!("Authentication required"))?;
+
+    let order = StoreOrderRecord::get_with_items(&pool, order_id)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("Order not found"))?;
+    let plan = ReorderPlan::build(&order.items);
+    if plan.lines.is_empty() {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "success": false,
+            "error": "None of the products on this order can be ordered right now",
+            "adjustments": plan.adjustments
+        })));
+    }
+
+    match request.target {
+        ReorderTarget::Cart => {
+            let cart = CartRecord::get_or_create(&pool, claims.sub, claims.store_id).await?;
+            for (product_id, quantity, unit_price) in &plan.lines {
+                cart.add_item(&pool, *product_id, *quantity, *unit_price).await?;
+            }
+            let cart = CartRecord::get_with_items(&pool, claims.sub, claims.store_id).await?;
+            Ok(HttpResponse::Ok().json(json!({
+                "success": true,
+                "data": CartWithItemsDto::from(&cart),
+                "adjustments": plan.adjustments,
+                "message": "Order lines added to cart"
+            })))
+        }
+        ReorderTarget::Order => {
+            let user_context =
+                UserContext::from_claims(claims.sub, claims.store_id, claims.role.clone());
+            let items: Vec<(u64, i32)> = plan
+                .lines
+                .iter()
+                .map(|(product_id, quantity, _)| (*product_id, *quantity))
+                .collect();
+            let notes = request
+                .notes
+                .unwrap_or_else(|| format!("Reorder of {}", order.order.order_number));
+            let (new_order_id, budget) = match StoreOrderRecord::create_order(
+                &pool,
+                &user_context,
+                order.order.store_id,
+                &items,
+                Some(&notes),
+            )
+            .await?
+            {
+                OrderCreateResult::Created { order_id, budget } => (order_id, budget),
+                OrderCreateResult::Rejected(issues) => return Ok(stock_rejected_response(&issues)),
+                OrderCreateResult::OverBudget(check) => {
+                    return Ok(budget_endpoint::over_budget_response(&check));
+                }
+            };
+            let new_order = StoreOrderRecord::get_with_items(&pool, new_order_id)
+                .await?
+                .ok_or_else(|| anyhow::anyhow!("Failed to retrieve created order"))?;
+            Ok(HttpResponse::Created().json(json!({
+                "success": true,
+                "data": OrderWithItemsDto::from(&new_order),
+                "adjustments": plan.adjustments,
+                "budget": budget,
+                "message": new_order.order.created_message()
             })))
         }
+    }
+}
+
+/// Store users always work on their own store; admins must name one.
+pub(crate) fn resolve_store_id(
+    req: &HttpRequest,
+    requested: Option<&str>,
+) -> Result<std::result::Result<u64, HttpResponse>> {
+    let claims = req
+        .get_claims()
+        .ok_or_else(|| anyhow::anyhow!("Authentication required"))?;
+    let requested_store_id = match requested {
+        Some(hash) => Some(serde_hash::hashids::decode_single(hash)?),
+        None => None,
     };
-    // Fetch store display name
-    let store_rec = StoreRecord::get_by_id(&pool, order.order.store_id).await?;
-    let store_name = if let Some(s) = store_rec {
-        match (s.city.as_deref(), s.address.as_deref()) {
-            (Some(city), Some(addr)) if !city.is_empty() && !addr.is_empty() => {
-                format!("{} - {}", city, addr)
+
+    let role = UserRole::from_str(&claims.role)?;
+    let store_id = match role {
+        UserRole::Store => {
+            if requested_store_id.is_some() && requested_store_id != claims.store_id {
+                return Ok(Err(HttpResponse::Forbidden().json(json!({
+                    "success": false,
+                    "error": "Access denied: You can only access your own store"
+                }))));
             }
-            (Some(city), _) if !city.is_empty() => city.to_string(),
-            (_, Some(addr)) if !addr.is_empty() => addr.to_string(),
-            _ => format!("Store {}", order.order.store_id),
+            claims.store_id
         }
-    } else {
-        format!("Store {}", order.order.store_id)
+        UserRole::Admin => requested_store_id,
+    };
+    match store_id {
+        Some(store_id) => Ok(Ok(store_id)),
+        None => Ok(Err(HttpResponse::BadRequest().json(json!({
+            "success": false,
+            "error": "A store is required"
+        })))),
+    }
+}
+
+/// Store users may only edit orders for their own store; admins may edit any order.
+pub(crate) async fn check_order_edit_access(
+    req: &HttpRequest,
+    pool: &sqlx::MySqlPool,
+    order_id: u64,
+) -> Result<Option<HttpResponse>> {
+    let claims = req
+        .get_claims()
+        .ok_or_else(|| anyhow::anyhow!("Authentication required"))?;
+    let role = UserRole::from_str(&claims.role)?;
+
+    let Some(order) = StoreOrderRecord::get_by_id(pool, order_id).await? else {
+        return Ok(Some(HttpResponse::NotFound().json(json!({
+            "success": false,
+            "error": "Order not found"
+        }))));
+    };
+
+    if matches!(role, UserRole::Store) && claims.store_id != Some(order.store_id) {
+        return Ok(Some(HttpResponse::Forbidden().json(json!({
+            "success": false,
+            "error": "Access denied: You can only update orders for your store"
+        }))));
+    }
+
+    Ok(None)
+}
+
+async fn order_edit_response(
+    pool: &sqlx::MySqlPool,
+    order_id: u64,
+    result: OrderEditResult,
+) -> Result<HttpResponse> {
+    match result {
+        OrderEditResult::Updated => {
+            let order = StoreOrderRecord::get_with_items(pool, order_id)
+                .await?
+                .ok_or_else(|| anyhow::anyhow!("Order not found after update"))?;
+            let message = if order.order.status == StoreOrderStatus::AwaitingApproval {
+                "Order updated and sent for approval: its total is above the store's approval limit"
+            } else {
+                "Order updated successfully"
+            };
+            let dto = OrderWithItemsDto::from(&order);
+            Ok(HttpResponse::Ok().json(json!({
+                "success": true,
+                "data": dto,
+                "message": message
+            })))
+        }
+        OrderEditResult::OrderNotFound => Ok(HttpResponse::NotFound().json(json!({
+            "success": false,
+            "error": "Order not found"
+        }))),
+        OrderEditResult::NotEditable(status) => Ok(HttpResponse::Conflict().json(json!({
+            "success": false,
+            "error": format!("Order is {} and can no longer be edited", status)
+        }))),
+        OrderEditResult::ItemNotFound => Ok(HttpResponse::NotFound().json(json!({
+            "success": false,
+            "error": "Order item not found"
+        }))),
+        OrderEditResult::Rejected(issues) => Ok(stock_rejected_response(&issues)),
+        OrderEditResult::OverBudget(check) => Ok(budget_endpoint::over_budget_response(&check)),
+    }
+}
+
+/// Loads an order with its items and the display name of its store
+async fn load_manifest(pool: &sqlx::MySqlPool, order_id: u64) -> Result<Option<ManifestPdfData>> {
+    let Some(order) = StoreOrderRecord::get_with_items(pool, order_id).await? else {
+        return Ok(None);
+    };
+    let order = OrderWithItemsDto::from(&order);
+
+    // Fetch store display name
+    let store_rec = StoreRecord::get_by_id(pool, order.order.store_id).await?;
+    let store_name = match store_rec {
+        Some(s) => s.display_name(),
+        None => format!("Store {}", order.order.store_id),
+    };
+    Ok(Some(ManifestPdfData { store_name, order }))
+}
+
+#[get("/{id}/manifest")]
+pub async fn get_order_manifest(
+    connection_data: web::Data<DatabaseConnectionData>,
+    path: web::Path<String>,
+) -> Result<impl Responder> {
+    let pool = connection_data.get_pool().await?;
+    let order_id = serde_hash::hashids::decode_single(path.as_str())?;
+
+    let Some(ManifestPdfData { store_name, order }) = load_manifest(&pool, order_id).await? else {
+        return Ok(HttpResponse::NotFound().json(json!({
+            "success": false,
+            "error": "Order not found"
+        })));
     };
 
     // Group items by category name (sorted)
@@ -344,19 +849,109 @@ pub async fn get_order_manifest(
         .map(|(cat, items)| serde_json::json!({"category_name": cat, "items": items}))
         .collect();
 
+    let history = StoreOrderRecord::get_status_history(&pool, order_id).await?;
+
     // Build Tera context
     let mut ctx = tera::Context::new();
     ctx.insert("store_name", &store_name);
     ctx.insert("dto", &order);
     ctx.insert("groups", &groups);
+    ctx.insert("history", &history);
 
     let mut tera = tera::Tera::default();
+    barcode_svg::register_tera_filter(&mut tera);
     tera.add_raw_template("order-manifest-template", MANIFEST_TEMPLATE)
         .unwrap();
     let manifest = tera.render("order-manifest-template", &ctx).unwrap();
     Ok(HttpResponse::Ok().content_type("text/html").body(manifest))
 }
 
+#[get("/{id}/manifest.pdf")]
+pub async fn get_order_manifest_pdf(
+    req: HttpRequest,
+    connection_data: web::Data<DatabaseConnectionData>,
+    path: web::Path<String>,
+) -> Result<impl Responder> {
+    let claims = req
+        .get_claims()
+        .ok_or_else(|| anyhow::anyhow!("Authentication required"))?;
+    let pool = connection_data.get_pool().await?;
+    let order_id = serde_hash::hashids::decode_single(path.as_str())?;
+
+    let Some(manifest) = load_manifest(&pool, order_id).await? else {
+        return Ok(HttpResponse::NotFound().json(json!({
+            "success": false,
+            "error": "Order not found"
+        })));
+    };
+    if claims.role != "admin" && claims.store_id != Some(manifest.order.order.store_id) {
+        return Ok(HttpResponse::Forbidden().json(json!({
+            "success": false,
+            "error": "Access denied: You can only view orders for your store"
+        })));
+    }
+
+    let file_name = format!("{}.pdf", manifest.order.order.order_number);
+    let pdf = manifest_pdf::render_manifests(&file_name, &[manifest])?;
+    Ok(pdf_response(&file_name, pdf))
+}
+
+/// Several manifests in one PDF, each order starting on a new page,
+/// e.g. `/api/orders/manifests.pdf?orders=abc,def`. Store users only get their own store's
+/// orders; the others are left out.
+#[get("/manifests.pdf")]
+pub async fn get_order_manifests_pdf(
+    req: HttpRequest,
+    connection_data: web::Data<DatabaseConnectionData>,
+    query: web::Query<OrderIdListQuery>,
+) -> Result<impl Responder> {
+    let claims = req
+        .get_claims()
+        .ok_or_else(|| anyhow::anyhow!("Authentication required"))?;
+    let pool = connection_data.get_pool().await?;
+
+    let order_ids = decode_order_id_list(&query.orders)?;
+    if order_ids.is_empty() {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "success": false,
+            "error": "At least one order is required"
+        })));
+    }
+
+    let mut manifests = Vec::with_capacity(order_ids.len());
+    for order_id in order_ids {
+        let Some(manifest) = load_manifest(&pool, order_id).await? else {
+            return Ok(HttpResponse::NotFound().json(json!({
+                "success": false,
+                "error": format!("Order {} not found", serde_hash::hashids::encode_single(order_id))
+            })));
+        };
+        if claims.role == "admin" || claims.store_id == Some(manifest.order.order.store_id) {
+            manifests.push(manifest);
+        }
+    }
+    if manifests.is_empty() {
+        return Ok(HttpResponse::NotFound().json(json!({
+            "success": false,
+            "error": "None of the orders belong to your store"
+        })));
+    }
+
+    let file_name = format!("manifests-{}.pdf", chrono::Utc::now().format("%Y%m%d"));
+    let pdf = manifest_pdf::render_manifests(&file_name, &manifests)?;
+    Ok(pdf_response(&file_name, pdf))
+}
+
+fn pdf_response(file_name: &str, pdf: Vec<u8>) -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("application/pdf")
+        .insert_header((
+            "Content-Disposition",
+            format!("inline; filename=\"{}\"", file_name),
+        ))
+        .body(pdf)
+}
+
 pub fn configure(cfg: &mut web::ServiceConfig) {
     let auth = HttpAuthentication::bearer(jwt_validator);
 
@@ -366,12 +961,60 @@ pub fn configure(cfg: &mut web::ServiceConfig) {
             .service(
                 web::scope("")
                     .wrap(auth)
+                    // Orders, shipments, receipts, par levels and templates carry one entry per
+                    // line, which outgrows the global limit at a few dozen lines. The routes share
+                    // this scope so `/{id}` is matched last; the limit is set for all of them.
+                    .app_data(crate::json_config(64 * 1024))
+                    // Cart, manifest, pick list, discrepancy and wave routes are registered first so they are not captured by `/{id}`
+                    .service(cart_endpoint::get_cart)
+                    .service(cart_endpoint::add_to_cart)
+                    .service(cart_endpoint::update_cart_item)
+                    .service(cart_endpoint::remove_cart_item)
+                    .service(cart_endpoint::clear_cart)
+                    .service(cart_endpoint::checkout_cart)
+                    .service(get_order_manifests_pdf)
+                    .service(get_order_manifest_pdf)
+                    .service(pick_list_endpoint::get_pick_list)
+                    .service(receiving_endpoint::get_discrepancies)
+                    .service(receiving_endpoint::resolve_discrepancy)
+                    .service(pick_wave_endpoint::get_waves)
+                    .service(pick_wave_endpoint::get_wave)
+                    .service(pick_wave_endpoint::get_wave_pick_list)
+                    .service(pick_wave_endpoint::get_wave_packing_lists)
+                    .service(pick_wave_endpoint::create_wave)
+                    .service(pick_wave_endpoint::close_wave)
+                    .service(export_endpoint::export_orders)
+                    .service(order_template_endpoint::get_templates)
+                    .service(order_template_endpoint::get_upcoming_template_orders)
+                    .service(order_template_endpoint::get_template)
+                    .service(order_template_endpoint::create_template)
+                    .service(order_template_endpoint::update_template)
+                    .service(order_template_endpoint::delete_template)
+                    .service(order_template_endpoint::get_next_occurrence)
+                    .service(order_template_endpoint::edit_next_occurrence)
+                    .service(order_template_endpoint::skip_next_occurrence)
+                    .service(par_level_endpoint::get_par_levels)
+                    .service(par_level_endpoint::set_par_levels)
+                    .service(par_level_endpoint::delete_par_level)
+                    .service(par_level_endpoint::get_suggested_order)
+                    .service(par_level_endpoint::accept_suggested_order)
+                    .service(budget_endpoint::get_budget)
+                    .service(budget_endpoint::save_budget)
+                    .service(budget_endpoint::delete_budget)
+                    .service(budget_endpoint::get_budget_spend)
                     .service(get_orders)
                     .service(get_store_orders)
                     .service(get_order)
+                    .service(get_order_history)
                     .service(create_order)
                     .service(update_order_status)
-                    .service(add_to_cart),
+                    .service(add_order_item)
+                    .service(update_order_item)
+                    .service(remove_order_item)
+                    .service(record_shipment)
+                    .service(reorder)
+                    .service(receiving_endpoint::receive_order)
+                    .service(receiving_endpoint::get_order_receipts),
             )
             .default_service(web::to(|| async {
                 HttpResponse::NotFound().json(json!({ "error": "API endpoint not found" }))
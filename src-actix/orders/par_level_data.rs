@@ -0,0 +1,234 @@
+use crate::orders::orders_data::ReorderTarget;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use serde_hash::HashIds;
+use sqlx::FromRow;
+
+/// A store's min/max stock target for one product
+#[derive(HashIds, Debug, Clone, FromRow)]
+pub struct ParLevelRecord {
+    #[hash]
+    pub store_id: u64,
+    #[hash]
+    pub product_id: u64,
+    /// Reorder point: a suggestion is made once projected stock is at or below it
+    pub min_quantity: i32,
+    /// Par: suggestions top projected stock back up to this
+    pub max_quantity: i32,
+    pub product_name: String,
+    pub product_sku: String,
+    pub case_pack_size: i32,
+    #[hash]
+    pub updated_by: Option<u64>,
+    pub updated_at: chrono::NaiveDateTime,
+}
+
+/// A par'd product with what the store has recently received and still has on order
+#[derive(Debug, Clone, FromRow)]
+pub struct ParLevelUsageRow {
+    pub product_id: u64,
+    pub min_quantity: i32,
+    pub max_quantity: i32,
+    pub product_name: String,
+    pub product_sku: String,
+    pub case_pack_size: i32,
+    pub current_price: Decimal,
+    pub is_active: bool,
+    pub in_stock: bool,
+    /// Outstanding quantity on the store's open orders
+    pub on_order: i64,
+}
+
+/// One delivered order line, used to estimate consumption
+#[derive(Debug, Clone, FromRow)]
+pub struct DeliveredLineRow {
+    pub product_id: u64,
+    pub delivered_at: chrono::NaiveDateTime,
+    pub quantity: i64,
+}
+
+/// Consumption history for one product over the lookback window
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ParUsage {
+    /// Units delivered in the window, spread evenly over it
+    pub daily_usage: f64,
+    /// Size of and days since the most recent delivery, if there was one in the window
+    pub last_delivery: Option<(i32, i64)>,
+    pub on_order: i32,
+}
+
+impl ParUsage {
+    /// Summarises one product's deliveries from the last `window_days` days
+    pub fn from_deliveries(
+        deliveries: &[&DeliveredLineRow],
+        on_order: i64,
+        window_days: u32,
+        now: chrono::NaiveDateTime,
+    ) -> Self {
+        let total: i64 = deliveries.iter().map(|d| d.quantity).sum();
+        // Several orders delivered at the same moment count as one delivery
+        let last_delivery = deliveries.iter().map(|d| d.delivered_at).max().map(|last| {
+            let quantity: i64 = deliveries
+                .iter()
+                .filter(|d| d.delivered_at == last)
+                .map(|d| d.quantity)
+                .sum();
+            (quantity as i32, (now - last).num_days().max(0))
+        });
+        Self {
+            daily_usage: total as f64 / window_days.max(1) as f64,
+            last_delivery,
+            on_order: on_order as i32,
+        }
+    }
+
+    /// What the store probably still has: the last delivery less what it used since,
+    /// plus everything already on order. Without delivery history the shelf is assumed empty.
+    pub fn projected_stock(&self) -> i32 {
+        let on_hand = match self.last_delivery {
+            Some((quantity, days_since)) => {
+                (quantity as f64 - self.daily_usage * days_since as f64).max(0.0).round() as i32
+            }
+            None => 0,
+        };
+        on_hand + self.on_order
+    }
+}
+
+/// Quantity to order to bring `projected` back up to `max`, in whole case packs.
+///
+/// Nothing is suggested above the reorder point. Otherwise the largest number of packs that
+/// stays within par is used, but at least one pack.
+pub fn suggest_quantity(min: i32, max: i32, case_pack_size: i32, projected: i32) -> i32 {
+    if projected > min {
+        return 0;
+    }
+    let pack = case_pack_size.max(1);
+    let needed = (max - projected).max(0);
+    (needed / pack).max(1) * pack
+}
+
+#[derive(Debug, Serialize)]
+pub struct OrderSuggestion {
+    pub product_id: String,
+    pub product_name: String,
+    pub product_sku: String,
+    pub min_quantity: i32,
+    pub max_quantity: i32,
+    pub case_pack_size: i32,
+    pub daily_usage: f64,
+    pub last_delivered_quantity: Option<i32>,
+    pub days_since_last_delivery: Option<i64>,
+    pub on_order: i32,
+    pub projected_stock: i32,
+    pub suggested_quantity: i32,
+    pub unit_price: f64,
+    pub line_total: f64,
+    /// Inactive and out-of-stock products are reported but cannot be ordered
+    pub orderable: bool,
+}
+
+impl OrderSuggestion {
+    pub fn build(row: &ParLevelUsageRow, usage: &ParUsage) -> Self {
+        let projected_stock = usage.projected_stock();
+        let suggested_quantity = suggest_quantity(
+            row.min_quantity,
+            row.max_quantity,
+            row.case_pack_size,
+            projected_stock,
+        );
+        Self {
+            product_id: serde_hash::hashids::encode_single(row.product_id),
+            product_name: row.product_name.clone(),
+            product_sku: row.product_sku.clone(),
+            min_quantity: row.min_quantity,
+            max_quantity: row.max_quantity,
+            case_pack_size: row.case_pack_size,
+            daily_usage: (usage.daily_usage * 100.0).round() / 100.0,
+            last_delivered_quantity: usage.last_delivery.map(|(quantity, _)| quantity),
+            days_since_last_delivery: usage.last_delivery.map(|(_, days)| days),
+            on_order: usage.on_order,
+            projected_stock,
+            suggested_quantity,
+            unit_price: row.current_price.to_f64().unwrap_or(0.0),
+            line_total: (row.current_price * Decimal::from(suggested_quantity))
+                .to_f64()
+                .unwrap_or(0.0),
+            orderable: row.is_active && row.in_stock,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ParLevelRequest {
+    pub product_id: String, // hashed ID
+    pub min_quantity: i32,
+    pub max_quantity: i32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SetParLevelsRequest {
+    pub store_id: Option<String>, // hashed ID, required for admins
+    pub levels: Vec<ParLevelRequest>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ParLevelQuery {
+    pub store_id: Option<String>, // hashed ID, required for admins
+    /// Days of delivery history to estimate consumption from, defaults to 90
+    pub days: Option<u32>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SuggestionLineRequest {
+    pub product_id: String, // hashed ID
+    pub quantity: i32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AcceptSuggestionsRequest {
+    pub store_id: Option<String>, // hashed ID, required for admins
+    #[serde(default)]
+    pub target: ReorderTarget,
+    /// Adjusted lines to order; omit to take every suggestion as proposed
+    pub lines: Option<Vec<SuggestionLineRequest>>,
+    pub notes: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn suggestions_round_down_to_packs_within_par() {
+        // Above the reorder point: nothing
+        assert_eq!(suggest_quantity(10, 40, 6, 11), 0);
+        // 40 - 10 = 30 needed, 5 packs of 6
+        assert_eq!(suggest_quantity(10, 40, 6, 10), 30);
+        // 40 - 8 = 32 needed, 5 packs of 6 stays within par
+        assert_eq!(suggest_quantity(10, 40, 6, 8), 30);
+        // Less than a pack needed still orders one
+        assert_eq!(suggest_quantity(10, 12, 24, 4), 24);
+        // Loose items
+        assert_eq!(suggest_quantity(2, 5, 1, 0), 5);
+    }
+
+    #[test]
+    fn projected_stock_subtracts_usage_since_last_delivery() {
+        let usage = ParUsage {
+            daily_usage: 2.0,
+            last_delivery: Some((30, 10)),
+            on_order: 6,
+        };
+        assert_eq!(usage.projected_stock(), 16);
+
+        let used_up = ParUsage {
+            daily_usage: 5.0,
+            last_delivery: Some((30, 10)),
+            on_order: 0,
+        };
+        assert_eq!(used_up.projected_stock(), 0);
+        assert_eq!(ParUsage::default().projected_stock(), 0);
+    }
+}
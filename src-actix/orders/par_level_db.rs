@@ -0,0 +1,189 @@
+use crate::orders::par_level_data::{
+    DeliveredLineRow, OrderSuggestion, ParLevelRecord, ParLevelUsageRow, ParUsage,
+};
+use crate::orders::store_order_status::StoreOrderStatus;
+use sqlx::{Executor, MySqlPool};
+use std::collections::HashMap;
+
+pub async fn initialize(pool: &MySqlPool) -> anyhow::Result<()> {
+    pool.execute(
+        r#"
+        CREATE TABLE IF NOT EXISTS `store_par_levels` (
+            `id` BIGINT UNSIGNED NOT NULL AUTO_INCREMENT,
+            `store_id` BIGINT UNSIGNED NOT NULL,
+            `product_id` BIGINT UNSIGNED NOT NULL,
+            `min_quantity` INT NOT NULL,
+            `max_quantity` INT NOT NULL,
+            `updated_by` BIGINT UNSIGNED,
+            `updated_at` DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+            PRIMARY KEY (`id`),
+            UNIQUE KEY `uq_store_product` (`store_id`, `product_id`),
+            FOREIGN KEY (`product_id`) REFERENCES `products`(`id`) ON DELETE CASCADE
+        )
+        "#,
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Quoted DB values of `statuses`, for an `IN (...)` list
+fn status_list(statuses: &[StoreOrderStatus]) -> String {
+    statuses
+        .iter()
+        .map(|s| format!("'{}'", s.as_db_str()))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+impl ParLevelRecord {
+    pub async fn get_for_store(pool: &MySqlPool, store_id: u64) -> anyhow::Result<Vec<Self>> {
+        let levels = sqlx::query_as::<_, Self>(
+            r#"
+            SELECT pl.store_id, pl.product_id, pl.min_quantity, pl.max_quantity,
+                   p.name AS product_name,
+                   p.sku AS product_sku,
+                   p.case_pack_size,
+                   pl.updated_by, pl.updated_at
+            FROM `store_par_levels` pl
+            JOIN `products` p ON pl.product_id = p.id
+            WHERE pl.store_id = ?
+            ORDER BY p.name ASC
+            "#,
+        )
+        .bind(store_id)
+        .fetch_all(pool)
+        .await?;
+        Ok(levels)
+    }
+
+    /// Creates or replaces the par levels of several products at once
+    pub async fn set_many(
+        pool: &MySqlPool,
+        store_id: u64,
+        levels: &[(u64, i32, i32)], // (product_id, min, max)
+        updated_by: u64,
+    ) -> anyhow::Result<()> {
+        let mut transaction = pool.begin().await?;
+        for (product_id, min_quantity, max_quantity) in levels {
+            sqlx::query(
+                r#"
+                INSERT INTO `store_par_levels` (`store_id`, `product_id`, `min_quantity`, `max_quantity`, `updated_by`)
+                VALUES (?, ?, ?, ?, ?)
+                ON DUPLICATE KEY UPDATE
+                    `min_quantity` = VALUES(`min_quantity`),
+                    `max_quantity` = VALUES(`max_quantity`),
+                    `updated_by` = VALUES(`updated_by`)
+                "#,
+            )
+            .bind(store_id)
+            .bind(product_id)
+            .bind(min_quantity)
+            .bind(max_quantity)
+            .bind(updated_by)
+            .execute(&mut *transaction)
+            .await?;
+        }
+        transaction.commit().await?;
+        Ok(())
+    }
+
+    pub async fn delete(pool: &MySqlPool, store_id: u64, product_id: u64) -> anyhow::Result<bool> {
+        let result =
+            sqlx::query("DELETE FROM `store_par_levels` WHERE `store_id` = ? AND `product_id` = ?")
+                .bind(store_id)
+                .bind(product_id)
+                .execute(pool)
+                .await?;
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+impl OrderSuggestion {
+    /// Suggested quantities for every par'd product of a store, from the deliveries of the
+    /// last `window_days` days and what is already on order.
+    pub async fn for_store(
+        pool: &MySqlPool,
+        store_id: u64,
+        window_days: u32,
+    ) -> anyhow::Result<Vec<Self>> {
+        // Shipped orders are in transit with what shipped; earlier ones with what is outstanding
+        let open_statuses = status_list(&[
+            StoreOrderStatus::AwaitingApproval,
+            StoreOrderStatus::Pending,
+            StoreOrderStatus::OnHold,
+            StoreOrderStatus::Picking,
+            StoreOrderStatus::Packed,
+        ]);
+        let rows = sqlx::query_as::<_, ParLevelUsageRow>(&format!(
+            r#"
+            SELECT pl.product_id, pl.min_quantity, pl.max_quantity,
+                   p.name AS product_name,
+                   p.sku AS product_sku,
+                   p.case_pack_size,
+                   p.price AS current_price,
+                   p.is_active, p.in_stock,
+                   CAST(COALESCE((
+                       SELECT SUM(CASE
+                           WHEN o.status = '{shipped}' THEN oi.quantity_shipped
+                           ELSE GREATEST(oi.quantity - oi.quantity_shipped - oi.quantity_backordered, 0)
+                       END)
+                       FROM `order_items` oi
+                       JOIN `orders` o ON oi.order_id = o.id
+                       WHERE o.store_id = pl.store_id AND oi.product_id = pl.product_id
+                         AND o.status IN ({open_statuses}, '{shipped}')
+                   ), 0) AS SIGNED) AS on_order
+            FROM `store_par_levels` pl
+            JOIN `products` p ON pl.product_id = p.id
+            WHERE pl.store_id = ?
+            ORDER BY p.name ASC
+            "#,
+            shipped = StoreOrderStatus::Shipped.as_db_str(),
+            open_statuses = open_statuses,
+        ))
+        .bind(store_id)
+        .fetch_all(pool)
+        .await?;
+
+        // Received quantity is what shipped, or what was not backordered if no shipment was recorded
+        let deliveries = sqlx::query_as::<_, DeliveredLineRow>(&format!(
+            r#"
+            SELECT oi.product_id,
+                   COALESCE(o.status_changed_to_completed, o.updated_at) AS delivered_at,
+                   CAST(CASE
+                       WHEN oi.quantity_shipped > 0 THEN oi.quantity_shipped
+                       ELSE oi.quantity - oi.quantity_backordered
+                   END AS SIGNED) AS quantity
+            FROM `order_items` oi
+            JOIN `orders` o ON oi.order_id = o.id
+            JOIN `store_par_levels` pl ON pl.store_id = o.store_id AND pl.product_id = oi.product_id
+            WHERE o.store_id = ?
+              AND o.status IN ({})
+              AND COALESCE(o.status_changed_to_completed, o.updated_at) >= NOW() - INTERVAL ? DAY
+            "#,
+            status_list(&[
+                StoreOrderStatus::Delivered,
+                StoreOrderStatus::DeliveredWithDiscrepancies,
+            ])
+        ))
+        .bind(store_id)
+        .bind(window_days)
+        .fetch_all(pool)
+        .await?;
+
+        let mut by_product: HashMap<u64, Vec<&DeliveredLineRow>> = HashMap::new();
+        for delivery in &deliveries {
+            by_product.entry(delivery.product_id).or_default().push(delivery);
+        }
+
+        let now = chrono::Local::now().naive_local();
+        Ok(rows
+            .iter()
+            .map(|row| {
+                let history = by_product.get(&row.product_id).map(Vec::as_slice).unwrap_or(&[]);
+                let usage = ParUsage::from_deliveries(history, row.on_order, window_days, now);
+                Self::build(row, &usage)
+            })
+            .collect())
+    }
+}
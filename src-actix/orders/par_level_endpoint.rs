@@ -0,0 +1,264 @@
+use crate::auth::ClaimsExtractor;
+use crate::orders::budget_endpoint::over_budget_response;
+use crate::orders::cart_data::{CartRecord, CartWithItemsDto};
+use crate::orders::orders_data::{
+    OrderCreateResult, OrderWithItemsDto, ReorderTarget, StoreOrderRecord, UserContext,
+};
+use crate::orders::orders_endpoint::{resolve_store_id, stock_rejected_response};
+use crate::orders::par_level_data::{
+    AcceptSuggestionsRequest, OrderSuggestion, ParLevelQuery, ParLevelRecord, SetParLevelsRequest,
+};
+use crate::products::ProductRecord;
+use actix_web::{delete, get, post, put, web, HttpRequest, HttpResponse, Responder};
+use database_common_lib::{database_connection::DatabaseConnectionData, http_error::Result};
+use serde_json::json;
+use std::collections::HashSet;
+
+/// Default and maximum days of delivery history behind a suggestion
+const DEFAULT_USAGE_WINDOW_DAYS: u32 = 90;
+const MAX_USAGE_WINDOW_DAYS: u32 = 365;
+
+#[get("/par-levels")]
+pub async fn get_par_levels(
+    req: HttpRequest,
+    connection_data: web::Data<DatabaseConnectionData>,
+    query: web::Query<ParLevelQuery>,
+) -> Result<impl Responder> {
+    let store_id = match resolve_store_id(&req, query.store_id.as_deref())? {
+        Ok(store_id) => store_id,
+        Err(response) => return Ok(response),
+    };
+    let pool = connection_data.get_pool().await?;
+
+    let levels = ParLevelRecord::get_for_store(&pool, store_id).await?;
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "data": levels
+    })))
+}
+
+#[put("/par-levels")]
+pub async fn set_par_levels(
+    req: HttpRequest,
+    connection_data: web::Data<DatabaseConnectionData>,
+    request: web::Json<SetParLevelsRequest>,
+) -> Result<impl Responder> {
+    let store_id = match resolve_store_id(&req, request.store_id.as_deref())? {
+        Ok(store_id) => store_id,
+        Err(response) => return Ok(response),
+    };
+    let claims = req
+        .get_claims()
+        .ok_or_else(|| anyhow::anyhow!("Authentication required"))?;
+    let pool = connection_data.get_pool().await?;
+
+    let mut errors = Vec::new();
+    let mut seen = HashSet::new();
+    let mut levels = Vec::with_capacity(request.levels.len());
+    for level in &request.levels {
+        let product_id = serde_hash::hashids::decode_single(&level.product_id)?;
+        if level.min_quantity < 0 || level.max_quantity <= 0 {
+            errors.push(format!(
+                "Par levels for product {} must be positive",
+                level.product_id
+            ));
+        } else if level.min_quantity >= level.max_quantity {
+            errors.push(format!(
+                "Minimum for product {} must be below its maximum",
+                level.product_id
+            ));
+        }
+        if !seen.insert(product_id) {
+            errors.push(format!("Product {} is listed more than once", level.product_id));
+        }
+        if ProductRecord::get_by_id_simple(&pool, product_id).await?.is_none() {
+            errors.push(format!("Product {} does not exist", level.product_id));
+        }
+        levels.push((product_id, level.min_quantity, level.max_quantity));
+    }
+    if !errors.is_empty() {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "success": false,
+            "error": "Invalid par levels",
+            "details": errors
+        })));
+    }
+
+    ParLevelRecord::set_many(&pool, store_id, &levels, claims.sub).await?;
+    let levels = ParLevelRecord::get_for_store(&pool, store_id).await?;
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "data": levels,
+        "message": "Par levels saved"
+    })))
+}
+
+#[delete("/par-levels/{product_id}")]
+pub async fn delete_par_level(
+    req: HttpRequest,
+    connection_data: web::Data<DatabaseConnectionData>,
+    path: web::Path<String>,
+    query: web::Query<ParLevelQuery>,
+) -> Result<impl Responder> {
+    let store_id = match resolve_store_id(&req, query.store_id.as_deref())? {
+        Ok(store_id) => store_id,
+        Err(response) => return Ok(response),
+    };
+    let pool = connection_data.get_pool().await?;
+    let product_id = serde_hash::hashids::decode_single(path.as_str())?;
+
+    if !ParLevelRecord::delete(&pool, store_id, product_id).await? {
+        return Ok(HttpResponse::NotFound().json(json!({
+            "success": false,
+            "error": "No par level set for this product"
+        })));
+    }
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "message": "Par level removed"
+    })))
+}
+
+#[get("/suggested-order")]
+pub async fn get_suggested_order(
+    req: HttpRequest,
+    connection_data: web::Data<DatabaseConnectionData>,
+    query: web::Query<ParLevelQuery>,
+) -> Result<impl Responder> {
+    let store_id = match resolve_store_id(&req, query.store_id.as_deref())? {
+        Ok(store_id) => store_id,
+        Err(response) => return Ok(response),
+    };
+    let pool = connection_data.get_pool().await?;
+
+    let days = query
+        .days
+        .unwrap_or(DEFAULT_USAGE_WINDOW_DAYS)
+        .clamp(1, MAX_USAGE_WINDOW_DAYS);
+    let suggestions = OrderSuggestion::for_store(&pool, store_id, days).await?;
+    let estimated_total: f64 = suggestions
+        .iter()
+        .filter(|s| s.orderable)
+        .map(|s| s.line_total)
+        .sum();
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "data": suggestions,
+        "estimated_total": estimated_total,
+        "usage_window_days": days
+    })))
+}
+
+/// Places the suggested (or adjusted) quantities as a new order or adds them to the cart
+#[post("/suggested-order/accept")]
+pub async fn accept_suggested_order(
+    req: HttpRequest,
+    connection_data: web::Data<DatabaseConnectionData>,
+    request: web::Json<AcceptSuggestionsRequest>,
+) -> Result<impl Responder> {
+    let store_id = match resolve_store_id(&req, request.store_id.as_deref())? {
+        Ok(store_id) => store_id,
+        Err(response) => return Ok(response),
+    };
+    let claims = req
+        .get_claims()
+        .ok_or_else(|| anyhow::anyhow!("Authentication required"))?;
+    let pool = connection_data.get_pool().await?;
+
+    let mut errors = Vec::new();
+    let mut lines = Vec::new(); // (product_id, quantity)
+    match &request.lines {
+        Some(requested) => {
+            let mut seen = HashSet::new();
+            for line in requested {
+                let product_id = serde_hash::hashids::decode_single(&line.product_id)?;
+                if line.quantity <= 0 {
+                    errors.push(format!(
+                        "Quantity for product {} must be greater than zero",
+                        line.product_id
+                    ));
+                }
+                if !seen.insert(product_id) {
+                    errors.push(format!("Product {} is listed more than once", line.product_id));
+                }
+                match ProductRecord::get_by_id_simple(&pool, product_id).await? {
+                    Some(product) if product.is_active && (product.in_stock || product.allow_backorder) => {}
+                    _ => errors.push(format!(
+                        "Product {} not found or out of stock",
+                        line.product_id
+                    )),
+                }
+                lines.push((product_id, line.quantity));
+            }
+        }
+        None => {
+            let suggestions =
+                OrderSuggestion::for_store(&pool, store_id, DEFAULT_USAGE_WINDOW_DAYS).await?;
+            for suggestion in suggestions
+                .iter()
+                .filter(|s| s.orderable && s.suggested_quantity > 0)
+            {
+                let product_id = serde_hash::hashids::decode_single(&suggestion.product_id)?;
+                lines.push((product_id, suggestion.suggested_quantity));
+            }
+        }
+    }
+    if lines.is_empty() {
+        errors.push("Nothing to order".to_string());
+    }
+    if !errors.is_empty() {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "success": false,
+            "error": "Suggested order could not be placed",
+            "details": errors
+        })));
+    }
+
+    match request.target {
+        ReorderTarget::Cart => {
+            let cart = CartRecord::get_or_create(&pool, claims.sub, claims.store_id).await?;
+            for (product_id, quantity) in &lines {
+                let product = ProductRecord::get_by_id_simple(&pool, *product_id)
+                    .await?
+                    .ok_or_else(|| anyhow::anyhow!("Product not found"))?;
+                cart.add_item(&pool, *product_id, *quantity, product.price).await?;
+            }
+            let cart = CartRecord::get_with_items(&pool, claims.sub, claims.store_id).await?;
+            Ok(HttpResponse::Ok().json(json!({
+                "success": true,
+                "data": CartWithItemsDto::from(&cart),
+                "message": "Suggested quantities added to cart"
+            })))
+        }
+        ReorderTarget::Order => {
+            let user_context =
+                UserContext::from_claims(claims.sub, claims.store_id, claims.role.clone());
+            let notes = request
+                .notes
+                .clone()
+                .unwrap_or_else(|| "Suggested order from par levels".to_string());
+            let (order_id, budget) = match StoreOrderRecord::create_order(
+                &pool,
+                &user_context,
+                store_id,
+                &lines,
+                Some(&notes),
+            )
+            .await?
+            {
+                OrderCreateResult::Created { order_id, budget } => (order_id, budget),
+                OrderCreateResult::Rejected(issues) => return Ok(stock_rejected_response(&issues)),
+                OrderCreateResult::OverBudget(check) => return Ok(over_budget_response(&check)),
+            };
+            let order = StoreOrderRecord::get_with_items(&pool, order_id)
+                .await?
+                .ok_or_else(|| anyhow::anyhow!("Failed to retrieve created order"))?;
+            Ok(HttpResponse::Created().json(json!({
+                "success": true,
+                "data": OrderWithItemsDto::from(&order),
+                "budget": budget,
+                "message": order.order.created_message()
+            })))
+        }
+    }
+}
@@ -0,0 +1,208 @@
+use serde::Serialize;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+/// One outstanding order line as loaded for picking
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct PickListRow {
+    pub order_id: u64,
+    pub order_number: String,
+    pub store_id: u64,
+    pub product_id: u64,
+    pub product_name: String,
+    pub product_sku: String,
+    pub product_bin_location: String,
+    pub product_unit_type: i32,
+    pub category_name: String,
+    pub quantity: i32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PickListStoreQuantity {
+    pub order_number: String,
+    pub store_name: String,
+    pub quantity: i32,
+}
+
+/// One product to pick, totalled across every order on the list
+#[derive(Debug, Clone, Serialize)]
+pub struct PickListLine {
+    pub product_id: String,
+    pub product_name: String,
+    pub product_sku: String,
+    pub product_bin_location: String,
+    pub product_unit_type: i32,
+    pub category_name: String,
+    pub total_quantity: i32,
+    pub stores: Vec<PickListStoreQuantity>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PickListOrder {
+    pub order_id: String,
+    pub order_number: String,
+    pub store_name: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PickList {
+    pub orders: Vec<PickListOrder>,
+    pub lines: Vec<PickListLine>,
+    pub total_units: i32,
+}
+
+impl PickList {
+    /// Merges order lines into one list in warehouse walking order.
+    ///
+    /// Lines are grouped by product and sorted by [`compare_bin_locations`], then SKU.
+    /// `store_names` maps store ids to their display name.
+    pub fn build(rows: &[PickListRow], store_names: &HashMap<u64, String>) -> Self {
+        let store_name = |store_id: u64| {
+            store_names
+                .get(&store_id)
+                .cloned()
+                .unwrap_or_else(|| format!("Store {}", store_id))
+        };
+
+        let mut orders: Vec<PickListOrder> = Vec::new();
+        let mut lines: Vec<PickListLine> = Vec::new();
+        let mut line_index: HashMap<u64, usize> = HashMap::new();
+
+        for row in rows {
+            if !orders.iter().any(|o| o.order_number == row.order_number) {
+                orders.push(PickListOrder {
+                    order_id: serde_hash::hashids::encode_single(row.order_id),
+                    order_number: row.order_number.clone(),
+                    store_name: store_name(row.store_id),
+                });
+            }
+            if row.quantity <= 0 {
+                continue;
+            }
+
+            let index = *line_index.entry(row.product_id).or_insert_with(|| {
+                lines.push(PickListLine {
+                    product_id: serde_hash::hashids::encode_single(row.product_id),
+                    product_name: row.product_name.clone(),
+                    product_sku: row.product_sku.clone(),
+                    product_bin_location: row.product_bin_location.clone(),
+                    product_unit_type: row.product_unit_type,
+                    category_name: row.category_name.clone(),
+                    total_quantity: 0,
+                    stores: Vec::new(),
+                });
+                lines.len() - 1
+            });
+            let line = &mut lines[index];
+            line.total_quantity += row.quantity;
+            line.stores.push(PickListStoreQuantity {
+                order_number: row.order_number.clone(),
+                store_name: store_name(row.store_id),
+                quantity: row.quantity,
+            });
+        }
+
+        lines.sort_by(|a, b| {
+            compare_bin_locations(&a.product_bin_location, &b.product_bin_location)
+                .then_with(|| a.product_sku.cmp(&b.product_sku))
+        });
+        for line in &mut lines {
+            line.stores.sort_by(|a, b| a.store_name.cmp(&b.store_name));
+        }
+        orders.sort_by(|a, b| a.order_number.cmp(&b.order_number));
+
+        let total_units = lines.iter().map(|l| l.total_quantity).sum();
+        Self {
+            orders,
+            lines,
+            total_units,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum BinSegment {
+    Number(u64),
+    Text(String),
+}
+
+/// Splits a bin location such as `A-12-03` or `B7 Shelf 2` into its aisle, shelf and
+/// position parts. Separators are dropped, letters are compared case-insensitively and
+/// digit runs as numbers.
+fn bin_segments(bin_location: &str) -> Vec<BinSegment> {
+    let mut segments = Vec::new();
+    let mut current = String::new();
+    let mut current_is_digit = false;
+
+    let flush = |current: &mut String, is_digit: bool, segments: &mut Vec<BinSegment>| {
+        if current.is_empty() {
+            return;
+        }
+        let segment = if is_digit {
+            BinSegment::Number(current.parse().unwrap_or(u64::MAX))
+        } else {
+            BinSegment::Text(current.to_ascii_uppercase())
+        };
+        segments.push(segment);
+        current.clear();
+    };
+
+    for ch in bin_location.chars() {
+        if !ch.is_ascii_alphanumeric() {
+            flush(&mut current, current_is_digit, &mut segments);
+            continue;
+        }
+        let is_digit = ch.is_ascii_digit();
+        if !current.is_empty() && is_digit != current_is_digit {
+            flush(&mut current, current_is_digit, &mut segments);
+        }
+        current_is_digit = is_digit;
+        current.push(ch);
+    }
+    flush(&mut current, current_is_digit, &mut segments);
+    segments
+}
+
+/// Orders bin locations the way a picker walks them (`A-2` before `A-10`).
+/// Products without a bin location sort last.
+pub fn compare_bin_locations(a: &str, b: &str) -> Ordering {
+    let (a, b) = (bin_segments(a), bin_segments(b));
+    match (a.is_empty(), b.is_empty()) {
+        (true, true) => return Ordering::Equal,
+        (true, false) => return Ordering::Greater,
+        (false, true) => return Ordering::Less,
+        _ => {}
+    }
+
+    for (x, y) in a.iter().zip(b.iter()) {
+        let ordering = match (x, y) {
+            (BinSegment::Number(x), BinSegment::Number(y)) => x.cmp(y),
+            (BinSegment::Text(x), BinSegment::Text(y)) => x.cmp(y),
+            // Aisle letters before numbers at the same position
+            (BinSegment::Text(_), BinSegment::Number(_)) => Ordering::Less,
+            (BinSegment::Number(_), BinSegment::Text(_)) => Ordering::Greater,
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+    a.len().cmp(&b.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bins_sort_numerically_within_an_aisle() {
+        let mut bins = vec!["A-10-1", "b-2", "A-2-3", "", "A-2-10", "A2-2"];
+        bins.sort_by(|a, b| compare_bin_locations(a, b));
+        assert_eq!(bins, vec!["A2-2", "A-2-3", "A-2-10", "A-10-1", "b-2", ""]);
+    }
+
+    #[test]
+    fn separators_and_case_are_ignored() {
+        assert_eq!(compare_bin_locations("a 12 / 3", "A-12-3"), Ordering::Equal);
+        assert_eq!(compare_bin_locations("A12", "A12-1"), Ordering::Less);
+    }
+}
@@ -0,0 +1,57 @@
+use crate::orders::pick_list_data::{PickList, PickListRow};
+use crate::stores::stores_data::StoreRecord;
+use sqlx::MySqlPool;
+use std::collections::HashMap;
+
+impl PickListRow {
+    /// Outstanding quantities (ordered less shipped and backordered) for every line of the given orders
+    pub async fn get_for_orders(pool: &MySqlPool, order_ids: &[u64]) -> anyhow::Result<Vec<Self>> {
+        if order_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let placeholders = vec!["?"; order_ids.len()].join(", ");
+        let query = format!(
+            r#"
+            SELECT
+                oi.order_id, o.order_number, o.store_id, oi.product_id,
+                p.name as product_name,
+                p.sku as product_sku,
+                p.bin_location as product_bin_location,
+                p.unit_type as product_unit_type,
+                c.name as category_name,
+                (oi.quantity - oi.quantity_shipped - oi.quantity_backordered) as quantity
+            FROM `order_items` oi
+            JOIN `orders` o ON oi.order_id = o.id
+            JOIN `products` p ON oi.product_id = p.id
+            JOIN `categories` c ON p.category_id = c.id
+            WHERE oi.order_id IN ({placeholders})
+            ORDER BY o.order_number ASC, oi.id ASC
+            "#
+        );
+
+        let mut sql_query = sqlx::query_as::<_, Self>(&query);
+        for order_id in order_ids {
+            sql_query = sql_query.bind(order_id);
+        }
+        Ok(sql_query.fetch_all(pool).await?)
+    }
+}
+
+impl PickList {
+    pub async fn for_orders(pool: &MySqlPool, order_ids: &[u64]) -> anyhow::Result<Self> {
+        let rows = PickListRow::get_for_orders(pool, order_ids).await?;
+
+        let mut store_names = HashMap::new();
+        for row in &rows {
+            if store_names.contains_key(&row.store_id) {
+                continue;
+            }
+            if let Some(store) = StoreRecord::get_by_id(pool, row.store_id).await? {
+                store_names.insert(row.store_id, store.display_name());
+            }
+        }
+
+        Ok(Self::build(&rows, &store_names))
+    }
+}
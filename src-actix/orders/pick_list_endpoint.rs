@@ -0,0 +1,71 @@
+use crate::auth::ClaimsExtractor;
+use crate::orders::orders_data::{decode_order_id_list, OrderIdListQuery, StoreOrderRecord};
+use crate::orders::pick_list_data::PickList;
+use crate::orders::store_order_status::StoreOrderStatus;
+use crate::products::barcode_svg;
+use actix_web::{get, web, HttpRequest, HttpResponse, Responder};
+use database_common_lib::{database_connection::DatabaseConnectionData, http_error::Result};
+use serde_json::json;
+
+static PICK_LIST_TEMPLATE: &str = include_str!("../../templates/pick-list-template.html.tera");
+
+/// Renders the pick list template under the given heading
+pub(crate) fn render_pick_list(pick_list: &PickList, title: &str) -> HttpResponse {
+    let mut ctx = tera::Context::new();
+    ctx.insert("title", title);
+    ctx.insert("pick_list", pick_list);
+
+    let mut tera = tera::Tera::default();
+    barcode_svg::register_tera_filter(&mut tera);
+    tera.add_raw_template("pick-list-template", PICK_LIST_TEMPLATE)
+        .unwrap();
+    let body = tera.render("pick-list-template", &ctx).unwrap();
+    HttpResponse::Ok().content_type("text/html").body(body)
+}
+
+/// Merged pick list for one or more Pending (or already Picking) orders,
+/// e.g. `/api/orders/pick-list?orders=abc,def`. Store users can only pick their own orders.
+#[get("/pick-list")]
+pub async fn get_pick_list(
+    req: HttpRequest,
+    connection_data: web::Data<DatabaseConnectionData>,
+    query: web::Query<OrderIdListQuery>,
+) -> Result<impl Responder> {
+    let claims = req
+        .get_claims()
+        .ok_or_else(|| anyhow::anyhow!("Authentication required"))?;
+    let pool = connection_data.get_pool().await?;
+
+    let mut order_ids = decode_order_id_list(&query.orders)?;
+    if order_ids.is_empty() {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "success": false,
+            "error": "At least one order is required"
+        })));
+    }
+    order_ids.sort_unstable();
+
+    for order_id in &order_ids {
+        let Some(order) = StoreOrderRecord::get_by_id(&pool, *order_id).await? else {
+            return Ok(HttpResponse::NotFound().json(json!({
+                "success": false,
+                "error": "Order not found"
+            })));
+        };
+        if claims.role != "admin" && claims.store_id != Some(order.store_id) {
+            return Ok(HttpResponse::Forbidden().json(json!({
+                "success": false,
+                "error": "Access denied: You can only pick orders for your store"
+            })));
+        }
+        if !matches!(order.status, StoreOrderStatus::Pending | StoreOrderStatus::Picking) {
+            return Ok(HttpResponse::BadRequest().json(json!({
+                "success": false,
+                "error": format!("Order {} is {} and cannot be picked", order.order_number, order.status)
+            })));
+        }
+    }
+
+    let pick_list = PickList::for_orders(&pool, &order_ids).await?;
+    Ok(render_pick_list(&pick_list, "Pick List"))
+}
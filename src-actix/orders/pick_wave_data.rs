@@ -0,0 +1,67 @@
+use crate::orders::orders_data::{OrderWithItemsDto, StoreOrderRecordDto};
+use serde::{Deserialize, Serialize};
+use serde_hash::HashIds;
+use sqlx::FromRow;
+
+/// A batch of orders picked together. A wave is open until `closed_at` is set.
+#[derive(HashIds, Debug, Clone, FromRow)]
+pub struct PickWaveRecord {
+    #[hash]
+    pub id: u64,
+    pub wave_number: String,
+    #[hash]
+    pub created_by: u64,
+    pub notes: Option<String>,
+    pub created_at: chrono::NaiveDateTime,
+    pub closed_at: Option<chrono::NaiveDateTime>,
+    #[hash]
+    pub closed_by: Option<u64>,
+    pub order_count: i64,
+}
+
+impl PickWaveRecord {
+    pub fn is_open(&self) -> bool {
+        self.closed_at.is_none()
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct PickWaveWithOrders {
+    #[serde(flatten)]
+    pub wave: PickWaveRecord,
+    pub orders: Vec<StoreOrderRecordDto>,
+}
+
+/// One store's share of a wave, rendered as a packing list
+#[derive(Debug, Serialize)]
+pub struct PackingList {
+    pub store_name: String,
+    #[serde(flatten)]
+    pub order: OrderWithItemsDto,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreatePickWaveRequest {
+    pub order_ids: Vec<String>, // hashed IDs
+    pub notes: Option<String>,
+}
+
+/// Outcome of creating a wave
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PickWaveCreateResult {
+    Created(u64),
+    /// Orders that are missing, not Pending or already in a wave; nothing was created
+    InvalidOrders(Vec<String>),
+}
+
+/// Outcome of closing a wave
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PickWaveCloseResult {
+    Closed {
+        shipped_orders: usize,
+        /// Orders left alone because they were held, cancelled or already shipped
+        skipped_orders: Vec<String>,
+    },
+    NotFound,
+    AlreadyClosed,
+}
@@ -0,0 +1,306 @@
+use crate::orders::orders_data::{OrderWithItemsDto, StoreOrderRecord, StoreOrderRecordDto};
+use crate::orders::orders_db::{
+    lock_order_status, next_sequence_value, record_status_change, ship_outstanding,
+};
+use crate::orders::pick_wave_data::{
+    PackingList, PickWaveCloseResult, PickWaveCreateResult, PickWaveRecord, PickWaveWithOrders,
+};
+use crate::orders::store_order_status::StoreOrderStatus;
+use crate::stores::stores_data::StoreRecord;
+use log::*;
+use sqlx::{Executor, MySql, MySqlPool, Row, Transaction};
+
+pub async fn initialize(pool: &MySqlPool) -> anyhow::Result<()> {
+    pool.execute(
+        r#"
+        CREATE TABLE IF NOT EXISTS `pick_waves` (
+            `id` BIGINT UNSIGNED NOT NULL AUTO_INCREMENT,
+            `wave_number` VARCHAR(50) NOT NULL UNIQUE,
+            `created_by` BIGINT UNSIGNED NOT NULL,
+            `notes` TEXT,
+            `created_at` DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            `closed_at` DATETIME,
+            `closed_by` BIGINT UNSIGNED,
+            PRIMARY KEY (`id`),
+            INDEX `idx_closed_at` (`closed_at`)
+        )
+        "#,
+    )
+    .await?;
+
+    // An order belongs to at most one wave
+    pool.execute(
+        r#"
+        CREATE TABLE IF NOT EXISTS `pick_wave_orders` (
+            `wave_id` BIGINT UNSIGNED NOT NULL,
+            `order_id` BIGINT UNSIGNED NOT NULL,
+            PRIMARY KEY (`wave_id`, `order_id`),
+            UNIQUE KEY `uq_order_id` (`order_id`),
+            FOREIGN KEY (`wave_id`) REFERENCES `pick_waves`(`id`) ON DELETE CASCADE,
+            FOREIGN KEY (`order_id`) REFERENCES `orders`(`id`) ON DELETE CASCADE
+        )
+        "#,
+    )
+    .await?;
+
+    Ok(())
+}
+
+impl PickWaveRecord {
+    pub async fn get_all(pool: &MySqlPool) -> anyhow::Result<Vec<Self>> {
+        let waves = sqlx::query_as::<_, Self>(
+            r#"
+            SELECT w.*, COUNT(wo.order_id) AS order_count
+            FROM `pick_waves` w
+            LEFT JOIN `pick_wave_orders` wo ON wo.wave_id = w.id
+            GROUP BY w.id
+            ORDER BY w.created_at DESC
+            "#,
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(waves)
+    }
+
+    pub async fn get_by_id(pool: &MySqlPool, id: u64) -> anyhow::Result<Option<Self>> {
+        let wave = sqlx::query_as::<_, Self>(
+            r#"
+            SELECT w.*, COUNT(wo.order_id) AS order_count
+            FROM `pick_waves` w
+            LEFT JOIN `pick_wave_orders` wo ON wo.wave_id = w.id
+            WHERE w.id = ?
+            GROUP BY w.id
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(wave)
+    }
+
+    pub async fn get_order_ids(pool: &MySqlPool, id: u64) -> anyhow::Result<Vec<u64>> {
+        let order_ids = sqlx::query_scalar::<_, u64>(
+            "SELECT `order_id` FROM `pick_wave_orders` WHERE `wave_id` = ? ORDER BY `order_id` ASC",
+        )
+        .bind(id)
+        .fetch_all(pool)
+        .await?;
+
+        Ok(order_ids)
+    }
+
+    pub async fn get_with_orders(
+        pool: &MySqlPool,
+        id: u64,
+    ) -> anyhow::Result<Option<PickWaveWithOrders>> {
+        let Some(wave) = Self::get_by_id(pool, id).await? else {
+            return Ok(None);
+        };
+
+        let mut orders = Vec::new();
+        for order_id in Self::get_order_ids(pool, id).await? {
+            if let Some(order) = StoreOrderRecord::get_by_id(pool, order_id).await? {
+                orders.push(StoreOrderRecordDto::from(&order));
+            }
+        }
+        Ok(Some(PickWaveWithOrders { wave, orders }))
+    }
+
+    /// One packing list per order in the wave, ordered by store name
+    pub async fn get_packing_lists(pool: &MySqlPool, id: u64) -> anyhow::Result<Vec<PackingList>> {
+        let mut packing_lists = Vec::new();
+        for order_id in Self::get_order_ids(pool, id).await? {
+            let Some(order) = StoreOrderRecord::get_with_items(pool, order_id).await? else {
+                continue;
+            };
+            let store_name = match StoreRecord::get_by_id(pool, order.order.store_id).await? {
+                Some(store) => store.display_name(),
+                None => format!("Store {}", order.order.store_id),
+            };
+            packing_lists.push(PackingList {
+                store_name,
+                order: OrderWithItemsDto::from(&order),
+            });
+        }
+        packing_lists.sort_by(|a, b| a.store_name.cmp(&b.store_name));
+        Ok(packing_lists)
+    }
+
+    /// Groups Pending orders into a new wave and moves them all to Picking.
+    ///
+    /// Every order is locked and checked first; if any is missing, not Pending or
+    /// already part of a wave, nothing is changed.
+    pub async fn create(
+        pool: &MySqlPool,
+        order_ids: &[u64],
+        created_by: u64,
+        notes: Option<&str>,
+    ) -> anyhow::Result<PickWaveCreateResult> {
+        let mut transaction = pool.begin().await?;
+
+        let mut errors = Vec::new();
+        for order_id in order_ids {
+            let order_hash = serde_hash::hashids::encode_single(*order_id);
+            match lock_order_status(&mut transaction, *order_id).await? {
+                None => errors.push(format!("Order {} not found", order_hash)),
+                Some(StoreOrderStatus::Pending) => {
+                    let in_wave: Option<u64> = sqlx::query_scalar(
+                        "SELECT `wave_id` FROM `pick_wave_orders` WHERE `order_id` = ?",
+                    )
+                    .bind(order_id)
+                    .fetch_optional(&mut *transaction)
+                    .await?;
+                    if in_wave.is_some() {
+                        errors.push(format!("Order {} is already in a wave", order_hash));
+                    }
+                }
+                Some(status) => errors.push(format!(
+                    "Order {} is {} and cannot be added to a wave",
+                    order_hash, status
+                )),
+            }
+        }
+        if !errors.is_empty() {
+            return Ok(PickWaveCreateResult::InvalidOrders(errors));
+        }
+
+        let wave_result = sqlx::query(
+            "INSERT INTO `pick_waves` (`wave_number`, `created_by`, `notes`) VALUES (?, ?, ?)",
+        )
+        .bind(Self::generate_wave_number(&mut transaction).await?)
+        .bind(created_by)
+        .bind(notes)
+        .execute(&mut *transaction)
+        .await?;
+        let wave_id = wave_result.last_insert_id();
+
+        for order_id in order_ids {
+            sqlx::query("INSERT INTO `pick_wave_orders` (`wave_id`, `order_id`) VALUES (?, ?)")
+                .bind(wave_id)
+                .bind(order_id)
+                .execute(&mut *transaction)
+                .await?;
+            sqlx::query("UPDATE `orders` SET `status` = ? WHERE `id` = ?")
+                .bind(StoreOrderStatus::Picking.as_db_str())
+                .bind(order_id)
+                .execute(&mut *transaction)
+                .await?;
+            record_status_change(
+                &mut transaction,
+                *order_id,
+                Some(&StoreOrderStatus::Pending),
+                &StoreOrderStatus::Picking,
+                created_by,
+                notes,
+            )
+            .await?;
+        }
+
+        transaction.commit().await?;
+        info!("Pick wave {} created by user {} with {} orders", wave_id, created_by, order_ids.len());
+        Ok(PickWaveCreateResult::Created(wave_id))
+    }
+
+    /// Closes a wave and ships every order in it in one transaction.
+    ///
+    /// Orders that were put on hold, cancelled or already moved past Packed while
+    /// the wave was open are left as they are and reported back.
+    pub async fn close(
+        pool: &MySqlPool,
+        id: u64,
+        closed_by: u64,
+    ) -> anyhow::Result<PickWaveCloseResult> {
+        let mut transaction = pool.begin().await?;
+
+        let wave_row = sqlx::query("SELECT `wave_number`, `closed_at` FROM `pick_waves` WHERE `id` = ? FOR UPDATE")
+            .bind(id)
+            .fetch_optional(&mut *transaction)
+            .await?;
+        let Some(wave_row) = wave_row else {
+            return Ok(PickWaveCloseResult::NotFound);
+        };
+        let closed_at: Option<chrono::NaiveDateTime> = wave_row.get("closed_at");
+        if closed_at.is_some() {
+            return Ok(PickWaveCloseResult::AlreadyClosed);
+        }
+        let wave_number: String = wave_row.get("wave_number");
+        let notes = format!("Shipped with wave {}", wave_number);
+
+        let order_ids: Vec<u64> = sqlx::query_scalar(
+            "SELECT `order_id` FROM `pick_wave_orders` WHERE `wave_id` = ? ORDER BY `order_id` ASC",
+        )
+        .bind(id)
+        .fetch_all(&mut *transaction)
+        .await?;
+
+        let mut shipped_orders = 0;
+        let mut skipped_orders = Vec::new();
+        for order_id in order_ids {
+            let Some(status) = lock_order_status(&mut transaction, order_id).await? else {
+                continue;
+            };
+            if !matches!(status, StoreOrderStatus::Picking | StoreOrderStatus::Packed) {
+                skipped_orders.push(format!(
+                    "Order {} is {}",
+                    serde_hash::hashids::encode_single(order_id),
+                    status
+                ));
+                continue;
+            }
+
+            sqlx::query("UPDATE `orders` SET `status` = ?, `status_changed_to_shipped` = NOW() WHERE `id` = ?")
+                .bind(StoreOrderStatus::Shipped.as_db_str())
+                .bind(order_id)
+                .execute(&mut *transaction)
+                .await?;
+            ship_outstanding(&mut transaction, order_id).await?;
+            record_status_change(
+                &mut transaction,
+                order_id,
+                Some(&status),
+                &StoreOrderStatus::Shipped,
+                closed_by,
+                Some(&notes),
+            )
+            .await?;
+            shipped_orders += 1;
+        }
+
+        sqlx::query("UPDATE `pick_waves` SET `closed_at` = NOW(), `closed_by` = ? WHERE `id` = ?")
+            .bind(closed_by)
+            .bind(id)
+            .execute(&mut *transaction)
+            .await?;
+
+        transaction.commit().await?;
+        info!("Pick wave {} closed by user {}: {} orders shipped", id, closed_by, shipped_orders);
+        Ok(PickWaveCloseResult::Closed {
+            shipped_orders,
+            skipped_orders,
+        })
+    }
+
+    /// Draws the next of today's wave numbers, e.g. `WAVE-20261017-0001`, from the same
+    /// sequence table as order numbers
+    async fn generate_wave_number(
+        transaction: &mut Transaction<'_, MySql>,
+    ) -> anyhow::Result<String> {
+        let prefix = format!("WAVE-{}-", chrono::Local::now().format("%Y%m%d"));
+
+        // Waves numbered before the sequence was used can already hold a number; skip past them
+        loop {
+            let seq = next_sequence_value(transaction, &prefix).await?;
+            let wave_number = format!("{}{:04}", prefix, seq);
+            let taken: Option<u64> =
+                sqlx::query_scalar("SELECT `id` FROM `pick_waves` WHERE `wave_number` = ?")
+                    .bind(&wave_number)
+                    .fetch_optional(&mut **transaction)
+                    .await?;
+            if taken.is_none() {
+                return Ok(wave_number);
+            }
+        }
+    }
+}
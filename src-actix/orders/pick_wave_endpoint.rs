@@ -0,0 +1,211 @@
+use crate::auth::ClaimsExtractor;
+use crate::orders::pick_list_data::PickList;
+use crate::orders::pick_list_endpoint::render_pick_list;
+use crate::orders::pick_wave_data::{
+    CreatePickWaveRequest, PickWaveCloseResult, PickWaveCreateResult, PickWaveRecord,
+};
+use actix_web::{get, post, web, HttpRequest, HttpResponse, Responder};
+use database_common_lib::{database_connection::DatabaseConnectionData, http_error::Result};
+use serde_json::json;
+
+static PACKING_LIST_TEMPLATE: &str = include_str!("../../templates/packing-list-template.html.tera");
+
+/// Returns the response to send when the caller is not an admin
+fn require_admin(req: &HttpRequest) -> Result<Option<HttpResponse>> {
+    let claims = req
+        .get_claims()
+        .ok_or_else(|| anyhow::anyhow!("Authentication required"))?;
+    if claims.role != "admin" {
+        return Ok(Some(HttpResponse::Forbidden().json(json!({
+            "success": false,
+            "error": "Admin access required"
+        }))));
+    }
+    Ok(None)
+}
+
+#[get("/waves")]
+pub async fn get_waves(
+    req: HttpRequest,
+    connection_data: web::Data<DatabaseConnectionData>,
+) -> Result<impl Responder> {
+    if let Some(denied) = require_admin(&req)? {
+        return Ok(denied);
+    }
+    let pool = connection_data.get_pool().await?;
+
+    let waves = PickWaveRecord::get_all(&pool).await?;
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "data": waves
+    })))
+}
+
+#[get("/waves/{id}")]
+pub async fn get_wave(
+    req: HttpRequest,
+    connection_data: web::Data<DatabaseConnectionData>,
+    path: web::Path<String>,
+) -> Result<impl Responder> {
+    if let Some(denied) = require_admin(&req)? {
+        return Ok(denied);
+    }
+    let pool = connection_data.get_pool().await?;
+    let wave_id = serde_hash::hashids::decode_single(path.as_str())?;
+
+    match PickWaveRecord::get_with_orders(&pool, wave_id).await? {
+        Some(wave) => Ok(HttpResponse::Ok().json(json!({
+            "success": true,
+            "data": wave
+        }))),
+        None => Ok(HttpResponse::NotFound().json(json!({
+            "success": false,
+            "error": "Pick wave not found"
+        }))),
+    }
+}
+
+#[post("/waves")]
+pub async fn create_wave(
+    req: HttpRequest,
+    connection_data: web::Data<DatabaseConnectionData>,
+    request: web::Json<CreatePickWaveRequest>,
+) -> Result<impl Responder> {
+    if let Some(denied) = require_admin(&req)? {
+        return Ok(denied);
+    }
+    let claims = req
+        .get_claims()
+        .ok_or_else(|| anyhow::anyhow!("Authentication required"))?;
+    let pool = connection_data.get_pool().await?;
+
+    let mut order_ids = Vec::with_capacity(request.order_ids.len());
+    for hash in &request.order_ids {
+        order_ids.push(serde_hash::hashids::decode_single(hash)?);
+    }
+    order_ids.sort_unstable();
+    order_ids.dedup();
+    if order_ids.is_empty() {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "success": false,
+            "error": "At least one order is required"
+        })));
+    }
+
+    match PickWaveRecord::create(&pool, &order_ids, claims.sub, request.notes.as_deref()).await? {
+        PickWaveCreateResult::Created(wave_id) => {
+            let wave = PickWaveRecord::get_with_orders(&pool, wave_id)
+                .await?
+                .ok_or_else(|| anyhow::anyhow!("Failed to retrieve created pick wave"))?;
+            Ok(HttpResponse::Created().json(json!({
+                "success": true,
+                "data": wave,
+                "message": "Pick wave created successfully"
+            })))
+        }
+        PickWaveCreateResult::InvalidOrders(errors) => Ok(HttpResponse::BadRequest().json(json!({
+            "success": false,
+            "error": "Pick wave could not be created",
+            "details": errors
+        }))),
+    }
+}
+
+#[post("/waves/{id}/close")]
+pub async fn close_wave(
+    req: HttpRequest,
+    connection_data: web::Data<DatabaseConnectionData>,
+    path: web::Path<String>,
+) -> Result<impl Responder> {
+    if let Some(denied) = require_admin(&req)? {
+        return Ok(denied);
+    }
+    let claims = req
+        .get_claims()
+        .ok_or_else(|| anyhow::anyhow!("Authentication required"))?;
+    let pool = connection_data.get_pool().await?;
+    let wave_id = serde_hash::hashids::decode_single(path.as_str())?;
+
+    match PickWaveRecord::close(&pool, wave_id, claims.sub).await? {
+        PickWaveCloseResult::Closed {
+            shipped_orders,
+            skipped_orders,
+        } => {
+            let wave = PickWaveRecord::get_with_orders(&pool, wave_id)
+                .await?
+                .ok_or_else(|| anyhow::anyhow!("Pick wave not found after update"))?;
+            Ok(HttpResponse::Ok().json(json!({
+                "success": true,
+                "data": wave,
+                "skipped": skipped_orders,
+                "message": format!("Pick wave closed, {} orders shipped", shipped_orders)
+            })))
+        }
+        PickWaveCloseResult::NotFound => Ok(HttpResponse::NotFound().json(json!({
+            "success": false,
+            "error": "Pick wave not found"
+        }))),
+        PickWaveCloseResult::AlreadyClosed => Ok(HttpResponse::Conflict().json(json!({
+            "success": false,
+            "error": "Pick wave is already closed"
+        }))),
+    }
+}
+
+#[get("/waves/{id}/pick-list")]
+pub async fn get_wave_pick_list(
+    req: HttpRequest,
+    connection_data: web::Data<DatabaseConnectionData>,
+    path: web::Path<String>,
+) -> Result<impl Responder> {
+    if let Some(denied) = require_admin(&req)? {
+        return Ok(denied);
+    }
+    let pool = connection_data.get_pool().await?;
+    let wave_id = serde_hash::hashids::decode_single(path.as_str())?;
+
+    let Some(wave) = PickWaveRecord::get_by_id(&pool, wave_id).await? else {
+        return Ok(HttpResponse::NotFound().json(json!({
+            "success": false,
+            "error": "Pick wave not found"
+        })));
+    };
+
+    let order_ids = PickWaveRecord::get_order_ids(&pool, wave_id).await?;
+    let pick_list = PickList::for_orders(&pool, &order_ids).await?;
+    Ok(render_pick_list(
+        &pick_list,
+        &format!("Pick List | Wave {}", wave.wave_number),
+    ))
+}
+
+#[get("/waves/{id}/packing-lists")]
+pub async fn get_wave_packing_lists(
+    req: HttpRequest,
+    connection_data: web::Data<DatabaseConnectionData>,
+    path: web::Path<String>,
+) -> Result<impl Responder> {
+    if let Some(denied) = require_admin(&req)? {
+        return Ok(denied);
+    }
+    let pool = connection_data.get_pool().await?;
+    let wave_id = serde_hash::hashids::decode_single(path.as_str())?;
+
+    let Some(wave) = PickWaveRecord::get_by_id(&pool, wave_id).await? else {
+        return Ok(HttpResponse::NotFound().json(json!({
+            "success": false,
+            "error": "Pick wave not found"
+        })));
+    };
+    let packing_lists = PickWaveRecord::get_packing_lists(&pool, wave_id).await?;
+
+    let mut ctx = tera::Context::new();
+    ctx.insert("wave", &wave);
+    ctx.insert("packing_lists", &packing_lists);
+
+    let mut tera = tera::Tera::default();
+    tera.add_raw_template("packing-list-template", PACKING_LIST_TEMPLATE)
+        .unwrap();
+    let body = tera.render("packing-list-template", &ctx).unwrap();
+    Ok(HttpResponse::Ok().content_type("text/html").body(body))
+}
@@ -0,0 +1,138 @@
+use crate::orders::store_order_status::StoreOrderStatus;
+use serde::{Deserialize, Serialize};
+use serde_hash::HashIds;
+use sqlx::FromRow;
+
+/// One receiving of an order by its store. A receipt with discrepancies doubles
+/// as the discrepancy report admins review and resolve.
+#[derive(HashIds, Debug, Clone, FromRow)]
+pub struct OrderReceiptRecord {
+    #[hash]
+    pub id: u64,
+    #[hash]
+    pub order_id: u64,
+    pub order_number: String,
+    #[hash]
+    pub store_id: u64,
+    #[hash]
+    pub received_by: u64,
+    pub has_discrepancies: bool,
+    pub notes: Option<String>,
+    pub resolved_at: Option<chrono::NaiveDateTime>,
+    #[hash]
+    pub resolved_by: Option<u64>,
+    pub resolution_notes: Option<String>,
+    pub created_at: chrono::NaiveDateTime,
+}
+
+#[derive(HashIds, Debug, Clone, FromRow)]
+pub struct OrderReceiptLineRecord {
+    #[hash]
+    pub id: u64,
+    #[hash]
+    pub receipt_id: u64,
+    #[hash]
+    pub order_item_id: u64,
+    #[hash]
+    pub product_id: u64,
+    pub product_name: String,
+    pub product_sku: String,
+    pub quantity_expected: i32,
+    pub quantity_received: i32,
+    pub quantity_damaged: i32,
+    pub quantity_short: i32,
+    pub quantity_over: i32,
+    pub comment: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OrderReceiptWithLines {
+    #[serde(flatten)]
+    pub receipt: OrderReceiptRecord,
+    pub lines: Vec<OrderReceiptLineRecord>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReceiveOrderRequest {
+    /// Every line of the order must be listed exactly once
+    pub items: Vec<ReceiveOrderLineRequest>,
+    pub notes: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReceiveOrderLineRequest {
+    pub item_id: String, // hashed ID
+    pub quantity_received: i32,
+    #[serde(default)]
+    pub quantity_damaged: i32,
+    #[serde(default)]
+    pub quantity_short: i32,
+    #[serde(default)]
+    pub quantity_over: i32,
+    pub comment: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ResolveDiscrepancyRequest {
+    pub resolution_notes: String,
+}
+
+/// Which discrepancy reports to list
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiscrepancyFilter {
+    #[default]
+    Open,
+    Resolved,
+    All,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DiscrepancyListQuery {
+    #[serde(default)]
+    pub status: DiscrepancyFilter,
+}
+
+/// A received line with its hashed ID already decoded
+#[derive(Debug, Clone)]
+pub struct ReceivedLine {
+    pub item_id: u64,
+    pub quantity_received: i32,
+    pub quantity_damaged: i32,
+    pub quantity_short: i32,
+    pub quantity_over: i32,
+    pub comment: Option<String>,
+}
+
+impl ReceivedLine {
+    /// A line is reconciled when exactly the expected quantity arrived in good condition
+    pub fn is_reconciled(&self, quantity_expected: i32) -> bool {
+        self.quantity_received == quantity_expected
+            && self.quantity_damaged == 0
+            && self.quantity_short == 0
+            && self.quantity_over == 0
+    }
+}
+
+/// Outcome of a store receiving an order
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReceivingResult {
+    Recorded {
+        receipt_id: u64,
+        status: StoreOrderStatus,
+    },
+    OrderNotFound,
+    /// The order is not at a point where the store may confirm delivery
+    NotReceivable(StoreOrderStatus),
+    /// Per-line validation errors; nothing was recorded
+    InvalidLines(Vec<String>),
+}
+
+/// Outcome of an admin resolving a discrepancy report
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiscrepancyResolveResult {
+    /// `order_delivered` is set when this closed the last open report and the order moved to Delivered
+    Resolved { order_delivered: bool },
+    NotFound,
+    AlreadyResolved,
+}
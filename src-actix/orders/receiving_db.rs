@@ -0,0 +1,353 @@
+use crate::auth::UserRole;
+use crate::orders::orders_db::{lock_order_status, record_status_change, settle_delivered_lines};
+use crate::orders::receiving_data::{
+    DiscrepancyFilter, DiscrepancyResolveResult, OrderReceiptLineRecord, OrderReceiptRecord,
+    OrderReceiptWithLines, ReceivedLine, ReceivingResult,
+};
+use crate::orders::store_order_status::StoreOrderStatus;
+use log::*;
+use sqlx::{Executor, MySqlPool, Row};
+use std::collections::{HashMap, HashSet};
+
+pub async fn initialize(pool: &MySqlPool) -> anyhow::Result<()> {
+    pool.execute(
+        r#"
+        CREATE TABLE IF NOT EXISTS `order_receipts` (
+            `id` BIGINT UNSIGNED NOT NULL AUTO_INCREMENT,
+            `order_id` BIGINT UNSIGNED NOT NULL,
+            `received_by` BIGINT UNSIGNED NOT NULL,
+            `has_discrepancies` BOOLEAN NOT NULL DEFAULT FALSE,
+            `notes` TEXT,
+            `resolved_at` DATETIME,
+            `resolved_by` BIGINT UNSIGNED,
+            `resolution_notes` TEXT,
+            `created_at` DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            PRIMARY KEY (`id`),
+            FOREIGN KEY (`order_id`) REFERENCES `orders`(`id`) ON DELETE CASCADE,
+            INDEX `idx_order_id` (`order_id`),
+            INDEX `idx_open_discrepancies` (`has_discrepancies`, `resolved_at`)
+        )
+        "#,
+    )
+    .await?;
+
+    pool.execute(
+        r#"
+        CREATE TABLE IF NOT EXISTS `order_receipt_lines` (
+            `id` BIGINT UNSIGNED NOT NULL AUTO_INCREMENT,
+            `receipt_id` BIGINT UNSIGNED NOT NULL,
+            `order_item_id` BIGINT UNSIGNED NOT NULL,
+            `quantity_expected` INT NOT NULL,
+            `quantity_received` INT NOT NULL,
+            `quantity_damaged` INT NOT NULL DEFAULT 0,
+            `quantity_short` INT NOT NULL DEFAULT 0,
+            `quantity_over` INT NOT NULL DEFAULT 0,
+            `comment` TEXT,
+            PRIMARY KEY (`id`),
+            FOREIGN KEY (`receipt_id`) REFERENCES `order_receipts`(`id`) ON DELETE CASCADE,
+            FOREIGN KEY (`order_item_id`) REFERENCES `order_items`(`id`) ON DELETE CASCADE,
+            INDEX `idx_receipt_id` (`receipt_id`)
+        )
+        "#,
+    )
+    .await?;
+
+    Ok(())
+}
+
+impl OrderReceiptRecord {
+    pub async fn get_by_id(pool: &MySqlPool, id: u64) -> anyhow::Result<Option<Self>> {
+        let receipt = sqlx::query_as::<_, Self>(
+            r#"
+            SELECT r.*, o.order_number, o.store_id
+            FROM `order_receipts` r
+            JOIN `orders` o ON r.order_id = o.id
+            WHERE r.id = ?
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(receipt)
+    }
+
+    pub async fn get_for_order(
+        pool: &MySqlPool,
+        order_id: u64,
+    ) -> anyhow::Result<Vec<OrderReceiptWithLines>> {
+        let receipts = sqlx::query_as::<_, Self>(
+            r#"
+            SELECT r.*, o.order_number, o.store_id
+            FROM `order_receipts` r
+            JOIN `orders` o ON r.order_id = o.id
+            WHERE r.order_id = ?
+            ORDER BY r.created_at ASC
+            "#,
+        )
+        .bind(order_id)
+        .fetch_all(pool)
+        .await?;
+
+        Self::with_lines(pool, receipts).await
+    }
+
+    /// Discrepancy reports across all orders, newest first
+    pub async fn get_discrepancies(
+        pool: &MySqlPool,
+        filter: DiscrepancyFilter,
+    ) -> anyhow::Result<Vec<OrderReceiptWithLines>> {
+        let resolution_clause = match filter {
+            DiscrepancyFilter::Open => "AND r.resolved_at IS NULL",
+            DiscrepancyFilter::Resolved => "AND r.resolved_at IS NOT NULL",
+            DiscrepancyFilter::All => "",
+        };
+        let query = format!(
+            r#"
+            SELECT r.*, o.order_number, o.store_id
+            FROM `order_receipts` r
+            JOIN `orders` o ON r.order_id = o.id
+            WHERE r.has_discrepancies = TRUE {resolution_clause}
+            ORDER BY r.created_at DESC
+            "#
+        );
+        let receipts = sqlx::query_as::<_, Self>(&query).fetch_all(pool).await?;
+
+        Self::with_lines(pool, receipts).await
+    }
+
+    async fn with_lines(
+        pool: &MySqlPool,
+        receipts: Vec<Self>,
+    ) -> anyhow::Result<Vec<OrderReceiptWithLines>> {
+        let mut result = Vec::with_capacity(receipts.len());
+        for receipt in receipts {
+            let lines = OrderReceiptLineRecord::get_for_receipt(pool, receipt.id).await?;
+            result.push(OrderReceiptWithLines { receipt, lines });
+        }
+        Ok(result)
+    }
+
+    /// Records the store receiving an order and closes out its delivery.
+    ///
+    /// Each line is compared with what the store should have received: the shipped
+    /// quantity once shipments have been recorded, otherwise the ordered quantity less
+    /// anything backordered. The order moves to Delivered when every line reconciles
+    /// and to Delivered with discrepancies otherwise, in which case the receipt is the
+    /// discrepancy report admins work from.
+    pub async fn record(
+        pool: &MySqlPool,
+        order_id: u64,
+        role: &UserRole,
+        received_by: u64,
+        lines: &[ReceivedLine],
+        notes: Option<&str>,
+    ) -> anyhow::Result<ReceivingResult> {
+        let mut transaction = pool.begin().await?;
+
+        let Some(status) = lock_order_status(&mut transaction, order_id).await? else {
+            return Ok(ReceivingResult::OrderNotFound);
+        };
+        if !status.can_transition_to(&StoreOrderStatus::Delivered, role) {
+            return Ok(ReceivingResult::NotReceivable(status));
+        }
+
+        let rows = sqlx::query(
+            "SELECT `id`, `quantity`, `quantity_shipped`, `quantity_backordered` FROM `order_items` WHERE `order_id` = ? FOR UPDATE",
+        )
+        .bind(order_id)
+        .fetch_all(&mut *transaction)
+        .await?;
+        let shipments_recorded = rows.iter().any(|row| row.get::<i32, _>("quantity_shipped") > 0);
+        // item_id -> expected quantity
+        let mut expected: HashMap<u64, i32> = rows
+            .iter()
+            .map(|row| {
+                let quantity: i32 = row.get("quantity");
+                let shipped: i32 = row.get("quantity_shipped");
+                let backordered: i32 = row.get("quantity_backordered");
+                let expected = if shipments_recorded { shipped } else { quantity - backordered };
+                (row.get("id"), expected)
+            })
+            .collect();
+
+        let mut errors = Vec::new();
+        let mut seen = HashSet::new();
+        for line in lines {
+            let item_hash = serde_hash::hashids::encode_single(line.item_id);
+            if !expected.contains_key(&line.item_id) {
+                errors.push(format!("Item {} is not on this order", item_hash));
+            } else if !seen.insert(line.item_id) {
+                errors.push(format!("Item {} is listed more than once", item_hash));
+            }
+            if line.quantity_received < 0
+                || line.quantity_damaged < 0
+                || line.quantity_short < 0
+                || line.quantity_over < 0
+            {
+                errors.push(format!("Item {}: quantities cannot be negative", item_hash));
+            }
+        }
+        for item_id in expected.keys() {
+            if !seen.contains(item_id) {
+                errors.push(format!(
+                    "Item {} is missing from the receipt",
+                    serde_hash::hashids::encode_single(*item_id)
+                ));
+            }
+        }
+        if !errors.is_empty() {
+            return Ok(ReceivingResult::InvalidLines(errors));
+        }
+
+        let has_discrepancies = lines
+            .iter()
+            .any(|line| !line.is_reconciled(expected[&line.item_id]));
+
+        let receipt_result = sqlx::query(
+            "INSERT INTO `order_receipts` (`order_id`, `received_by`, `has_discrepancies`, `notes`) VALUES (?, ?, ?, ?)",
+        )
+        .bind(order_id)
+        .bind(received_by)
+        .bind(has_discrepancies)
+        .bind(notes)
+        .execute(&mut *transaction)
+        .await?;
+        let receipt_id = receipt_result.last_insert_id();
+
+        for line in lines {
+            let quantity_expected = expected.remove(&line.item_id).unwrap_or_default();
+            sqlx::query(
+                r#"
+                INSERT INTO `order_receipt_lines`
+                    (`receipt_id`, `order_item_id`, `quantity_expected`, `quantity_received`,
+                     `quantity_damaged`, `quantity_short`, `quantity_over`, `comment`)
+                VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+                "#,
+            )
+            .bind(receipt_id)
+            .bind(line.item_id)
+            .bind(quantity_expected)
+            .bind(line.quantity_received)
+            .bind(line.quantity_damaged)
+            .bind(line.quantity_short)
+            .bind(line.quantity_over)
+            .bind(&line.comment)
+            .execute(&mut *transaction)
+            .await?;
+        }
+
+        let new_status = if has_discrepancies {
+            StoreOrderStatus::DeliveredWithDiscrepancies
+        } else {
+            StoreOrderStatus::Delivered
+        };
+        sqlx::query("UPDATE `orders` SET `status` = ?, `status_changed_to_completed` = NOW() WHERE `id` = ?")
+            .bind(new_status.as_db_str())
+            .bind(order_id)
+            .execute(&mut *transaction)
+            .await?;
+        settle_delivered_lines(&mut transaction, order_id, received_by).await?;
+        record_status_change(
+            &mut transaction,
+            order_id,
+            Some(&status),
+            &new_status,
+            received_by,
+            notes,
+        )
+        .await?;
+
+        transaction.commit().await?;
+        Ok(ReceivingResult::Recorded {
+            receipt_id,
+            status: new_status,
+        })
+    }
+
+    /// Marks a discrepancy report resolved. Once an order has no open reports left
+    /// it moves from Delivered with discrepancies to Delivered.
+    pub async fn resolve(
+        pool: &MySqlPool,
+        id: u64,
+        resolved_by: u64,
+        resolution_notes: &str,
+    ) -> anyhow::Result<DiscrepancyResolveResult> {
+        let mut transaction = pool.begin().await?;
+
+        let receipt_row = sqlx::query(
+            "SELECT `order_id`, `resolved_at` FROM `order_receipts` WHERE `id` = ? AND `has_discrepancies` = TRUE FOR UPDATE",
+        )
+        .bind(id)
+        .fetch_optional(&mut *transaction)
+        .await?;
+        let Some(receipt_row) = receipt_row else {
+            return Ok(DiscrepancyResolveResult::NotFound);
+        };
+        let resolved_at: Option<chrono::NaiveDateTime> = receipt_row.get("resolved_at");
+        if resolved_at.is_some() {
+            return Ok(DiscrepancyResolveResult::AlreadyResolved);
+        }
+        let order_id: u64 = receipt_row.get("order_id");
+
+        sqlx::query(
+            "UPDATE `order_receipts` SET `resolved_at` = NOW(), `resolved_by` = ?, `resolution_notes` = ? WHERE `id` = ?",
+        )
+        .bind(resolved_by)
+        .bind(resolution_notes)
+        .bind(id)
+        .execute(&mut *transaction)
+        .await?;
+
+        let status = lock_order_status(&mut transaction, order_id).await?;
+        let open_reports: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM `order_receipts` WHERE `order_id` = ? AND `has_discrepancies` = TRUE AND `resolved_at` IS NULL",
+        )
+        .bind(order_id)
+        .fetch_one(&mut *transaction)
+        .await?;
+
+        let order_delivered =
+            status == Some(StoreOrderStatus::DeliveredWithDiscrepancies) && open_reports == 0;
+        if order_delivered {
+            sqlx::query("UPDATE `orders` SET `status` = ? WHERE `id` = ?")
+                .bind(StoreOrderStatus::Delivered.as_db_str())
+                .bind(order_id)
+                .execute(&mut *transaction)
+                .await?;
+            record_status_change(
+                &mut transaction,
+                order_id,
+                Some(&StoreOrderStatus::DeliveredWithDiscrepancies),
+                &StoreOrderStatus::Delivered,
+                resolved_by,
+                Some(resolution_notes),
+            )
+            .await?;
+        }
+
+        transaction.commit().await?;
+        info!("Discrepancy report {} for order {} resolved by user {}", id, order_id, resolved_by);
+        Ok(DiscrepancyResolveResult::Resolved { order_delivered })
+    }
+}
+
+impl OrderReceiptLineRecord {
+    pub async fn get_for_receipt(pool: &MySqlPool, receipt_id: u64) -> anyhow::Result<Vec<Self>> {
+        let lines = sqlx::query_as::<_, Self>(
+            r#"
+            SELECT l.*, oi.product_id, p.name AS product_name, p.sku AS product_sku
+            FROM `order_receipt_lines` l
+            JOIN `order_items` oi ON l.order_item_id = oi.id
+            JOIN `products` p ON oi.product_id = p.id
+            WHERE l.receipt_id = ?
+            ORDER BY l.id ASC
+            "#,
+        )
+        .bind(receipt_id)
+        .fetch_all(pool)
+        .await?;
+
+        Ok(lines)
+    }
+}
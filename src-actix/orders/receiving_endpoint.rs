@@ -0,0 +1,181 @@
+use crate::auth::{ClaimsExtractor, UserRole};
+use crate::orders::orders_data::{OrderWithItemsDto, StoreOrderRecord};
+use crate::orders::orders_endpoint::check_order_edit_access;
+use crate::orders::receiving_data::{
+    DiscrepancyListQuery, DiscrepancyResolveResult, OrderReceiptLineRecord, OrderReceiptRecord,
+    OrderReceiptWithLines, ReceiveOrderRequest, ReceivedLine, ReceivingResult,
+    ResolveDiscrepancyRequest,
+};
+use actix_web::{get, post, web, HttpRequest, HttpResponse, Responder};
+use database_common_lib::{database_connection::DatabaseConnectionData, http_error::Result};
+use serde_json::json;
+
+#[post("/{id}/receive")]
+pub async fn receive_order(
+    req: HttpRequest,
+    connection_data: web::Data<DatabaseConnectionData>,
+    path: web::Path<String>,
+    request: web::Json<ReceiveOrderRequest>,
+) -> Result<impl Responder> {
+    let pool = connection_data.get_pool().await?;
+    let order_id = serde_hash::hashids::decode_single(path.as_str())?;
+
+    let claims = req
+        .get_claims()
+        .ok_or_else(|| anyhow::anyhow!("Authentication required"))?;
+    let role = UserRole::from_str(&claims.role)?;
+
+    if let Some(denied) = check_order_edit_access(&req, &pool, order_id).await? {
+        return Ok(denied);
+    }
+
+    let mut lines = Vec::with_capacity(request.items.len());
+    for line in &request.items {
+        lines.push(ReceivedLine {
+            item_id: serde_hash::hashids::decode_single(&line.item_id)?,
+            quantity_received: line.quantity_received,
+            quantity_damaged: line.quantity_damaged,
+            quantity_short: line.quantity_short,
+            quantity_over: line.quantity_over,
+            comment: line.comment.clone(),
+        });
+    }
+
+    let result = OrderReceiptRecord::record(
+        &pool,
+        order_id,
+        &role,
+        claims.sub,
+        &lines,
+        request.notes.as_deref(),
+    )
+    .await?;
+
+    match result {
+        ReceivingResult::Recorded { receipt_id, status } => {
+            let order = StoreOrderRecord::get_with_items(&pool, order_id)
+                .await?
+                .ok_or_else(|| anyhow::anyhow!("Order not found after update"))?;
+            let receipt = OrderReceiptRecord::get_by_id(&pool, receipt_id)
+                .await?
+                .ok_or_else(|| anyhow::anyhow!("Failed to retrieve receipt"))?;
+            let lines = OrderReceiptLineRecord::get_for_receipt(&pool, receipt_id).await?;
+            Ok(HttpResponse::Created().json(json!({
+                "success": true,
+                "data": OrderWithItemsDto::from(&order),
+                "receipt": OrderReceiptWithLines { receipt, lines },
+                "message": format!("Order received: {}", status)
+            })))
+        }
+        ReceivingResult::OrderNotFound => Ok(HttpResponse::NotFound().json(json!({
+            "success": false,
+            "error": "Order not found"
+        }))),
+        ReceivingResult::NotReceivable(status) => Ok(HttpResponse::Conflict().json(json!({
+            "success": false,
+            "error": format!("Order is {} and cannot be received", status)
+        }))),
+        ReceivingResult::InvalidLines(errors) => Ok(HttpResponse::BadRequest().json(json!({
+            "success": false,
+            "error": "Receipt could not be recorded",
+            "details": errors
+        }))),
+    }
+}
+
+#[get("/{id}/receipts")]
+pub async fn get_order_receipts(
+    req: HttpRequest,
+    connection_data: web::Data<DatabaseConnectionData>,
+    path: web::Path<String>,
+) -> Result<impl Responder> {
+    let pool = connection_data.get_pool().await?;
+    let order_id = serde_hash::hashids::decode_single(path.as_str())?;
+
+    if let Some(denied) = check_order_edit_access(&req, &pool, order_id).await? {
+        return Ok(denied);
+    }
+
+    let receipts = OrderReceiptRecord::get_for_order(&pool, order_id).await?;
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "data": receipts
+    })))
+}
+
+#[get("/discrepancies")]
+pub async fn get_discrepancies(
+    req: HttpRequest,
+    connection_data: web::Data<DatabaseConnectionData>,
+    query: web::Query<DiscrepancyListQuery>,
+) -> Result<impl Responder> {
+    let pool = connection_data.get_pool().await?;
+
+    let claims = req
+        .get_claims()
+        .ok_or_else(|| anyhow::anyhow!("Authentication required"))?;
+    if claims.role != "admin" {
+        return Ok(HttpResponse::Forbidden().json(json!({
+            "success": false,
+            "error": "Admin access required"
+        })));
+    }
+
+    let reports = OrderReceiptRecord::get_discrepancies(&pool, query.status).await?;
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "data": reports
+    })))
+}
+
+#[post("/discrepancies/{id}/resolve")]
+pub async fn resolve_discrepancy(
+    req: HttpRequest,
+    connection_data: web::Data<DatabaseConnectionData>,
+    path: web::Path<String>,
+    request: web::Json<ResolveDiscrepancyRequest>,
+) -> Result<impl Responder> {
+    let pool = connection_data.get_pool().await?;
+    let receipt_id = serde_hash::hashids::decode_single(path.as_str())?;
+
+    let claims = req
+        .get_claims()
+        .ok_or_else(|| anyhow::anyhow!("Authentication required"))?;
+    if claims.role != "admin" {
+        return Ok(HttpResponse::Forbidden().json(json!({
+            "success": false,
+            "error": "Admin access required"
+        })));
+    }
+
+    let resolution_notes = request.resolution_notes.trim();
+    if resolution_notes.is_empty() {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "success": false,
+            "error": "Resolution notes are required"
+        })));
+    }
+
+    match OrderReceiptRecord::resolve(&pool, receipt_id, claims.sub, resolution_notes).await? {
+        DiscrepancyResolveResult::Resolved { order_delivered } => {
+            let receipt = OrderReceiptRecord::get_by_id(&pool, receipt_id)
+                .await?
+                .ok_or_else(|| anyhow::anyhow!("Discrepancy report not found after update"))?;
+            let lines = OrderReceiptLineRecord::get_for_receipt(&pool, receipt_id).await?;
+            Ok(HttpResponse::Ok().json(json!({
+                "success": true,
+                "data": OrderReceiptWithLines { receipt, lines },
+                "order_delivered": order_delivered,
+                "message": "Discrepancy resolved successfully"
+            })))
+        }
+        DiscrepancyResolveResult::NotFound => Ok(HttpResponse::NotFound().json(json!({
+            "success": false,
+            "error": "Discrepancy report not found"
+        }))),
+        DiscrepancyResolveResult::AlreadyResolved => Ok(HttpResponse::Conflict().json(json!({
+            "success": false,
+            "error": "Discrepancy report is already resolved"
+        }))),
+    }
+}
@@ -1,40 +1,205 @@
+use crate::auth::UserRole;
 use serde::{Deserialize, Serialize};
 use std::fmt;
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum StoreOrderStatus {
+    /// Over the store's approval threshold; the warehouse does not see it until an admin approves
+    AwaitingApproval,
     Pending,
+    OnHold,
+    Picking,
+    Packed,
     Shipped,
     Delivered,
+    /// Received by the store with damaged, short or over lines awaiting admin review
+    DeliveredWithDiscrepancies,
+    Cancelled,
 }
 
 impl StoreOrderStatus {
+    pub const ALL: [StoreOrderStatus; 9] = [
+        StoreOrderStatus::AwaitingApproval,
+        StoreOrderStatus::Pending,
+        StoreOrderStatus::OnHold,
+        StoreOrderStatus::Picking,
+        StoreOrderStatus::Packed,
+        StoreOrderStatus::Shipped,
+        StoreOrderStatus::Delivered,
+        StoreOrderStatus::DeliveredWithDiscrepancies,
+        StoreOrderStatus::Cancelled,
+    ];
+
     pub fn as_db_str(&self) -> &'static str {
         match self {
+            StoreOrderStatus::AwaitingApproval => "AWAITING_APPROVAL",
             StoreOrderStatus::Pending => "PENDING",
+            StoreOrderStatus::OnHold => "ON_HOLD",
+            StoreOrderStatus::Picking => "PICKING",
+            StoreOrderStatus::Packed => "PACKED",
             StoreOrderStatus::Shipped => "SHIPPED",
             StoreOrderStatus::Delivered => "DELIVERED",
+            StoreOrderStatus::DeliveredWithDiscrepancies => "DELIVERED_WITH_DISCREPANCIES",
+            StoreOrderStatus::Cancelled => "CANCELLED",
         }
     }
 
     pub fn from_str_case_insensitive(s: &str) -> Option<Self> {
         match s.to_ascii_uppercase().as_str() {
+            "AWAITING_APPROVAL" | "AWAITINGAPPROVAL" => Some(StoreOrderStatus::AwaitingApproval),
             "PENDING" => Some(StoreOrderStatus::Pending),
+            "ON_HOLD" | "ONHOLD" => Some(StoreOrderStatus::OnHold),
+            "PICKING" => Some(StoreOrderStatus::Picking),
+            "PACKED" => Some(StoreOrderStatus::Packed),
             "SHIPPED" => Some(StoreOrderStatus::Shipped),
             "DELIVERED" => Some(StoreOrderStatus::Delivered),
+            "DELIVERED_WITH_DISCREPANCIES" | "DELIVEREDWITHDISCREPANCIES" => {
+                Some(StoreOrderStatus::DeliveredWithDiscrepancies)
+            }
+            "CANCELLED" | "CANCELED" => Some(StoreOrderStatus::Cancelled),
             _ => None,
         }
     }
+
+    /// Comma separated list of quoted DB values, used for the `status` ENUM column
+    pub fn sql_enum_values() -> String {
+        Self::ALL
+            .iter()
+            .map(|s| format!("'{}'", s.as_db_str()))
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+
+    /// Statuses the given role may move an order to from this status.
+    ///
+    /// This is the single source of truth for order transitions:
+    /// - Orders over the store's approval threshold start as Awaiting approval. Admins approve
+    ///   them (-> Pending) or reject them (-> Cancelled); stores may withdraw them. Nothing
+    ///   moves an order back into Awaiting approval.
+    /// - Admins drive the warehouse flow (Pending -> Picking -> Packed -> Shipped) and may
+    ///   hold, release or cancel an order until it ships.
+    /// - Stores may cancel their own order while it is still Pending and confirm delivery
+    ///   once the warehouse has started on it.
+    /// - Delivered with discrepancies is only entered by receiving and only left by
+    ///   resolving its discrepancy reports, never through a plain status change.
+    pub fn allowed_transitions(&self, role: &UserRole) -> &'static [StoreOrderStatus] {
+        use StoreOrderStatus::*;
+        match role {
+            UserRole::Admin => match self {
+                AwaitingApproval => &[Pending, Cancelled],
+                Pending => &[Picking, Packed, Shipped, Delivered, OnHold, Cancelled],
+                OnHold => &[Pending, Cancelled],
+                Picking => &[Packed, Shipped, OnHold, Cancelled],
+                Packed => &[Shipped, OnHold, Cancelled],
+                Shipped => &[Delivered],
+                Delivered | DeliveredWithDiscrepancies | Cancelled => &[],
+            },
+            UserRole::Store => match self {
+                AwaitingApproval => &[Cancelled],
+                Pending => &[Delivered, Cancelled],
+                Picking | Packed | Shipped => &[Delivered],
+                OnHold | Delivered | DeliveredWithDiscrepancies | Cancelled => &[],
+            },
+        }
+    }
+
+    pub fn can_transition_to(&self, target: &StoreOrderStatus, role: &UserRole) -> bool {
+        self.allowed_transitions(role).contains(target)
+    }
+
+    /// Whether the order can no longer change status
+    pub fn is_terminal(&self) -> bool {
+        matches!(self, StoreOrderStatus::Delivered | StoreOrderStatus::Cancelled)
+    }
 }
 
 impl fmt::Display for StoreOrderStatus {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}", match self {
+            StoreOrderStatus::AwaitingApproval => "Awaiting approval",
             StoreOrderStatus::Pending => "Pending",
+            StoreOrderStatus::OnHold => "On Hold",
+            StoreOrderStatus::Picking => "Picking",
+            StoreOrderStatus::Packed => "Packed",
             StoreOrderStatus::Shipped => "Shipped",
             StoreOrderStatus::Delivered => "Delivered",
+            StoreOrderStatus::DeliveredWithDiscrepancies => "Delivered with discrepancies",
+            StoreOrderStatus::Cancelled => "Cancelled",
         })
     }
 }
 
 impl Default for StoreOrderStatus { fn default() -> Self { StoreOrderStatus::Pending } }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn db_strings_round_trip() {
+        for status in StoreOrderStatus::ALL {
+            assert_eq!(
+                StoreOrderStatus::from_str_case_insensitive(status.as_db_str()),
+                Some(status)
+            );
+        }
+        assert_eq!(
+            StoreOrderStatus::from_str_case_insensitive("canceled"),
+            Some(StoreOrderStatus::Cancelled)
+        );
+    }
+
+    #[test]
+    fn terminal_statuses_have_no_transitions() {
+        for role in [UserRole::Admin, UserRole::Store] {
+            assert!(StoreOrderStatus::Delivered.allowed_transitions(&role).is_empty());
+            assert!(StoreOrderStatus::Cancelled.allowed_transitions(&role).is_empty());
+        }
+    }
+
+    #[test]
+    fn stores_can_only_cancel_pending_orders() {
+        let store = UserRole::Store;
+        assert!(StoreOrderStatus::Pending.can_transition_to(&StoreOrderStatus::Cancelled, &store));
+        assert!(!StoreOrderStatus::Picking.can_transition_to(&StoreOrderStatus::Cancelled, &store));
+        assert!(!StoreOrderStatus::Pending.can_transition_to(&StoreOrderStatus::Shipped, &store));
+        assert!(StoreOrderStatus::Shipped.can_transition_to(&StoreOrderStatus::Delivered, &store));
+    }
+
+    #[test]
+    fn admins_cannot_cancel_shipped_orders() {
+        let admin = UserRole::Admin;
+        assert!(StoreOrderStatus::Packed.can_transition_to(&StoreOrderStatus::Cancelled, &admin));
+        assert!(!StoreOrderStatus::Shipped.can_transition_to(&StoreOrderStatus::Cancelled, &admin));
+        assert!(StoreOrderStatus::OnHold.can_transition_to(&StoreOrderStatus::Pending, &admin));
+    }
+
+    #[test]
+    fn discrepancies_are_not_a_manual_status() {
+        for role in [UserRole::Admin, UserRole::Store] {
+            assert!(StoreOrderStatus::DeliveredWithDiscrepancies
+                .allowed_transitions(&role)
+                .is_empty());
+            for status in StoreOrderStatus::ALL {
+                assert!(!status.can_transition_to(&StoreOrderStatus::DeliveredWithDiscrepancies, &role));
+            }
+        }
+    }
+
+    #[test]
+    fn approval_is_only_left_by_approving_or_rejecting() {
+        let admin = UserRole::Admin;
+        let store = UserRole::Store;
+        let awaiting = StoreOrderStatus::AwaitingApproval;
+        assert!(awaiting.can_transition_to(&StoreOrderStatus::Pending, &admin));
+        assert!(awaiting.can_transition_to(&StoreOrderStatus::Cancelled, &admin));
+        assert!(!awaiting.can_transition_to(&StoreOrderStatus::Picking, &admin));
+        assert!(!awaiting.can_transition_to(&StoreOrderStatus::Pending, &store));
+        assert!(awaiting.can_transition_to(&StoreOrderStatus::Cancelled, &store));
+        for role in [admin, store] {
+            for status in StoreOrderStatus::ALL {
+                assert!(!status.can_transition_to(&StoreOrderStatus::AwaitingApproval, &role));
+            }
+        }
+    }
+}
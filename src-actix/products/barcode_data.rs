@@ -0,0 +1,240 @@
+use crate::products::barcode_svg::Symbology;
+use crate::products::products_data::ProductWithCategory;
+use serde::{Deserialize, Serialize};
+use serde_hash::HashIds;
+use sqlx::mysql::MySqlRow;
+use sqlx::{FromRow, Row};
+
+/// Longest barcode stored, which also keeps internal codes short enough to print
+pub const MAX_BARCODE_CHARS: usize = 48;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BarcodeKind {
+    /// 12-digit UPC-A
+    UpcA,
+    /// 13-digit EAN-13
+    Ean13,
+    /// 8-digit EAN-8
+    Ean8,
+    /// Our own printable code, e.g. a shelf tag; no check digit
+    Internal,
+}
+
+impl BarcodeKind {
+    pub const ALL: [BarcodeKind; 4] = [
+        BarcodeKind::UpcA,
+        BarcodeKind::Ean13,
+        BarcodeKind::Ean8,
+        BarcodeKind::Internal,
+    ];
+
+    pub fn as_db_str(&self) -> &'static str {
+        match self {
+            BarcodeKind::UpcA => "UPC_A",
+            BarcodeKind::Ean13 => "EAN_13",
+            BarcodeKind::Ean8 => "EAN_8",
+            BarcodeKind::Internal => "INTERNAL",
+        }
+    }
+
+    pub fn from_str_case_insensitive(s: &str) -> Option<Self> {
+        match s.to_ascii_uppercase().as_str() {
+            "UPC_A" => Some(BarcodeKind::UpcA),
+            "EAN_13" => Some(BarcodeKind::Ean13),
+            "EAN_8" => Some(BarcodeKind::Ean8),
+            "INTERNAL" => Some(BarcodeKind::Internal),
+            _ => None,
+        }
+    }
+
+    /// Comma separated list of quoted DB values, used for the `kind` ENUM column
+    pub fn sql_enum_values() -> String {
+        Self::ALL
+            .iter()
+            .map(|k| format!("'{}'", k.as_db_str()))
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+
+    /// Digits in a code of this kind, check digit included; `None` for internal codes
+    pub fn gtin_length(&self) -> Option<usize> {
+        match self {
+            BarcodeKind::UpcA => Some(12),
+            BarcodeKind::Ean13 => Some(13),
+            BarcodeKind::Ean8 => Some(8),
+            BarcodeKind::Internal => None,
+        }
+    }
+
+    /// The kind a scanned code most likely is: a UPC or EAN when its length and check digit
+    /// fit, otherwise internal
+    pub fn detect(code: &str) -> Self {
+        [BarcodeKind::UpcA, BarcodeKind::Ean13, BarcodeKind::Ean8]
+            .into_iter()
+            .find(|kind| kind.validate(code).is_ok())
+            .unwrap_or(BarcodeKind::Internal)
+    }
+
+    /// Checks `code` is a well-formed barcode of this kind, including its check digit
+    pub fn validate(&self, code: &str) -> Result<(), String> {
+        match self.gtin_length() {
+            Some(length) => {
+                if code.len() != length || !code.bytes().all(|b| b.is_ascii_digit()) {
+                    return Err(format!("A {} barcode is {} digits", self.label(), length));
+                }
+                let (body, check) = code.split_at(length - 1);
+                let expected = gtin_check_digit(body);
+                if check != expected.to_string() {
+                    return Err(format!(
+                        "Check digit of {} should be {}, not {}",
+                        code, expected, check
+                    ));
+                }
+                Ok(())
+            }
+            None => {
+                if code.is_empty() || code.len() > MAX_BARCODE_CHARS {
+                    return Err(format!(
+                        "An internal barcode is 1 to {} characters",
+                        MAX_BARCODE_CHARS
+                    ));
+                }
+                // Code 128 can print every visible ASCII character and the space
+                if !code.bytes().all(|b| (b' '..=b'~').contains(&b)) {
+                    return Err("An internal barcode can only use visible ASCII characters".to_string());
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            BarcodeKind::UpcA => "UPC-A",
+            BarcodeKind::Ean13 => "EAN-13",
+            BarcodeKind::Ean8 => "EAN-8",
+            BarcodeKind::Internal => "internal",
+        }
+    }
+}
+
+/// GS1 check digit for the digits before it: weights 3 and 1 alternate from the right
+pub fn gtin_check_digit(digits: &str) -> u32 {
+    let sum: u32 = digits
+        .bytes()
+        .rev()
+        .enumerate()
+        .map(|(i, b)| u32::from(b - b'0') * if i % 2 == 0 { 3 } else { 1 })
+        .sum();
+    (10 - sum % 10) % 10
+}
+
+/// Codes a scan may be stored under. Scanners report a UPC-A either as printed or as the
+/// equivalent EAN-13 with a leading zero, so each form also finds the other.
+pub fn lookup_candidates(code: &str) -> Vec<String> {
+    let mut candidates = vec![code.to_string()];
+    if code.bytes().all(|b| b.is_ascii_digit()) {
+        match code.len() {
+            12 => candidates.push(format!("0{}", code)),
+            13 if code.starts_with('0') => candidates.push(code[1..].to_string()),
+            _ => {}
+        }
+    }
+    candidates
+}
+
+#[derive(HashIds, Debug, Clone)]
+pub struct ProductBarcodeRecord {
+    #[hash]
+    pub id: u64,
+    #[hash]
+    pub product_id: u64,
+    pub code: String,
+    pub kind: BarcodeKind,
+    pub created_at: chrono::NaiveDateTime,
+}
+
+impl<'r> FromRow<'r, MySqlRow> for ProductBarcodeRecord {
+    fn from_row(row: &'r MySqlRow) -> Result<Self, sqlx::Error> {
+        let kind_raw: String = row.try_get("kind")?;
+        let kind = BarcodeKind::from_str_case_insensitive(&kind_raw).ok_or_else(|| {
+            sqlx::Error::Protocol(format!("Unexpected barcode kind value '{}'", kind_raw))
+        })?;
+        Ok(Self {
+            id: row.try_get("id")?,
+            product_id: row.try_get("product_id")?,
+            code: row.try_get("code")?,
+            kind,
+            created_at: row.try_get("created_at")?,
+        })
+    }
+}
+
+/// A product found by scanning, with the barcode that matched; `None` when the scan was
+/// the product's SKU
+#[derive(Debug)]
+pub struct BarcodeLookup {
+    pub product: ProductWithCategory,
+    pub barcode: Option<ProductBarcodeRecord>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateBarcodeRequest {
+    pub code: String,
+    /// Detected from the code when left out
+    pub kind: Option<BarcodeKind>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BarcodeSvgQuery {
+    /// Picked from the code when left out
+    pub symbology: Option<Symbology>,
+    pub height: Option<f32>,
+    /// Print the code under the bars; on by default
+    pub text: Option<bool>,
+}
+
+/// Outcome of adding a barcode to a product
+#[derive(Debug, Clone)]
+pub enum BarcodeAddResult {
+    Added(ProductBarcodeRecord),
+    ProductNotFound,
+    /// The code, or its UPC-A/EAN-13 equivalent, already belongs to a product
+    AlreadyAssigned { product_id: u64 },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_digits_are_validated() {
+        assert_eq!(gtin_check_digit("400638133393"), 1);
+        assert!(BarcodeKind::Ean13.validate("4006381333931").is_ok());
+        assert!(BarcodeKind::Ean13.validate("4006381333932").is_err());
+        assert!(BarcodeKind::UpcA.validate("036000291452").is_ok());
+        assert!(BarcodeKind::Ean8.validate("96385074").is_ok());
+        assert!(BarcodeKind::UpcA.validate("03600029145").is_err());
+        assert!(BarcodeKind::Internal.validate("SHELF A-12").is_ok());
+        assert!(BarcodeKind::Internal.validate("caf\u{e9}").is_err());
+    }
+
+    #[test]
+    fn kinds_are_detected_and_upc_matches_its_ean_form() {
+        assert_eq!(BarcodeKind::detect("036000291452"), BarcodeKind::UpcA);
+        assert_eq!(BarcodeKind::detect("4006381333931"), BarcodeKind::Ean13);
+        assert_eq!(BarcodeKind::detect("036000291453"), BarcodeKind::Internal);
+        assert_eq!(
+            lookup_candidates("036000291452"),
+            vec!["036000291452", "0036000291452"]
+        );
+        assert_eq!(
+            lookup_candidates("0036000291452"),
+            vec!["0036000291452", "036000291452"]
+        );
+        for kind in BarcodeKind::ALL {
+            assert_eq!(BarcodeKind::from_str_case_insensitive(kind.as_db_str()), Some(kind));
+        }
+    }
+}
@@ -0,0 +1,144 @@
+use crate::products::barcode_data::{
+    lookup_candidates, BarcodeAddResult, BarcodeKind, BarcodeLookup, ProductBarcodeRecord,
+};
+use crate::products::products_data::ProductRecord;
+use log::*;
+use sqlx::{Executor, MySqlPool};
+
+pub async fn initialize(pool: &MySqlPool) -> anyhow::Result<()> {
+    let kind_values = BarcodeKind::sql_enum_values();
+
+    pool.execute(
+        format!(
+            r#"
+        CREATE TABLE IF NOT EXISTS `product_barcodes` (
+            `id` BIGINT UNSIGNED NOT NULL AUTO_INCREMENT,
+            `product_id` BIGINT UNSIGNED NOT NULL,
+            `code` VARCHAR(48) NOT NULL,
+            `kind` ENUM({kind_values}) NOT NULL,
+            `created_at` DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            PRIMARY KEY (`id`),
+            UNIQUE KEY `uq_code` (`code`),
+            FOREIGN KEY (`product_id`) REFERENCES `products`(`id`) ON DELETE CASCADE,
+            INDEX `idx_product_id` (`product_id`)
+        )
+        "#
+        )
+        .as_str(),
+    )
+    .await?;
+
+    Ok(())
+}
+
+impl ProductBarcodeRecord {
+    pub async fn get_by_id(pool: &MySqlPool, id: u64) -> anyhow::Result<Option<Self>> {
+        let barcode = sqlx::query_as::<_, Self>("SELECT * FROM `product_barcodes` WHERE `id` = ?")
+            .bind(id)
+            .fetch_optional(pool)
+            .await?;
+        Ok(barcode)
+    }
+
+    pub async fn get_for_product(pool: &MySqlPool, product_id: u64) -> anyhow::Result<Vec<Self>> {
+        let barcodes = sqlx::query_as::<_, Self>(
+            "SELECT * FROM `product_barcodes` WHERE `product_id` = ? ORDER BY `id` ASC",
+        )
+        .bind(product_id)
+        .fetch_all(pool)
+        .await?;
+        Ok(barcodes)
+    }
+
+    /// The barcode stored under `code` or its UPC-A/EAN-13 equivalent
+    pub async fn find_by_code(pool: &MySqlPool, code: &str) -> anyhow::Result<Option<Self>> {
+        let candidates = lookup_candidates(code);
+        let placeholders = vec!["?"; candidates.len()].join(", ");
+        let query = format!(
+            "SELECT * FROM `product_barcodes` WHERE `code` IN ({placeholders}) ORDER BY `id` ASC LIMIT 1"
+        );
+        let mut sql_query = sqlx::query_as::<_, Self>(&query);
+        for candidate in &candidates {
+            sql_query = sql_query.bind(candidate);
+        }
+        Ok(sql_query.fetch_optional(pool).await?)
+    }
+
+    /// Assigns an already validated `code` to a product
+    pub async fn add(
+        pool: &MySqlPool,
+        product_id: u64,
+        code: &str,
+        kind: BarcodeKind,
+    ) -> anyhow::Result<BarcodeAddResult> {
+        if ProductRecord::get_by_id_simple(pool, product_id).await?.is_none() {
+            return Ok(BarcodeAddResult::ProductNotFound);
+        }
+        if let Some(existing) = Self::find_by_code(pool, code).await? {
+            return Ok(BarcodeAddResult::AlreadyAssigned {
+                product_id: existing.product_id,
+            });
+        }
+
+        let result = sqlx::query(
+            "INSERT INTO `product_barcodes` (`product_id`, `code`, `kind`) VALUES (?, ?, ?)",
+        )
+        .bind(product_id)
+        .bind(code)
+        .bind(kind.as_db_str())
+        .execute(pool)
+        .await;
+        let barcode_id = match result {
+            Ok(result) => result.last_insert_id(),
+            // Another request assigned the same code in the meantime
+            Err(sqlx::Error::Database(e)) if e.is_unique_violation() => {
+                let existing = Self::find_by_code(pool, code)
+                    .await?
+                    .ok_or_else(|| anyhow::anyhow!("Barcode {} disappeared", code))?;
+                return Ok(BarcodeAddResult::AlreadyAssigned {
+                    product_id: existing.product_id,
+                });
+            }
+            Err(e) => return Err(e.into()),
+        };
+        info!("Added barcode {} ({}) to product {}", code, kind.as_db_str(), product_id);
+
+        let barcode = Self::get_by_id(pool, barcode_id)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("Failed to retrieve created barcode"))?;
+        Ok(BarcodeAddResult::Added(barcode))
+    }
+
+    pub async fn delete(pool: &MySqlPool, product_id: u64, id: u64) -> anyhow::Result<bool> {
+        let result =
+            sqlx::query("DELETE FROM `product_barcodes` WHERE `id` = ? AND `product_id` = ?")
+                .bind(id)
+                .bind(product_id)
+                .execute(pool)
+                .await?;
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+impl BarcodeLookup {
+    /// The active product a scanned code belongs to. Codes without a barcode entry are tried
+    /// as a SKU, which is what pick lists and manifests print.
+    pub async fn find(pool: &MySqlPool, code: &str) -> anyhow::Result<Option<Self>> {
+        let (product_id, barcode) = match ProductBarcodeRecord::find_by_code(pool, code).await? {
+            Some(barcode) => (barcode.product_id, Some(barcode)),
+            None => {
+                let product_id: Option<u64> =
+                    sqlx::query_scalar("SELECT `id` FROM `products` WHERE `sku` = ?")
+                        .bind(code)
+                        .fetch_optional(pool)
+                        .await?;
+                let Some(product_id) = product_id else {
+                    return Ok(None);
+                };
+                (product_id, None)
+            }
+        };
+        let product = ProductRecord::get_by_id(pool, product_id).await?;
+        Ok(product.map(|product| Self { product, barcode }))
+    }
+}
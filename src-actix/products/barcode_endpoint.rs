@@ -0,0 +1,183 @@
+use crate::auth::ClaimsExtractor;
+use crate::products::barcode_data::{
+    BarcodeAddResult, BarcodeKind, BarcodeLookup, BarcodeSvgQuery, CreateBarcodeRequest,
+    ProductBarcodeRecord,
+};
+use crate::products::barcode_svg::{render_svg, SvgOptions, Symbology};
+use crate::products::ProductRecord;
+use actix_web::{delete, get, post, web, HttpRequest, HttpResponse, Responder};
+use database_common_lib::{database_connection::DatabaseConnectionData, http_error::Result};
+use serde_json::json;
+
+const MAX_SVG_HEIGHT: f32 = 500.0;
+
+/// The product a scanned code belongs to, by barcode or SKU
+#[get("/barcode/{code}")]
+pub async fn lookup_barcode(
+    connection_data: web::Data<DatabaseConnectionData>,
+    path: web::Path<String>,
+) -> Result<impl Responder> {
+    let code = path.trim();
+    let pool = connection_data.get_pool().await?;
+
+    match BarcodeLookup::find(&pool, code).await? {
+        Some(lookup) => Ok(HttpResponse::Ok().json(json!({
+            "success": true,
+            "data": lookup.product,
+            "barcode": lookup.barcode
+        }))),
+        None => Ok(HttpResponse::NotFound().json(json!({
+            "success": false,
+            "error": "No product has this barcode"
+        }))),
+    }
+}
+
+/// Any code drawn as an SVG barcode, for labels and shelf tags
+#[get("/barcode/{code}/svg")]
+pub async fn get_barcode_svg(
+    path: web::Path<String>,
+    query: web::Query<BarcodeSvgQuery>,
+) -> Result<impl Responder> {
+    let code = path.trim();
+    let mut options = SvgOptions::default();
+    if let Some(height) = query.height {
+        if !height.is_finite() || height <= 0.0 || height > MAX_SVG_HEIGHT {
+            return Ok(HttpResponse::BadRequest().json(json!({
+                "success": false,
+                "error": format!("Height must be between 0 and {}", MAX_SVG_HEIGHT)
+            })));
+        }
+        options.bar_height = height;
+    }
+    if let Some(text) = query.text {
+        options.show_text = text;
+    }
+    let symbology = query.symbology.unwrap_or_else(|| Symbology::for_code(code));
+
+    match render_svg(symbology, code, options) {
+        Ok(svg) => Ok(HttpResponse::Ok().content_type("image/svg+xml").body(svg)),
+        Err(error) => Ok(HttpResponse::BadRequest().json(json!({
+            "success": false,
+            "error": error
+        }))),
+    }
+}
+
+#[get("/{id}/barcodes")]
+pub async fn get_product_barcodes(
+    req: HttpRequest,
+    connection_data: web::Data<DatabaseConnectionData>,
+    path: web::Path<String>,
+) -> Result<impl Responder> {
+    let Some(claims) = req.get_claims() else {
+        return Ok(HttpResponse::Unauthorized().json(json!({
+            "error": "Authentication required"
+        })));
+    };
+    if claims.role != "admin" {
+        return Ok(HttpResponse::Forbidden().json(json!({
+            "error": "Admin access required"
+        })));
+    }
+    let pool = connection_data.get_pool().await?;
+    let product_id = serde_hash::hashids::decode_single(path.as_str())?;
+
+    if ProductRecord::get_by_id_simple(&pool, product_id).await?.is_none() {
+        return Ok(HttpResponse::NotFound().json(json!({
+            "error": "Product not found"
+        })));
+    }
+    let barcodes = ProductBarcodeRecord::get_for_product(&pool, product_id).await?;
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "data": barcodes,
+        "count": barcodes.len()
+    })))
+}
+
+#[post("/{id}/barcodes")]
+pub async fn add_product_barcode(
+    req: HttpRequest,
+    connection_data: web::Data<DatabaseConnectionData>,
+    path: web::Path<String>,
+    request: web::Json<CreateBarcodeRequest>,
+) -> Result<impl Responder> {
+    let Some(claims) = req.get_claims() else {
+        return Ok(HttpResponse::Unauthorized().json(json!({
+            "error": "Authentication required"
+        })));
+    };
+    if claims.role != "admin" {
+        return Ok(HttpResponse::Forbidden().json(json!({
+            "error": "Admin access required"
+        })));
+    }
+    let product_id = serde_hash::hashids::decode_single(path.as_str())?;
+
+    let code = request.code.trim();
+    let kind = request.kind.unwrap_or_else(|| BarcodeKind::detect(code));
+    if let Err(error) = kind.validate(code) {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "success": false,
+            "error": "Invalid barcode",
+            "details": [error]
+        })));
+    }
+
+    let pool = connection_data.get_pool().await?;
+    match ProductBarcodeRecord::add(&pool, product_id, code, kind).await? {
+        BarcodeAddResult::Added(barcode) => Ok(HttpResponse::Created().json(json!({
+            "success": true,
+            "data": barcode
+        }))),
+        BarcodeAddResult::ProductNotFound => Ok(HttpResponse::NotFound().json(json!({
+            "error": "Product not found"
+        }))),
+        BarcodeAddResult::AlreadyAssigned { product_id: owner } => {
+            let error = if owner == product_id {
+                "This product already has this barcode"
+            } else {
+                "This barcode belongs to another product"
+            };
+            Ok(HttpResponse::Conflict().json(json!({
+                "success": false,
+                "error": error,
+                "product_id": serde_hash::hashids::encode_single(owner)
+            })))
+        }
+    }
+}
+
+#[delete("/{id}/barcodes/{barcode_id}")]
+pub async fn delete_product_barcode(
+    req: HttpRequest,
+    connection_data: web::Data<DatabaseConnectionData>,
+    path: web::Path<(String, String)>,
+) -> Result<impl Responder> {
+    let Some(claims) = req.get_claims() else {
+        return Ok(HttpResponse::Unauthorized().json(json!({
+            "error": "Authentication required"
+        })));
+    };
+    if claims.role != "admin" {
+        return Ok(HttpResponse::Forbidden().json(json!({
+            "error": "Admin access required"
+        })));
+    }
+    let (product_id, barcode_id) = path.into_inner();
+    let product_id = serde_hash::hashids::decode_single(product_id.as_str())?;
+    let barcode_id = serde_hash::hashids::decode_single(barcode_id.as_str())?;
+
+    let pool = connection_data.get_pool().await?;
+    if ProductBarcodeRecord::delete(&pool, product_id, barcode_id).await? {
+        Ok(HttpResponse::Ok().json(json!({
+            "success": true,
+            "message": "Barcode removed"
+        })))
+    } else {
+        Ok(HttpResponse::NotFound().json(json!({
+            "error": "Barcode not found"
+        })))
+    }
+}
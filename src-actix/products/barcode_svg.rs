@@ -0,0 +1,285 @@
+use crate::auth::email_service::escape_html;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// Bar and space widths of each Code 128 symbol, in modules, starting with a bar. The stop
+/// symbol ends with the two-module termination bar.
+const CODE128_PATTERNS: [&str; 107] = [
+    "212222", "222122", "222221", "121223", "121322", "131222", "122213", "122312", "132212",
+    "221213", "221312", "231212", "112232", "122132", "122231", "113222", "123122", "123221",
+    "223211", "221132", "221231", "213212", "223112", "312131", "311222", "321122", "321221",
+    "312212", "322112", "322211", "212123", "212321", "232121", "111323", "131123", "131321",
+    "112313", "132113", "132311", "211313", "231113", "231311", "112133", "112331", "132131",
+    "113123", "113321", "133121", "313121", "211331", "231131", "213113", "213311", "213131",
+    "311123", "311321", "331121", "312113", "312311", "332111", "314111", "221411", "431111",
+    "111224", "111422", "121124", "121421", "141122", "141221", "112214", "112412", "122114",
+    "122411", "142112", "142211", "241211", "221114", "413111", "241112", "134111", "111242",
+    "121142", "121241", "114212", "124112", "124211", "411212", "421112", "421211", "212141",
+    "214121", "412121", "111143", "111341", "131141", "114113", "114311", "411113", "411311",
+    "113141", "114131", "311141", "411131", "211412", "211214", "211232", "2331112",
+];
+const CODE128_START_B: usize = 104;
+const CODE128_START_C: usize = 105;
+const CODE128_STOP: usize = 106;
+
+/// EAN left-hand digits with odd parity; even parity (G) is these reversed and inverted,
+/// right-hand digits (R) are these inverted
+const EAN_L_CODES: [&str; 10] = [
+    "0001101", "0011001", "0010011", "0111101", "0100011", "0110001", "0101111", "0111011",
+    "0110111", "0001011",
+];
+/// Parity of the six left-hand digits for each leading digit: L odd, G even
+const EAN13_PARITY: [&str; 10] = [
+    "LLLLLL", "LLGLGG", "LLGGLG", "LLGGGL", "LGLLGG", "LGGLLG", "LGGGLL", "LGLGLG", "LGLGGL",
+    "LGGLGL",
+];
+
+const QUIET_ZONE_MODULES: usize = 10;
+const DEFAULT_MODULE_WIDTH: f32 = 2.0;
+const DEFAULT_BAR_HEIGHT: f32 = 60.0;
+const TEXT_HEIGHT: f32 = 14.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Symbology {
+    Code128,
+    Ean13,
+}
+
+impl Symbology {
+    /// EAN-13 for codes that are one (or a UPC-A, printed in its EAN-13 form), Code 128
+    /// for anything else
+    pub fn for_code(code: &str) -> Self {
+        let all_digits = !code.is_empty() && code.bytes().all(|b| b.is_ascii_digit());
+        if all_digits && matches!(code.len(), 12 | 13) && ean13_digits(code).is_ok() {
+            Symbology::Ean13
+        } else {
+            Symbology::Code128
+        }
+    }
+}
+
+/// Size and text options for [`render_svg`]
+#[derive(Debug, Clone, Copy)]
+pub struct SvgOptions {
+    /// Width of the narrowest bar, in pixels
+    pub module_width: f32,
+    pub bar_height: f32,
+    /// Print the code in text under the bars
+    pub show_text: bool,
+}
+
+impl Default for SvgOptions {
+    fn default() -> Self {
+        Self {
+            module_width: DEFAULT_MODULE_WIDTH,
+            bar_height: DEFAULT_BAR_HEIGHT,
+            show_text: true,
+        }
+    }
+}
+
+/// Symbol values for `text`, start symbol and check symbol included. All-digit text of
+/// even length uses code set C, two digits per symbol; anything else code set B.
+fn code128_symbols(text: &str) -> Result<Vec<usize>, String> {
+    if text.is_empty() {
+        return Err("Nothing to encode".to_string());
+    }
+    let bytes = text.as_bytes();
+    let mut symbols = if bytes.len().is_multiple_of(2) && bytes.iter().all(u8::is_ascii_digit) {
+        let mut symbols = vec![CODE128_START_C];
+        symbols.extend(
+            bytes
+                .chunks(2)
+                .map(|pair| usize::from(pair[0] - b'0') * 10 + usize::from(pair[1] - b'0')),
+        );
+        symbols
+    } else {
+        if let Some(c) = text.chars().find(|c| !(' '..='~').contains(c)) {
+            return Err(format!("Code 128 cannot encode '{}'", c));
+        }
+        let mut symbols = vec![CODE128_START_B];
+        symbols.extend(bytes.iter().map(|b| usize::from(b - b' ')));
+        symbols
+    };
+    let checksum = symbols
+        .iter()
+        .enumerate()
+        .map(|(position, value)| position.max(1) * value)
+        .sum::<usize>()
+        % 103;
+    symbols.push(checksum);
+    symbols.push(CODE128_STOP);
+    Ok(symbols)
+}
+
+/// Modules of a Code 128 barcode, `true` for bar
+pub fn code128_modules(text: &str) -> Result<Vec<bool>, String> {
+    let mut modules = Vec::new();
+    for symbol in code128_symbols(text)? {
+        for (i, width) in CODE128_PATTERNS[symbol].bytes().enumerate() {
+            let is_bar = i % 2 == 0;
+            modules.extend(std::iter::repeat_n(is_bar, usize::from(width - b'0')));
+        }
+    }
+    Ok(modules)
+}
+
+/// The 13 digits of an EAN-13, or a UPC-A with its leading zero added, check digit verified
+fn ean13_digits(code: &str) -> Result<Vec<u8>, String> {
+    let code = match code.len() {
+        12 => format!("0{}", code),
+        13 => code.to_string(),
+        _ => return Err("EAN-13 needs 13 digits, or 12 for a UPC-A".to_string()),
+    };
+    if !code.bytes().all(|b| b.is_ascii_digit()) {
+        return Err("EAN-13 can only encode digits".to_string());
+    }
+    let digits: Vec<u8> = code.bytes().map(|b| b - b'0').collect();
+    if crate::products::barcode_data::gtin_check_digit(&code[..12]) != u32::from(digits[12]) {
+        return Err(format!("Check digit of {} is wrong", code));
+    }
+    Ok(digits)
+}
+
+/// Modules of an EAN-13 barcode, `true` for bar
+pub fn ean13_modules(code: &str) -> Result<Vec<bool>, String> {
+    let digits = ean13_digits(code)?;
+    let bits = |pattern: &str| pattern.bytes().map(|b| b == b'1').collect::<Vec<bool>>();
+
+    let mut modules = bits("101");
+    let parity = EAN13_PARITY[usize::from(digits[0])].as_bytes();
+    for (i, digit) in digits[1..7].iter().enumerate() {
+        let odd = bits(EAN_L_CODES[usize::from(*digit)]);
+        if parity[i] == b'G' {
+            modules.extend(odd.iter().rev().map(|bar| !bar));
+        } else {
+            modules.extend(odd);
+        }
+    }
+    modules.extend(bits("01010"));
+    for digit in &digits[7..] {
+        modules.extend(bits(EAN_L_CODES[usize::from(*digit)]).iter().map(|bar| !bar));
+    }
+    modules.extend(bits("101"));
+    Ok(modules)
+}
+
+/// Renders `code` as a standalone SVG image
+pub fn render_svg(symbology: Symbology, code: &str, options: SvgOptions) -> Result<String, String> {
+    let modules = match symbology {
+        Symbology::Code128 => code128_modules(code)?,
+        Symbology::Ean13 => ean13_modules(code)?,
+    };
+    let module_width = options.module_width;
+    let quiet_zone = QUIET_ZONE_MODULES as f32 * module_width;
+    let width = modules.len() as f32 * module_width + 2.0 * quiet_zone;
+    let height = options.bar_height + if options.show_text { TEXT_HEIGHT } else { 0.0 };
+
+    let mut svg = format!(
+        r##"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}" role="img" aria-label="{label}"><rect width="100%" height="100%" fill="#fff"/><g fill="#000">"##,
+        label = escape_html(code)
+    );
+    // One rectangle per run of bar modules
+    let mut index = 0;
+    while index < modules.len() {
+        if !modules[index] {
+            index += 1;
+            continue;
+        }
+        let run = modules[index..].iter().take_while(|bar| **bar).count();
+        svg.push_str(&format!(
+            r#"<rect x="{}" y="0" width="{}" height="{}"/>"#,
+            quiet_zone + index as f32 * module_width,
+            run as f32 * module_width,
+            options.bar_height
+        ));
+        index += run;
+    }
+    svg.push_str("</g>");
+    if options.show_text {
+        svg.push_str(&format!(
+            r#"<text x="{}" y="{}" font-family="monospace" font-size="{}" text-anchor="middle">{}</text>"#,
+            width / 2.0,
+            height - 2.0,
+            TEXT_HEIGHT - 2.0,
+            escape_html(code)
+        ));
+    }
+    svg.push_str("</svg>");
+    Ok(svg)
+}
+
+/// Registers the `barcode` filter, which turns a code into an inline SVG for the manifest and
+/// pick list templates: `{{ line.product_sku | barcode(height=30) | safe }}`
+pub fn register_tera_filter(tera: &mut tera::Tera) {
+    tera.register_filter("barcode", barcode_filter);
+}
+
+/// Codes that cannot be drawn render as nothing rather than failing the page
+fn barcode_filter(
+    value: &tera::Value,
+    args: &HashMap<String, tera::Value>,
+) -> tera::Result<tera::Value> {
+    let code = value
+        .as_str()
+        .ok_or_else(|| tera::Error::msg("The barcode filter needs a string"))?;
+    let mut options = SvgOptions {
+        module_width: 1.0,
+        ..SvgOptions::default()
+    };
+    if let Some(height) = args.get("height").and_then(tera::Value::as_f64) {
+        options.bar_height = height as f32;
+    }
+    let svg = render_svg(Symbology::for_code(code), code, options).unwrap_or_default();
+    Ok(tera::Value::String(svg))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn code128_patterns_are_well_formed() {
+        let mut seen = std::collections::HashSet::new();
+        for (symbol, pattern) in CODE128_PATTERNS.iter().enumerate() {
+            let widths: Vec<u32> = pattern.bytes().map(|b| u32::from(b - b'0')).collect();
+            let expected_modules = if symbol == CODE128_STOP { 13 } else { 11 };
+            assert_eq!(widths.iter().sum::<u32>(), expected_modules, "symbol {}", symbol);
+            // Bars always add up to an even number of modules
+            let bars: u32 = widths.iter().step_by(2).sum();
+            assert_eq!(bars % 2, 0, "symbol {}", symbol);
+            assert!(seen.insert(*pattern), "symbol {} repeats a pattern", symbol);
+        }
+    }
+
+    #[test]
+    fn code128_picks_the_code_set_and_checksum() {
+        // Start B, "P" "J" "J" "1" "2" "3" "C", then the check symbol:
+        // (104 + 1×48 + 2×42 + 3×42 + 4×17 + 5×18 + 6×19 + 7×35) mod 103 = 55
+        assert_eq!(
+            code128_symbols("PJJ123C").unwrap(),
+            vec![104, 48, 42, 42, 17, 18, 19, 35, 55, 106]
+        );
+        assert_eq!(code128_symbols("1234").unwrap(), vec![105, 12, 34, 82, 106]);
+        assert!(code128_symbols("caf\u{e9}").is_err());
+        // 11 modules per symbol, 13 for the stop with its termination bar
+        assert_eq!(code128_modules("1234").unwrap().len(), 4 * 11 + 13);
+    }
+
+    #[test]
+    fn ean13_has_ninety_five_modules() {
+        let modules = ean13_modules("4006381333931").unwrap();
+        assert_eq!(modules.len(), 95);
+        // A leading 4 gives parities L G L L G G, so the third digit, 0, is written as G
+        let second_digit: String = modules[10..17].iter().map(|bar| if *bar { '1' } else { '0' }).collect();
+        assert_eq!(second_digit, "0100111");
+        assert_eq!(ean13_modules("036000291452").unwrap().len(), 95);
+        assert!(ean13_modules("4006381333932").is_err());
+        assert_eq!(Symbology::for_code("036000291452"), Symbology::Ean13);
+        assert_eq!(Symbology::for_code("SKU-1"), Symbology::Code128);
+
+        let svg = render_svg(Symbology::Code128, "A<B", SvgOptions::default()).unwrap();
+        assert!(svg.starts_with("<svg") && svg.contains("A&lt;B"));
+    }
+}
@@ -0,0 +1,129 @@
+use crate::products::products_data::{
+    serialize_hash_id, ProductFilter, ProductQuery, ProductRecord, UpdateProductRequest,
+};
+use serde::{Deserialize, Serialize};
+use serde_hash::hashids::decode_single;
+
+/// Most products one bulk update may change
+pub const MAX_BULK_PRODUCTS: usize = 1000;
+
+#[derive(Debug, Deserialize)]
+pub struct BulkUpdateRequest {
+    /// Hashed IDs of the products to change
+    pub ids: Option<Vec<String>>,
+    /// Listing filters choosing the products instead of `ids`; sorting and paging are ignored
+    pub filter: Option<ProductQuery>,
+    pub patch: UpdateProductRequest,
+}
+
+/// The products a bulk update applies to
+#[derive(Debug, Clone, PartialEq)]
+pub enum BulkSelection {
+    Ids(Vec<u64>),
+    Filter(ProductFilter),
+}
+
+impl BulkSelection {
+    /// Checks that exactly one of `ids` and `filter` is given, collecting every problem
+    pub fn from_request(
+        ids: Option<Vec<String>>,
+        filter: Option<ProductQuery>,
+    ) -> Result<Self, Vec<String>> {
+        match (ids, filter) {
+            (Some(hashes), None) => {
+                if hashes.is_empty() {
+                    return Err(vec!["ids cannot be empty".to_string()]);
+                }
+                if hashes.len() > MAX_BULK_PRODUCTS {
+                    return Err(vec![format!(
+                        "At most {} products can be updated at once",
+                        MAX_BULK_PRODUCTS
+                    )]);
+                }
+                let mut ids = Vec::with_capacity(hashes.len());
+                let mut errors = Vec::new();
+                for hash in &hashes {
+                    match decode_single(hash) {
+                        Ok(id) if !ids.contains(&id) => ids.push(id),
+                        Ok(_) => {}
+                        Err(_) => errors.push(format!("Invalid product ID: {}", hash)),
+                    }
+                }
+                if errors.is_empty() {
+                    Ok(BulkSelection::Ids(ids))
+                } else {
+                    Err(errors)
+                }
+            }
+            (None, Some(query)) => {
+                let filter = ProductFilter::from_query(query)?;
+                if !filter.has_conditions() {
+                    return Err(vec![
+                        "The filter must narrow the selection; list ids to change specific products"
+                            .to_string(),
+                    ]);
+                }
+                Ok(BulkSelection::Filter(filter))
+            }
+            (Some(_), Some(_)) => Err(vec!["Set either ids or filter, not both".to_string()]),
+            (None, None) => Err(vec!["Set either ids or filter".to_string()]),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BulkItemStatus {
+    Updated,
+    NotFound,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BulkItemResult {
+    #[serde(serialize_with = "serialize_hash_id")]
+    pub product_id: u64,
+    pub status: BulkItemStatus,
+    /// The product after the update
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub product: Option<ProductRecord>,
+}
+
+/// Outcome of a bulk update
+#[derive(Debug)]
+pub enum BulkUpdateResult {
+    /// One result per selected product, in ID order for a filter and request order for IDs
+    Applied(Vec<BulkItemResult>),
+    /// The filter matches more than [`MAX_BULK_PRODUCTS`]; nothing was changed
+    TooManyProducts,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn selection_needs_exactly_one_narrow_source() {
+        assert!(BulkSelection::from_request(None, None).is_err());
+        assert!(BulkSelection::from_request(Some(vec![]), None).is_err());
+        assert!(
+            BulkSelection::from_request(Some(vec!["a".into()]), Some(ProductQuery::default()))
+                .is_err()
+        );
+        let too_many = vec!["a".to_string(); MAX_BULK_PRODUCTS + 1];
+        assert!(BulkSelection::from_request(Some(too_many), None).is_err());
+
+        // An empty filter would select the whole catalogue
+        assert!(BulkSelection::from_request(None, Some(ProductQuery::default())).is_err());
+        let discontinued = ProductQuery {
+            search: Some(" discontinued ".to_string()),
+            is_active: Some(true),
+            ..ProductQuery::default()
+        };
+        match BulkSelection::from_request(None, Some(discontinued)) {
+            Ok(BulkSelection::Filter(filter)) => {
+                assert_eq!(filter.search.as_deref(), Some("discontinued"));
+            }
+            other => panic!("unexpected selection: {:?}", other),
+        }
+    }
+}
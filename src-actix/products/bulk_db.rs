@@ -0,0 +1,94 @@
+use crate::products::bulk_data::{
+    BulkItemResult, BulkItemStatus, BulkSelection, BulkUpdateResult, MAX_BULK_PRODUCTS,
+};
+use crate::products::products_data::{ProductFilter, ProductRecord, UpdateProductRequest};
+use crate::products::products_db::push_filter_conditions;
+use log::*;
+use sqlx::{MySql, MySqlPool, QueryBuilder, Transaction};
+
+impl ProductRecord {
+    /// Applies `patch` to every selected product in one transaction, so either all of them
+    /// change or, on error, none do. `category_id` is the patch's category, already decoded.
+    pub async fn bulk_update(
+        pool: &MySqlPool,
+        selection: &BulkSelection,
+        patch: &UpdateProductRequest,
+        category_id: Option<u64>,
+    ) -> anyhow::Result<BulkUpdateResult> {
+        let mut transaction = pool.begin().await?;
+        let ids = match selection {
+            BulkSelection::Ids(ids) => ids.clone(),
+            BulkSelection::Filter(filter) => {
+                let ids = lock_matching_ids(&mut transaction, filter).await?;
+                if ids.len() > MAX_BULK_PRODUCTS {
+                    return Ok(BulkUpdateResult::TooManyProducts);
+                }
+                ids
+            }
+        };
+
+        let mut results = Vec::with_capacity(ids.len());
+        for product_id in ids {
+            let product = Self::update_with_transaction(
+                &mut transaction,
+                product_id,
+                patch.name.as_deref(),
+                patch.description.as_deref(),
+                patch.sku.as_deref(),
+                category_id,
+                patch.image_url.as_deref(),
+                patch.is_active,
+                patch.price,
+                patch.bin_location.as_deref(),
+                patch.unit_type.map(|u| u8::from(u) as i32),
+                patch.case_pack_size,
+                patch.allow_backorder,
+                patch.reorder_point,
+            )
+            .await?;
+            let status = match product {
+                Some(_) => BulkItemStatus::Updated,
+                None => BulkItemStatus::NotFound,
+            };
+            results.push(BulkItemResult {
+                product_id,
+                status,
+                product,
+            });
+        }
+        transaction.commit().await?;
+
+        let updated = results
+            .iter()
+            .filter(|result| result.status == BulkItemStatus::Updated)
+            .count();
+        info!("Bulk update changed {} product(s)", updated);
+        Ok(BulkUpdateResult::Applied(results))
+    }
+}
+
+/// IDs of the products matching `filter`, locked until the transaction ends; one more than
+/// [`MAX_BULK_PRODUCTS`] at most, enough to tell the selection is too large
+async fn lock_matching_ids(
+    transaction: &mut Transaction<'_, MySql>,
+    filter: &ProductFilter,
+) -> anyhow::Result<Vec<u64>> {
+    let mut query = QueryBuilder::<MySql>::new(
+        r#"
+        SELECT p.id
+        FROM `products` p
+        JOIN `categories` c ON p.category_id = c.id
+        WHERE 1 = 1
+        "#,
+    );
+    push_filter_conditions(&mut query, filter);
+    query
+        .push(" ORDER BY p.id ASC LIMIT ")
+        .push_bind(MAX_BULK_PRODUCTS as u64 + 1)
+        .push(" FOR UPDATE");
+    let ids = query
+        .build_query_scalar::<u64>()
+        .fetch_all(&mut **transaction)
+        .await?;
+    Ok(ids)
+}
@@ -0,0 +1,92 @@
+use crate::auth::ClaimsExtractor;
+use crate::categories::categories_data::CategoryRecord;
+use crate::products::bulk_data::{
+    BulkItemStatus, BulkSelection, BulkUpdateRequest, BulkUpdateResult, MAX_BULK_PRODUCTS,
+};
+use crate::products::ProductRecord;
+use actix_web::{patch, web, HttpRequest, HttpResponse, Responder};
+use database_common_lib::{database_connection::DatabaseConnectionData, http_error::Result};
+use serde_json::json;
+
+/// Applies one `UpdateProductRequest` patch to a list of products or a filter selection,
+/// e.g. to deactivate discontinued items or move products to another category
+#[patch("")]
+pub async fn bulk_update_products(
+    req: HttpRequest,
+    connection_data: web::Data<DatabaseConnectionData>,
+    request: web::Json<BulkUpdateRequest>,
+) -> Result<impl Responder> {
+    let Some(claims) = req.get_claims() else {
+        return Ok(HttpResponse::Unauthorized().json(json!({
+            "error": "Authentication required"
+        })));
+    };
+    if claims.role != "admin" {
+        return Ok(HttpResponse::Forbidden().json(json!({
+            "error": "Admin access required"
+        })));
+    }
+    let BulkUpdateRequest { ids, filter, patch } = request.into_inner();
+
+    let mut errors = Vec::new();
+    let selection = match BulkSelection::from_request(ids, filter) {
+        Ok(selection) => Some(selection),
+        Err(selection_errors) => {
+            errors.extend(selection_errors);
+            None
+        }
+    };
+    if patch.is_empty() {
+        errors.push("The patch does not change anything".to_string());
+    }
+    if patch.sku.is_some() {
+        errors.push("SKUs are unique and cannot be set in bulk".to_string());
+    }
+    errors.extend(patch.validation_errors());
+    let category_id = match patch.category_id.as_deref() {
+        Some(hash) => match serde_hash::hashids::decode_single(hash) {
+            Ok(id) => Some(id),
+            Err(_) => {
+                errors.push(format!("Invalid category ID: {}", hash));
+                None
+            }
+        },
+        None => None,
+    };
+
+    let pool = connection_data.get_pool().await?;
+    if let Some(category_id) = category_id {
+        if CategoryRecord::get_by_id(&pool, category_id).await?.is_none() {
+            errors.push("Category not found".to_string());
+        }
+    }
+    let Some(selection) = selection.filter(|_| errors.is_empty()) else {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "success": false,
+            "error": "Invalid bulk update",
+            "details": errors
+        })));
+    };
+
+    match ProductRecord::bulk_update(&pool, &selection, &patch, category_id).await? {
+        BulkUpdateResult::Applied(results) => {
+            let updated = results
+                .iter()
+                .filter(|result| result.status == BulkItemStatus::Updated)
+                .count();
+            Ok(HttpResponse::Ok().json(json!({
+                "success": true,
+                "updated": updated,
+                "not_found": results.len() - updated,
+                "data": results
+            })))
+        }
+        BulkUpdateResult::TooManyProducts => Ok(HttpResponse::BadRequest().json(json!({
+            "success": false,
+            "error": format!(
+                "The filter matches more than {} products; narrow it and try again",
+                MAX_BULK_PRODUCTS
+            )
+        }))),
+    }
+}
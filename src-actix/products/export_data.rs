@@ -0,0 +1,53 @@
+use crate::products::import_data::ProductFileFormat;
+use crate::products::products_data::serialize_hash_id;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+/// Format of `GET /api/products/admin/export`; the rows are chosen by the listing filters
+#[derive(Debug, Deserialize)]
+pub struct ProductExportQuery {
+    #[serde(default)]
+    pub format: ProductFileFormat,
+}
+
+/// A product in the columns the import reads, so an edited export can be uploaded again.
+/// `stock_quantity` is for reference and ignored on import.
+#[derive(Debug, Serialize, FromRow)]
+pub struct ProductExportRow {
+    pub sku: String,
+    pub name: String,
+    pub description: String,
+    #[serde(serialize_with = "serialize_hash_id")]
+    pub category_id: u64,
+    pub category: String,
+    pub price: Decimal,
+    pub bin_location: String,
+    pub unit_type: i32,
+    pub case_pack_size: i32,
+    pub allow_backorder: bool,
+    pub reorder_point: Option<f32>,
+    pub is_active: bool,
+    pub image_url: Option<String>,
+    pub stock_quantity: f32,
+}
+
+impl ProductExportRow {
+    /// CSV header, in field order
+    pub const HEADERS: [&'static str; 14] = [
+        "sku",
+        "name",
+        "description",
+        "category_id",
+        "category",
+        "price",
+        "bin_location",
+        "unit_type",
+        "case_pack_size",
+        "allow_backorder",
+        "reorder_point",
+        "is_active",
+        "image_url",
+        "stock_quantity",
+    ];
+}
@@ -0,0 +1,32 @@
+use crate::products::export_data::ProductExportRow;
+use crate::products::products_data::ProductFilter;
+use crate::products::products_db::push_filter_conditions;
+use sqlx::{MySql, MySqlPool, QueryBuilder};
+
+impl ProductExportRow {
+    /// Every product matching `filter`, active or not unless it says otherwise, in its sort
+    /// order; paging is ignored
+    pub async fn get_all(pool: &MySqlPool, filter: &ProductFilter) -> anyhow::Result<Vec<Self>> {
+        let mut query = QueryBuilder::<MySql>::new(
+            r#"
+            SELECT p.sku, p.name, p.description, p.category_id, c.name AS category, p.price,
+                   p.bin_location, p.unit_type, p.case_pack_size, p.allow_backorder,
+                   p.reorder_point, p.is_active, p.image_url, p.stock_quantity
+            FROM `products` p
+            JOIN `categories` c ON p.category_id = c.id
+            WHERE 1 = 1
+            "#,
+        );
+        push_filter_conditions(&mut query, filter);
+        let direction = filter.direction.as_sql();
+        query.push(format!(
+            " ORDER BY {} {}, p.id {}",
+            filter.sort.column(),
+            direction,
+            direction
+        ));
+
+        let rows = query.build_query_as::<Self>().fetch_all(pool).await?;
+        Ok(rows)
+    }
+}
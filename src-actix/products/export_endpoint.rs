@@ -0,0 +1,67 @@
+use crate::auth::ClaimsExtractor;
+use crate::products::export_data::{ProductExportQuery, ProductExportRow};
+use crate::products::import_data::ProductFileFormat;
+use crate::products::products_data::{ProductFilter, ProductQuery};
+use actix_web::{get, web, HttpRequest, HttpResponse, Responder};
+use database_common_lib::{database_connection::DatabaseConnectionData, http_error::Result};
+use serde_json::json;
+
+/// Products matching the listing filters as a CSV or JSON download the import accepts back
+#[get("/export")]
+pub async fn export_products(
+    req: HttpRequest,
+    connection_data: web::Data<DatabaseConnectionData>,
+    query: web::Query<ProductExportQuery>,
+    filter_query: web::Query<ProductQuery>,
+) -> Result<impl Responder> {
+    let Some(claims) = req.get_claims() else {
+        return Ok(HttpResponse::Unauthorized().json(json!({
+            "error": "Authentication required"
+        })));
+    };
+    if claims.role != "admin" {
+        return Ok(HttpResponse::Forbidden().json(json!({
+            "error": "Admin access required"
+        })));
+    }
+    let filter = match ProductFilter::from_query(filter_query.into_inner()) {
+        Ok(filter) => filter,
+        Err(errors) => {
+            return Ok(HttpResponse::BadRequest().json(json!({
+                "success": false,
+                "error": "Invalid product filter",
+                "details": errors
+            })));
+        }
+    };
+
+    let pool = connection_data.get_pool().await?;
+    let rows = ProductExportRow::get_all(&pool, &filter).await?;
+
+    let file_name = format!("products-{}", chrono::Local::now().format("%Y%m%d-%H%M%S"));
+    let (content_type, extension, body) = match query.format {
+        ProductFileFormat::Csv => {
+            // The header is written by hand so an empty export still has one
+            let mut writer = csv::WriterBuilder::new()
+                .has_headers(false)
+                .from_writer(Vec::new());
+            writer.write_record(ProductExportRow::HEADERS)?;
+            for row in &rows {
+                writer.serialize(row)?;
+            }
+            let body = writer
+                .into_inner()
+                .map_err(|e| anyhow::anyhow!("Failed to write the CSV: {}", e))?;
+            ("text/csv; charset=utf-8", "csv", body)
+        }
+        ProductFileFormat::Json => ("application/json", "json", serde_json::to_vec(&rows)?),
+    };
+
+    Ok(HttpResponse::Ok()
+        .content_type(content_type)
+        .insert_header((
+            "Content-Disposition",
+            format!("attachment; filename=\"{}.{}\"", file_name, extension),
+        ))
+        .body(body))
+}
@@ -0,0 +1,650 @@
+use crate::products::products_data::{ProductRecord, UnitType};
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use serde_hash::hashids::{decode_single, encode_single};
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+
+/// Largest import body accepted
+pub const MAX_IMPORT_BYTES: usize = 10 * 1024 * 1024;
+pub const MAX_IMPORT_ROWS: usize = 5000;
+/// Column sizes of the `products` table
+const MAX_NAME_CHARS: usize = 255;
+const MAX_SKU_CHARS: usize = 100;
+const MAX_BIN_LOCATION_CHARS: usize = 100;
+const MAX_IMAGE_URL_CHARS: usize = 500;
+/// Largest price a `DECIMAL(10,2)` holds
+const MAX_PRICE_CENTS: i64 = 9_999_999_999;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProductFileFormat {
+    #[default]
+    Csv,
+    Json,
+}
+
+impl ProductFileFormat {
+    /// The format a request body declares in its `Content-Type`
+    pub fn from_content_type(content_type: &str) -> Option<Self> {
+        let mime = content_type.split(';').next()?.trim().to_ascii_lowercase();
+        match mime.as_str() {
+            "text/csv" | "application/csv" => Some(ProductFileFormat::Csv),
+            "application/json" => Some(ProductFileFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ProductImportQuery {
+    /// Taken from the `Content-Type` when left out
+    pub format: Option<ProductFileFormat>,
+    /// Report what would change without writing anything
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+/// One product of an import file, keyed by `sku`. Blank or missing fields keep the current
+/// value of an existing product; a new product takes the `CreateProductRequest` defaults.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct ProductImportRow {
+    pub sku: Option<String>,
+    pub name: Option<String>,
+    pub description: Option<String>,
+    /// Hashed ID; used over `category` when both are set
+    pub category_id: Option<String>,
+    /// Category name or hashed ID
+    pub category: Option<String>,
+    pub image_url: Option<String>,
+    pub price: Option<Decimal>,
+    pub bin_location: Option<String>,
+    pub unit_type: Option<UnitType>,
+    pub case_pack_size: Option<i32>,
+    pub allow_backorder: Option<bool>,
+    pub reorder_point: Option<f32>,
+    pub is_active: Option<bool>,
+}
+
+/// A row of an import file, or why it could not be read
+#[derive(Debug)]
+pub struct ParsedImportRow {
+    /// Line number in a CSV file, 1-based position in a JSON array
+    pub row: usize,
+    pub product: Result<ProductImportRow, String>,
+}
+
+/// Reads the rows of a CSV file with a header line, or of a JSON array of objects. A row
+/// that does not parse is reported on its own; `Err` means the file as a whole is unreadable.
+pub fn parse_import_file(
+    format: ProductFileFormat,
+    body: &[u8],
+) -> Result<Vec<ParsedImportRow>, String> {
+    let rows = match format {
+        ProductFileFormat::Csv => parse_csv(body)?,
+        ProductFileFormat::Json => parse_json(body)?,
+    };
+    if rows.is_empty() {
+        return Err("The file has no products".to_string());
+    }
+    if rows.len() > MAX_IMPORT_ROWS {
+        return Err(format!("At most {} products can be imported at once", MAX_IMPORT_ROWS));
+    }
+    Ok(rows)
+}
+
+fn parse_csv(body: &[u8]) -> Result<Vec<ParsedImportRow>, String> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(body);
+    // Spreadsheets tend to title their columns, so `Case Pack Size` reads as `case_pack_size`
+    let headers: csv::StringRecord = reader
+        .headers()
+        .map_err(|e| format!("Could not read the CSV header: {}", e))?
+        .iter()
+        .map(|header| header.to_ascii_lowercase().replace(' ', "_"))
+        .collect();
+    if !headers.iter().any(|header| header == "sku") {
+        return Err("The CSV needs a sku column".to_string());
+    }
+
+    let mut rows = Vec::new();
+    for record in reader.records() {
+        let record = record.map_err(|e| format!("Could not read the CSV: {}", e))?;
+        let row = record.position().map_or(rows.len() + 2, |p| p.line() as usize);
+        let product = record
+            .deserialize::<ProductImportRow>(Some(&headers))
+            .map(ProductImportRow::normalized)
+            .map_err(|e| match e.kind() {
+                csv::ErrorKind::Deserialize { err, .. } => {
+                    match err.field().and_then(|field| headers.get(field as usize)) {
+                        Some(column) => format!("{}: {}", column, err.kind()),
+                        None => err.kind().to_string(),
+                    }
+                }
+                _ => e.to_string(),
+            });
+        rows.push(ParsedImportRow { row, product });
+    }
+    Ok(rows)
+}
+
+fn parse_json(body: &[u8]) -> Result<Vec<ParsedImportRow>, String> {
+    let values: Vec<Value> = serde_json::from_slice(body)
+        .map_err(|e| format!("The JSON must be an array of products: {}", e))?;
+    Ok(values
+        .into_iter()
+        .enumerate()
+        .map(|(index, value)| ParsedImportRow {
+            row: index + 1,
+            product: serde_json::from_value::<ProductImportRow>(value)
+                .map(ProductImportRow::normalized)
+                .map_err(|e| e.to_string()),
+        })
+        .collect())
+}
+
+impl ProductImportRow {
+    /// Trims text fields, treating blank ones as missing
+    fn normalized(self) -> Self {
+        let text = |value: Option<String>| {
+            value
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty())
+        };
+        Self {
+            sku: text(self.sku),
+            name: text(self.name),
+            description: text(self.description),
+            category_id: text(self.category_id),
+            category: text(self.category),
+            image_url: text(self.image_url),
+            bin_location: text(self.bin_location),
+            ..self
+        }
+    }
+
+    /// The product this row describes: `current` with the row's fields laid over it, or a new
+    /// product when there is none. Every problem with the row is collected.
+    pub fn resolve(
+        self,
+        current: Option<&ProductValues>,
+        categories: &CategoryResolver,
+    ) -> Result<ProductValues, Vec<String>> {
+        let mut errors = Vec::new();
+        if current.is_none() {
+            let required = [
+                ("name", self.name.is_some()),
+                ("price", self.price.is_some()),
+                ("unit_type", self.unit_type.is_some()),
+            ];
+            for (field, _) in required.iter().filter(|(_, present)| !present) {
+                errors.push(format!("{} is required for a new product", field));
+            }
+        }
+        let (name, price, unit_type) = (self.name, self.price, self.unit_type);
+        let category_id = match (self.category_id, self.category) {
+            (Some(hash), _) => match categories.resolve_id(&hash) {
+                Ok(id) => Some(id),
+                Err(error) => {
+                    errors.push(error);
+                    None
+                }
+            },
+            (None, Some(reference)) => match categories.resolve(&reference) {
+                Ok(id) => Some(id),
+                Err(error) => {
+                    errors.push(error);
+                    None
+                }
+            },
+            (None, None) => {
+                if current.is_none() {
+                    errors.push("category is required for a new product".to_string());
+                }
+                None
+            }
+        };
+
+        let sku = self.sku.unwrap_or_default();
+        if sku.chars().count() > MAX_SKU_CHARS {
+            errors.push(format!("sku is longer than {} characters", MAX_SKU_CHARS));
+        }
+        if name.as_ref().is_some_and(|n| n.chars().count() > MAX_NAME_CHARS) {
+            errors.push(format!("name is longer than {} characters", MAX_NAME_CHARS));
+        }
+        if self.bin_location.as_ref().is_some_and(|b| b.chars().count() > MAX_BIN_LOCATION_CHARS) {
+            errors.push(format!("bin_location is longer than {} characters", MAX_BIN_LOCATION_CHARS));
+        }
+        if self.image_url.as_ref().is_some_and(|u| u.chars().count() > MAX_IMAGE_URL_CHARS) {
+            errors.push(format!("image_url is longer than {} characters", MAX_IMAGE_URL_CHARS));
+        }
+        if let Some(price) = price {
+            if price.is_sign_negative() {
+                errors.push("Price cannot be negative".to_string());
+            } else if price.normalize().scale() > 2 || price * Decimal::ONE_HUNDRED > Decimal::from(MAX_PRICE_CENTS) {
+                errors.push("Price must be in cents and below 100,000,000".to_string());
+            }
+        }
+        if self.case_pack_size.is_some_and(|size| size < 1) {
+            errors.push("Case pack size must be at least 1".to_string());
+        }
+        if self.reorder_point.is_some_and(|point| !point.is_finite() || point < 0.0) {
+            errors.push("Reorder point cannot be negative".to_string());
+        }
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        let values = match current {
+            Some(current) => ProductValues {
+                sku: current.sku.clone(),
+                name: name.unwrap_or_else(|| current.name.clone()),
+                description: self.description.unwrap_or_else(|| current.description.clone()),
+                category_id: category_id.unwrap_or(current.category_id),
+                image_url: self.image_url.or_else(|| current.image_url.clone()),
+                price: price.unwrap_or(current.price),
+                bin_location: self.bin_location.unwrap_or_else(|| current.bin_location.clone()),
+                unit_type: unit_type.unwrap_or(current.unit_type),
+                case_pack_size: self.case_pack_size.unwrap_or(current.case_pack_size),
+                allow_backorder: self.allow_backorder.unwrap_or(current.allow_backorder),
+                reorder_point: self.reorder_point.or(current.reorder_point),
+                is_active: self.is_active.unwrap_or(current.is_active),
+            },
+            // Every required field was checked above
+            None => ProductValues {
+                sku,
+                name: name.unwrap_or_default(),
+                description: self.description.unwrap_or_default(),
+                category_id: category_id.unwrap_or_default(),
+                image_url: self.image_url,
+                price: price.unwrap_or_default(),
+                bin_location: self.bin_location.unwrap_or_default(),
+                unit_type: unit_type.unwrap_or(UnitType::Each),
+                case_pack_size: self.case_pack_size.unwrap_or(1),
+                allow_backorder: self.allow_backorder.unwrap_or(false),
+                reorder_point: self.reorder_point,
+                is_active: self.is_active.unwrap_or(true),
+            },
+        };
+        Ok(values)
+    }
+}
+
+/// Every field an import can set
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProductValues {
+    pub sku: String,
+    pub name: String,
+    pub description: String,
+    pub category_id: u64,
+    pub image_url: Option<String>,
+    pub price: Decimal,
+    pub bin_location: String,
+    pub unit_type: UnitType,
+    pub case_pack_size: i32,
+    pub allow_backorder: bool,
+    pub reorder_point: Option<f32>,
+    pub is_active: bool,
+}
+
+impl From<&ProductRecord> for ProductValues {
+    fn from(product: &ProductRecord) -> Self {
+        let unit_type = u8::try_from(product.unit_type)
+            .ok()
+            .and_then(|unit_type| UnitType::try_from(unit_type).ok())
+            .unwrap_or(UnitType::Each);
+        Self {
+            sku: product.sku.clone(),
+            name: product.name.clone(),
+            description: product.description.clone(),
+            category_id: product.category_id,
+            image_url: product.image_url.clone(),
+            price: product.price,
+            bin_location: product.bin_location.clone(),
+            unit_type,
+            case_pack_size: product.case_pack_size,
+            allow_backorder: product.allow_backorder,
+            reorder_point: product.reorder_point,
+            is_active: product.is_active,
+        }
+    }
+}
+
+impl ProductValues {
+    /// Fields as they appear in an import report
+    fn report_fields(&self) -> [(&'static str, Value); 11] {
+        [
+            ("name", json!(self.name)),
+            ("description", json!(self.description)),
+            ("category_id", json!(encode_single(self.category_id))),
+            ("image_url", json!(self.image_url)),
+            ("price", json!(self.price.to_f64())),
+            ("bin_location", json!(self.bin_location)),
+            ("unit_type", json!(u8::from(self.unit_type))),
+            ("case_pack_size", json!(self.case_pack_size)),
+            ("allow_backorder", json!(self.allow_backorder)),
+            ("reorder_point", json!(self.reorder_point)),
+            ("is_active", json!(self.is_active)),
+        ]
+    }
+
+    /// The fields that differ from `current`, or every field of a new product
+    pub fn changes_from(&self, current: Option<&ProductValues>) -> Vec<FieldChange> {
+        let before = current.map(ProductValues::report_fields);
+        self.report_fields()
+            .into_iter()
+            .enumerate()
+            .filter_map(|(index, (field, to))| {
+                let from = before
+                    .as_ref()
+                    .map_or(Value::Null, |before| before[index].1.clone());
+                (current.is_none() || from != to).then_some(FieldChange { field, from, to })
+            })
+            .collect()
+    }
+}
+
+/// Finds active categories by name, ignoring case, or by hashed ID
+pub struct CategoryResolver {
+    by_name: HashMap<String, Vec<u64>>,
+    ids: HashSet<u64>,
+}
+
+impl CategoryResolver {
+    pub fn new<'a>(categories: impl IntoIterator<Item = (u64, &'a str)>) -> Self {
+        let mut by_name: HashMap<String, Vec<u64>> = HashMap::new();
+        let mut ids = HashSet::new();
+        for (id, name) in categories {
+            by_name.entry(name.trim().to_lowercase()).or_default().push(id);
+            ids.insert(id);
+        }
+        Self { by_name, ids }
+    }
+
+    /// A category by name, then by hashed ID
+    pub fn resolve(&self, reference: &str) -> Result<u64, String> {
+        match self.by_name.get(&reference.to_lowercase()).map(Vec::as_slice) {
+            Some([id]) => Ok(*id),
+            Some(_) => Err(format!(
+                "More than one category is named {}; use its ID instead",
+                reference
+            )),
+            None => self
+                .resolve_id(reference)
+                .map_err(|_| format!("Unknown category: {}", reference)),
+        }
+    }
+
+    pub fn resolve_id(&self, hash: &str) -> Result<u64, String> {
+        decode_single(hash)
+            .ok()
+            .filter(|id| self.ids.contains(id))
+            .ok_or_else(|| format!("Unknown category ID: {}", hash))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportAction {
+    Create,
+    Update,
+    Unchanged,
+    Invalid,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FieldChange {
+    pub field: &'static str,
+    /// `null` for a new product
+    pub from: Value,
+    pub to: Value,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportRowReport {
+    pub row: usize,
+    pub sku: Option<String>,
+    pub action: ImportAction,
+    /// Hashed ID of the existing product, or of the new one once it is created
+    pub product_id: Option<String>,
+    pub changes: Vec<FieldChange>,
+    pub errors: Vec<String>,
+}
+
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct ImportSummary {
+    pub rows: usize,
+    pub created: usize,
+    pub updated: usize,
+    pub unchanged: usize,
+    pub invalid: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ProductImportReport {
+    pub dry_run: bool,
+    /// Whether anything was written; a file with an invalid row writes nothing
+    pub applied: bool,
+    pub summary: ImportSummary,
+    pub rows: Vec<ImportRowReport>,
+}
+
+/// A product write an import will make
+#[derive(Debug, Clone)]
+pub struct PlannedWrite {
+    /// Index into [`ImportPlan::rows`]
+    pub report_index: usize,
+    /// The product being updated; `None` creates one
+    pub product_id: Option<u64>,
+    pub values: ProductValues,
+}
+
+/// What an import file would do, row by row
+#[derive(Debug)]
+pub struct ImportPlan {
+    pub rows: Vec<ImportRowReport>,
+    pub writes: Vec<PlannedWrite>,
+}
+
+impl ImportPlan {
+    /// Checks each row against the product with its SKU in `existing`, keyed by lowercase
+    /// SKU as MySQL compares them
+    pub fn build(
+        rows: Vec<ParsedImportRow>,
+        existing: &HashMap<String, (u64, ProductValues)>,
+        categories: &CategoryResolver,
+    ) -> Self {
+        let mut plan = Self {
+            rows: Vec::with_capacity(rows.len()),
+            writes: Vec::new(),
+        };
+        let mut first_rows: HashMap<String, usize> = HashMap::new();
+
+        for parsed in rows {
+            let mut report = ImportRowReport {
+                row: parsed.row,
+                sku: None,
+                action: ImportAction::Invalid,
+                product_id: None,
+                changes: Vec::new(),
+                errors: Vec::new(),
+            };
+            let product = match parsed.product {
+                Ok(product) => product,
+                Err(error) => {
+                    report.errors.push(error);
+                    plan.rows.push(report);
+                    continue;
+                }
+            };
+            let Some(sku) = product.sku.clone() else {
+                report.errors.push("sku is required".to_string());
+                plan.rows.push(report);
+                continue;
+            };
+            report.sku = Some(sku.clone());
+            let key = sku.to_lowercase();
+            if let Some(first_row) = first_rows.get(&key) {
+                report.errors.push(format!("SKU {} is already on row {}", sku, first_row));
+                plan.rows.push(report);
+                continue;
+            }
+            first_rows.insert(key.clone(), parsed.row);
+
+            let current = existing.get(&key);
+            report.product_id = current.map(|(id, _)| encode_single(*id));
+            match product.resolve(current.map(|(_, values)| values), categories) {
+                Ok(values) => {
+                    report.changes = values.changes_from(current.map(|(_, values)| values));
+                    report.action = match current {
+                        None => ImportAction::Create,
+                        Some(_) if report.changes.is_empty() => ImportAction::Unchanged,
+                        Some(_) => ImportAction::Update,
+                    };
+                    if report.action != ImportAction::Unchanged {
+                        plan.writes.push(PlannedWrite {
+                            report_index: plan.rows.len(),
+                            product_id: current.map(|(id, _)| *id),
+                            values,
+                        });
+                    }
+                }
+                Err(errors) => report.errors = errors,
+            }
+            plan.rows.push(report);
+        }
+        plan
+    }
+
+    pub fn summary(&self) -> ImportSummary {
+        let mut summary = ImportSummary {
+            rows: self.rows.len(),
+            ..ImportSummary::default()
+        };
+        for row in &self.rows {
+            match row.action {
+                ImportAction::Create => summary.created += 1,
+                ImportAction::Update => summary.updated += 1,
+                ImportAction::Unchanged => summary.unchanged += 1,
+                ImportAction::Invalid => summary.invalid += 1,
+            }
+        }
+        summary
+    }
+
+    pub fn has_invalid_rows(&self) -> bool {
+        self.rows.iter().any(|row| row.action == ImportAction::Invalid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn categories() -> CategoryResolver {
+        CategoryResolver::new([(1, "Paper Goods"), (2, "Cleaning"), (3, "cleaning ")])
+    }
+
+    #[test]
+    fn csv_rows_parse_with_blank_cells_as_missing() {
+        let body = b"SKU,Name,Price,Unit Type,Case Pack Size,Stock Quantity\n\
+            PT-1, Paper Towels ,12.50,1,,40\n\
+            PT-2,Napkins,cheap,0,6,\n";
+        let rows = parse_import_file(ProductFileFormat::Csv, body).unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].row, 2);
+        let first = rows[0].product.as_ref().unwrap();
+        assert_eq!(first.name.as_deref(), Some("Paper Towels"));
+        assert_eq!(first.price, Some(Decimal::new(1250, 2)));
+        assert_eq!(first.unit_type, Some(UnitType::Case));
+        assert_eq!(first.case_pack_size, None);
+        assert!(rows[1].product.as_ref().unwrap_err().contains("\"cheap\""));
+
+        assert!(parse_import_file(ProductFileFormat::Csv, b"name\nTowels\n").is_err());
+        assert!(parse_import_file(ProductFileFormat::Json, b"{\"sku\": \"A\"}").is_err());
+    }
+
+    #[test]
+    fn rows_are_planned_against_existing_products() {
+        let existing_values = ProductValues {
+            sku: "PT-1".to_string(),
+            name: "Paper Towels".to_string(),
+            description: String::new(),
+            category_id: 1,
+            image_url: None,
+            price: Decimal::new(1250, 2),
+            bin_location: "A1".to_string(),
+            unit_type: UnitType::Case,
+            case_pack_size: 6,
+            allow_backorder: false,
+            reorder_point: None,
+            is_active: true,
+        };
+        let existing = HashMap::from([("pt-1".to_string(), (7, existing_values))]);
+        let row = |row, json: &str| ParsedImportRow {
+            row,
+            product: Ok(serde_json::from_str::<ProductImportRow>(json).unwrap().normalized()),
+        };
+        let rows = vec![
+            row(1, r#"{"sku": "pt-1", "price": "12.5", "bin_location": " "}"#),
+            row(2, r#"{"sku": "PT-1", "price": 13}"#),
+            row(3, r#"{"sku": "GL-1", "name": "Gloves", "price": 4, "unit_type": 0, "category": "paper goods"}"#),
+            row(4, r#"{"sku": "SP-1", "name": "Soap", "price": 2.999, "unit_type": 0, "category": "Cleaning"}"#),
+            row(5, r#"{"name": "No SKU"}"#),
+        ];
+        let plan = ImportPlan::build(rows, &existing, &categories());
+
+        let actions: Vec<ImportAction> = plan.rows.iter().map(|r| r.action).collect();
+        assert_eq!(
+            actions,
+            [
+                ImportAction::Unchanged,
+                ImportAction::Invalid,
+                ImportAction::Create,
+                ImportAction::Invalid,
+                ImportAction::Invalid,
+            ]
+        );
+        assert_eq!(plan.rows[1].errors, ["SKU PT-1 is already on row 1"]);
+        assert!(plan.rows[3].errors.iter().any(|e| e.starts_with("Price")));
+        assert!(plan.rows[3].errors.iter().any(|e| e.starts_with("More than one category")));
+        assert_eq!(plan.writes.len(), 1);
+        assert_eq!(plan.writes[0].values.category_id, 1);
+        assert_eq!(plan.writes[0].values.case_pack_size, 1);
+
+        let summary = plan.summary();
+        assert_eq!((summary.created, summary.unchanged, summary.invalid), (1, 1, 3));
+    }
+
+    #[test]
+    fn changes_list_only_differing_fields() {
+        let current = ProductValues {
+            sku: "PT-1".to_string(),
+            name: "Paper Towels".to_string(),
+            description: String::new(),
+            category_id: 1,
+            image_url: None,
+            price: Decimal::new(1250, 2),
+            bin_location: "A1".to_string(),
+            unit_type: UnitType::Case,
+            case_pack_size: 6,
+            allow_backorder: false,
+            reorder_point: None,
+            is_active: true,
+        };
+        let updated = ProductValues {
+            price: Decimal::new(13, 0),
+            reorder_point: Some(4.0),
+            ..current.clone()
+        };
+        let changes = updated.changes_from(Some(&current));
+        let fields: Vec<&str> = changes.iter().map(|c| c.field).collect();
+        assert_eq!(fields, ["price", "reorder_point"]);
+        assert_eq!(changes[0].from, json!(12.5));
+        assert_eq!(changes[0].to, json!(13.0));
+        assert_eq!(updated.changes_from(None).len(), 11);
+    }
+}
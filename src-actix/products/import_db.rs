@@ -0,0 +1,147 @@
+use crate::categories::categories_data::CategoryRecord;
+use crate::products::import_data::{
+    CategoryResolver, ImportPlan, ParsedImportRow, ProductImportReport, ProductValues,
+};
+use crate::products::products_data::ProductRecord;
+use log::*;
+use serde_hash::hashids::encode_single;
+use sqlx::{MySql, MySqlPool, QueryBuilder, Transaction};
+use std::collections::HashMap;
+
+impl ProductImportReport {
+    /// Checks every row against the catalogue and, unless this is a dry run or a row is
+    /// invalid, writes the creates and updates in one transaction.
+    ///
+    /// Stock is not part of an import; it only changes through the inventory ledger.
+    pub async fn run(
+        pool: &MySqlPool,
+        rows: Vec<ParsedImportRow>,
+        dry_run: bool,
+    ) -> anyhow::Result<Self> {
+        let categories = CategoryRecord::get_all(pool).await?;
+        let resolver =
+            CategoryResolver::new(categories.iter().map(|c| (c.id, c.name.as_str())));
+        let existing = existing_products(pool, &rows).await?;
+        let mut plan = ImportPlan::build(rows, &existing, &resolver);
+
+        let applied = !dry_run && !plan.has_invalid_rows() && !plan.writes.is_empty();
+        if applied {
+            let mut transaction = pool.begin().await?;
+            for write in &plan.writes {
+                let product_id = match write.product_id {
+                    Some(product_id) => {
+                        update_product(&mut transaction, product_id, &write.values).await?;
+                        product_id
+                    }
+                    None => insert_product(&mut transaction, &write.values).await?,
+                };
+                plan.rows[write.report_index].product_id = Some(encode_single(product_id));
+            }
+            transaction.commit().await?;
+        }
+
+        let summary = plan.summary();
+        if applied {
+            info!(
+                "Product import created {} and updated {} product(s)",
+                summary.created, summary.updated
+            );
+        }
+        Ok(Self {
+            dry_run,
+            applied,
+            summary,
+            rows: plan.rows,
+        })
+    }
+}
+
+/// Current values of the products the rows name, keyed by lowercase SKU
+async fn existing_products(
+    pool: &MySqlPool,
+    rows: &[ParsedImportRow],
+) -> anyhow::Result<HashMap<String, (u64, ProductValues)>> {
+    let skus: Vec<&str> = rows
+        .iter()
+        .filter_map(|row| row.product.as_ref().ok()?.sku.as_deref())
+        .collect();
+    if skus.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let mut query = QueryBuilder::<MySql>::new("SELECT * FROM `products` WHERE `sku` IN (");
+    let mut separated = query.separated(", ");
+    for sku in skus {
+        separated.push_bind(sku);
+    }
+    query.push(")");
+    let products = query.build_query_as::<ProductRecord>().fetch_all(pool).await?;
+
+    Ok(products
+        .iter()
+        .map(|product| {
+            (
+                product.sku.to_lowercase(),
+                (product.id, ProductValues::from(product)),
+            )
+        })
+        .collect())
+}
+
+async fn insert_product(
+    transaction: &mut Transaction<'_, MySql>,
+    values: &ProductValues,
+) -> anyhow::Result<u64> {
+    let result = sqlx::query(
+        r#"
+        INSERT INTO `products` (`name`, `description`, `sku`, `category_id`, `image_url`, `bin_location`, `unit_type`, `price`, `case_pack_size`, `allow_backorder`, `reorder_point`, `is_active`)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+        "#,
+    )
+    .bind(&values.name)
+    .bind(&values.description)
+    .bind(&values.sku)
+    .bind(values.category_id)
+    .bind(&values.image_url)
+    .bind(&values.bin_location)
+    .bind(u8::from(values.unit_type))
+    .bind(values.price)
+    .bind(values.case_pack_size)
+    .bind(values.allow_backorder)
+    .bind(values.reorder_point)
+    .bind(values.is_active)
+    .execute(&mut **transaction)
+    .await?;
+    Ok(result.last_insert_id())
+}
+
+async fn update_product(
+    transaction: &mut Transaction<'_, MySql>,
+    product_id: u64,
+    values: &ProductValues,
+) -> anyhow::Result<()> {
+    sqlx::query(
+        r#"
+        UPDATE `products`
+        SET `name` = ?, `description` = ?, `category_id` = ?, `image_url` = ?, `bin_location` = ?,
+            `unit_type` = ?, `price` = ?, `case_pack_size` = ?, `allow_backorder` = ?,
+            `reorder_point` = ?, `is_active` = ?, `updated_at` = CURRENT_TIMESTAMP
+        WHERE `id` = ?
+        "#,
+    )
+    .bind(&values.name)
+    .bind(&values.description)
+    .bind(values.category_id)
+    .bind(&values.image_url)
+    .bind(&values.bin_location)
+    .bind(u8::from(values.unit_type))
+    .bind(values.price)
+    .bind(values.case_pack_size)
+    .bind(values.allow_backorder)
+    .bind(values.reorder_point)
+    .bind(values.is_active)
+    .bind(product_id)
+    .execute(&mut **transaction)
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,80 @@
+use crate::auth::ClaimsExtractor;
+use crate::products::import_data::{
+    parse_import_file, ProductFileFormat, ProductImportQuery, ProductImportReport,
+    MAX_IMPORT_BYTES,
+};
+use actix_web::http::header::CONTENT_TYPE;
+use actix_web::{post, web, HttpRequest, HttpResponse, Responder};
+use database_common_lib::{database_connection::DatabaseConnectionData, http_error::Result};
+use futures_util::StreamExt;
+use serde_json::json;
+
+/// Creates or updates products by SKU from a CSV or JSON body. With `dry_run=true`, or when
+/// any row is invalid, nothing is written and the per-row report shows what would change.
+#[post("/import")]
+pub async fn import_products(
+    req: HttpRequest,
+    connection_data: web::Data<DatabaseConnectionData>,
+    query: web::Query<ProductImportQuery>,
+    mut payload: web::Payload,
+) -> Result<impl Responder> {
+    let Some(claims) = req.get_claims() else {
+        return Ok(HttpResponse::Unauthorized().json(json!({
+            "error": "Authentication required"
+        })));
+    };
+    if claims.role != "admin" {
+        return Ok(HttpResponse::Forbidden().json(json!({
+            "error": "Admin access required"
+        })));
+    }
+
+    let content_type = req
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(ProductFileFormat::from_content_type);
+    let Some(format) = query.format.or(content_type) else {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "success": false,
+            "error": "Send the file as text/csv or application/json, or set format=csv|json"
+        })));
+    };
+
+    // Read here rather than through `web::Bytes` so the limit is the import's own
+    let mut body = web::BytesMut::new();
+    while let Some(chunk) = payload.next().await {
+        let chunk = chunk.map_err(|e| anyhow::anyhow!("Failed to read the upload: {}", e))?;
+        if body.len() + chunk.len() > MAX_IMPORT_BYTES {
+            return Ok(HttpResponse::PayloadTooLarge().json(json!({
+                "success": false,
+                "error": format!("Imports are limited to {} MB", MAX_IMPORT_BYTES / (1024 * 1024))
+            })));
+        }
+        body.extend_from_slice(&chunk);
+    }
+
+    let rows = match parse_import_file(format, &body) {
+        Ok(rows) => rows,
+        Err(error) => {
+            return Ok(HttpResponse::BadRequest().json(json!({
+                "success": false,
+                "error": error
+            })));
+        }
+    };
+
+    let pool = connection_data.get_pool().await?;
+    let report = ProductImportReport::run(&pool, rows, query.dry_run).await?;
+    if !report.dry_run && report.summary.invalid > 0 {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "success": false,
+            "error": format!("{} row(s) are invalid; nothing was imported", report.summary.invalid),
+            "data": report
+        })));
+    }
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "data": report
+    })))
+}
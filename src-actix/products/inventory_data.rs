@@ -0,0 +1,210 @@
+use serde::{Deserialize, Serialize};
+use serde_hash::HashIds;
+use sqlx::mysql::MySqlRow;
+use sqlx::{FromRow, Row};
+
+/// Why a product's stock changed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MovementType {
+    /// Taken by an order, when it is created or a line is added or increased
+    OrderDecrement,
+    /// Returned by cancelling an order or reducing or removing one of its lines
+    CancellationRestock,
+    /// Correction by an admin, e.g. after a stock count
+    ManualAdjustment,
+    /// Stock level loaded from the FileMaker import
+    ImportSync,
+    /// Goods received into the warehouse
+    Receiving,
+    /// Damaged, expired or missing stock written off
+    Shrink,
+}
+
+impl MovementType {
+    pub const ALL: [MovementType; 6] = [
+        MovementType::OrderDecrement,
+        MovementType::CancellationRestock,
+        MovementType::ManualAdjustment,
+        MovementType::ImportSync,
+        MovementType::Receiving,
+        MovementType::Shrink,
+    ];
+
+    pub fn as_db_str(&self) -> &'static str {
+        match self {
+            MovementType::OrderDecrement => "ORDER_DECREMENT",
+            MovementType::CancellationRestock => "CANCELLATION_RESTOCK",
+            MovementType::ManualAdjustment => "MANUAL_ADJUSTMENT",
+            MovementType::ImportSync => "IMPORT_SYNC",
+            MovementType::Receiving => "RECEIVING",
+            MovementType::Shrink => "SHRINK",
+        }
+    }
+
+    pub fn from_str_case_insensitive(s: &str) -> Option<Self> {
+        match s.to_ascii_uppercase().as_str() {
+            "ORDER_DECREMENT" => Some(MovementType::OrderDecrement),
+            "CANCELLATION_RESTOCK" => Some(MovementType::CancellationRestock),
+            "MANUAL_ADJUSTMENT" => Some(MovementType::ManualAdjustment),
+            "IMPORT_SYNC" => Some(MovementType::ImportSync),
+            "RECEIVING" => Some(MovementType::Receiving),
+            "SHRINK" => Some(MovementType::Shrink),
+            _ => None,
+        }
+    }
+
+    /// Comma separated list of quoted DB values, used for the `movement_type` ENUM column
+    pub fn sql_enum_values() -> String {
+        Self::ALL
+            .iter()
+            .map(|t| format!("'{}'", t.as_db_str()))
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+
+    /// Types an admin can enter by hand; the others are written by the flows that cause them
+    pub fn is_manual(&self) -> bool {
+        matches!(
+            self,
+            MovementType::ManualAdjustment | MovementType::Receiving | MovementType::Shrink
+        )
+    }
+}
+
+/// Context recorded in the ledger alongside a stock change
+#[derive(Debug, Clone, Copy)]
+pub struct StockMovement<'a> {
+    pub movement_type: MovementType,
+    /// Order the change belongs to, if any
+    pub reference_id: Option<u64>,
+    pub user_id: Option<u64>,
+    pub reason: Option<&'a str>,
+}
+
+impl StockMovement<'_> {
+    pub fn for_order(movement_type: MovementType, order_id: u64, user_id: u64) -> Self {
+        Self {
+            movement_type,
+            reference_id: Some(order_id),
+            user_id: Some(user_id),
+            reason: None,
+        }
+    }
+}
+
+/// One entry of a product's stock ledger
+#[derive(HashIds, Debug, Clone)]
+pub struct InventoryMovementRecord {
+    #[hash]
+    pub id: u64,
+    #[hash]
+    pub product_id: u64,
+    pub movement_type: MovementType,
+    pub quantity_delta: f32,
+    /// Stock level right after the change
+    pub quantity_after: f32,
+    #[hash]
+    pub reference_id: Option<u64>,
+    #[hash]
+    pub user_id: Option<u64>,
+    pub user_email: Option<String>,
+    pub reason: Option<String>,
+    pub created_at: chrono::NaiveDateTime,
+}
+
+impl<'r> FromRow<'r, MySqlRow> for InventoryMovementRecord {
+    fn from_row(row: &'r MySqlRow) -> Result<Self, sqlx::Error> {
+        let type_raw: String = row.try_get("movement_type")?;
+        let movement_type = MovementType::from_str_case_insensitive(&type_raw).ok_or_else(|| {
+            sqlx::Error::Protocol(format!("Unexpected movement type value '{}'", type_raw))
+        })?;
+        Ok(Self {
+            id: row.try_get("id")?,
+            product_id: row.try_get("product_id")?,
+            movement_type,
+            quantity_delta: row.try_get("quantity_delta")?,
+            quantity_after: row.try_get("quantity_after")?,
+            reference_id: row.try_get("reference_id")?,
+            user_id: row.try_get("user_id")?,
+            user_email: row.try_get("user_email")?,
+            reason: row.try_get("reason")?,
+            created_at: row.try_get("created_at")?,
+        })
+    }
+}
+
+/// How a manual adjustment changes the stock level
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StockChange {
+    Delta(f32),
+    /// Set the level to what was counted on the shelf
+    Counted(f32),
+}
+
+impl StockChange {
+    pub fn delta_from(&self, current: f32) -> f32 {
+        match self {
+            StockChange::Delta(delta) => *delta,
+            StockChange::Counted(counted) => counted - current,
+        }
+    }
+}
+
+/// Outcome of a manual stock adjustment
+#[derive(Debug, Clone)]
+pub enum StockAdjustmentResult {
+    Applied(InventoryMovementRecord),
+    /// A count matched the current level; nothing was recorded
+    Unchanged,
+    ProductNotFound,
+    /// A decrease would take stock below zero
+    InsufficientStock { available: f32 },
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StockAdjustmentRequest {
+    #[serde(default = "default_adjustment_type")]
+    pub movement_type: MovementType,
+    /// Change to apply; set either this or `counted_quantity`
+    pub quantity_delta: Option<f32>,
+    pub counted_quantity: Option<f32>,
+    pub reason: Option<String>,
+}
+
+fn default_adjustment_type() -> MovementType {
+    MovementType::ManualAdjustment
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MovementQuery {
+    pub movement_type: Option<MovementType>,
+    pub limit: Option<u32>,
+    pub offset: Option<u32>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn movement_types_round_trip_through_the_database() {
+        for movement_type in MovementType::ALL {
+            assert_eq!(
+                MovementType::from_str_case_insensitive(movement_type.as_db_str()),
+                Some(movement_type)
+            );
+        }
+        let manual: Vec<_> = MovementType::ALL.into_iter().filter(|t| t.is_manual()).collect();
+        assert_eq!(
+            manual,
+            vec![MovementType::ManualAdjustment, MovementType::Receiving, MovementType::Shrink]
+        );
+    }
+
+    #[test]
+    fn counts_become_the_difference_to_the_current_level() {
+        assert_eq!(StockChange::Counted(12.0).delta_from(15.0), -3.0);
+        assert_eq!(StockChange::Delta(4.0).delta_from(15.0), 4.0);
+    }
+}
@@ -0,0 +1,192 @@
+use crate::products::inventory_data::{
+    InventoryMovementRecord, MovementType, StockAdjustmentResult, StockChange, StockMovement,
+};
+use log::*;
+use sqlx::{Executor, MySql, MySqlPool, Transaction};
+
+pub async fn initialize(pool: &MySqlPool) -> anyhow::Result<()> {
+    let type_values = MovementType::sql_enum_values();
+
+    pool.execute(
+        format!(
+            r#"
+        CREATE TABLE IF NOT EXISTS `inventory_movements` (
+            `id` BIGINT UNSIGNED NOT NULL AUTO_INCREMENT,
+            `product_id` BIGINT UNSIGNED NOT NULL,
+            `movement_type` ENUM({type_values}) NOT NULL,
+            `quantity_delta` FLOAT NOT NULL,
+            `quantity_after` FLOAT NOT NULL,
+            `reference_id` BIGINT UNSIGNED,
+            `user_id` BIGINT UNSIGNED,
+            `reason` TEXT,
+            `created_at` DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            PRIMARY KEY (`id`),
+            FOREIGN KEY (`product_id`) REFERENCES `products`(`id`) ON DELETE CASCADE,
+            INDEX `idx_product_created` (`product_id`, `created_at`),
+            INDEX `idx_reference_id` (`reference_id`)
+        )
+        "#
+        )
+        .as_str(),
+    )
+    .await?;
+
+    Ok(())
+}
+
+impl InventoryMovementRecord {
+    /// Applies a stock change to a product and records it in the ledger; a positive delta
+    /// restocks, a negative one consumes. Every change to `products.stock_quantity` goes
+    /// through here.
+    ///
+    /// Returns the ID of the ledger entry, or `None` when `delta` is zero and nothing changed.
+    pub async fn apply(
+        transaction: &mut Transaction<'_, MySql>,
+        product_id: u64,
+        delta: f32,
+        movement: &StockMovement<'_>,
+    ) -> anyhow::Result<Option<u64>> {
+        if delta == 0.0 {
+            return Ok(None);
+        }
+
+        // MySQL applies SET assignments left to right, so `in_stock` sees the adjusted quantity
+        sqlx::query(
+            r#"
+            UPDATE `products`
+            SET `stock_quantity` = `stock_quantity` + ?,
+                `in_stock` = `stock_quantity` > 0
+            WHERE `id` = ?
+            "#,
+        )
+        .bind(delta)
+        .bind(product_id)
+        .execute(&mut **transaction)
+        .await?;
+
+        let quantity_after: f32 =
+            sqlx::query_scalar("SELECT `stock_quantity` FROM `products` WHERE `id` = ?")
+                .bind(product_id)
+                .fetch_one(&mut **transaction)
+                .await?;
+        let movement_id = Self::record(transaction, product_id, delta, quantity_after, movement).await?;
+        Ok(Some(movement_id))
+    }
+
+    /// Adds a ledger entry for a change already made to `stock_quantity`
+    pub async fn record(
+        transaction: &mut Transaction<'_, MySql>,
+        product_id: u64,
+        delta: f32,
+        quantity_after: f32,
+        movement: &StockMovement<'_>,
+    ) -> anyhow::Result<u64> {
+        let result = sqlx::query(
+            r#"
+            INSERT INTO `inventory_movements`
+                (`product_id`, `movement_type`, `quantity_delta`, `quantity_after`, `reference_id`, `user_id`, `reason`)
+            VALUES (?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(product_id)
+        .bind(movement.movement_type.as_db_str())
+        .bind(delta)
+        .bind(quantity_after)
+        .bind(movement.reference_id)
+        .bind(movement.user_id)
+        .bind(movement.reason)
+        .execute(&mut **transaction)
+        .await?;
+        Ok(result.last_insert_id())
+    }
+
+    pub async fn get_by_id(pool: &MySqlPool, id: u64) -> anyhow::Result<Option<Self>> {
+        let movement = sqlx::query_as::<_, Self>(
+            r#"
+            SELECT m.*, u.email AS user_email
+            FROM `inventory_movements` m
+            LEFT JOIN `users` u ON m.user_id = u.id
+            WHERE m.id = ?
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(pool)
+        .await?;
+        Ok(movement)
+    }
+
+    /// A product's ledger, newest first
+    pub async fn get_for_product(
+        pool: &MySqlPool,
+        product_id: u64,
+        movement_type: Option<MovementType>,
+        limit: u32,
+        offset: u32,
+    ) -> anyhow::Result<Vec<Self>> {
+        let movements = sqlx::query_as::<_, Self>(
+            r#"
+            SELECT m.*, u.email AS user_email
+            FROM `inventory_movements` m
+            LEFT JOIN `users` u ON m.user_id = u.id
+            WHERE m.product_id = ? AND (? IS NULL OR m.movement_type = ?)
+            ORDER BY m.created_at DESC, m.id DESC
+            LIMIT ? OFFSET ?
+            "#,
+        )
+        .bind(product_id)
+        .bind(movement_type.map(|t| t.as_db_str()))
+        .bind(movement_type.map(|t| t.as_db_str()))
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(pool)
+        .await?;
+        Ok(movements)
+    }
+
+    /// Applies an adjustment entered by an admin, with the product row locked so a
+    /// count is compared against the current level.
+    pub async fn adjust(
+        pool: &MySqlPool,
+        product_id: u64,
+        change: StockChange,
+        movement: &StockMovement<'_>,
+    ) -> anyhow::Result<StockAdjustmentResult> {
+        let mut transaction = pool.begin().await?;
+
+        let current: Option<f32> =
+            sqlx::query_scalar("SELECT `stock_quantity` FROM `products` WHERE `id` = ? FOR UPDATE")
+                .bind(product_id)
+                .fetch_optional(&mut *transaction)
+                .await?;
+        let Some(current) = current else {
+            return Ok(StockAdjustmentResult::ProductNotFound);
+        };
+
+        let delta = change.delta_from(current);
+        if delta == 0.0 {
+            return Ok(StockAdjustmentResult::Unchanged);
+        }
+        if delta < 0.0 && current + delta < 0.0 {
+            return Ok(StockAdjustmentResult::InsufficientStock {
+                available: current.max(0.0),
+            });
+        }
+
+        let movement_id = Self::apply(&mut transaction, product_id, delta, movement)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("Stock adjustment recorded no movement"))?;
+        transaction.commit().await?;
+        info!(
+            "Stock of product {} changed by {} ({}) by user {:?}",
+            product_id,
+            delta,
+            movement.movement_type.as_db_str(),
+            movement.user_id
+        );
+
+        let record = Self::get_by_id(pool, movement_id)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("Failed to retrieve recorded movement"))?;
+        Ok(StockAdjustmentResult::Applied(record))
+    }
+}
@@ -0,0 +1,149 @@
+use crate::auth::ClaimsExtractor;
+use crate::products::inventory_data::{
+    InventoryMovementRecord, MovementQuery, MovementType, StockAdjustmentRequest,
+    StockAdjustmentResult, StockChange, StockMovement,
+};
+use crate::products::ProductRecord;
+use actix_web::{get, post, web, HttpRequest, HttpResponse, Responder};
+use database_common_lib::{database_connection::DatabaseConnectionData, http_error::Result};
+use serde_json::json;
+
+const DEFAULT_MOVEMENT_LIMIT: u32 = 50;
+const MAX_MOVEMENT_LIMIT: u32 = 500;
+
+#[post("/{id}/stock-adjustments")]
+pub async fn adjust_stock(
+    req: HttpRequest,
+    connection_data: web::Data<DatabaseConnectionData>,
+    path: web::Path<String>,
+    request: web::Json<StockAdjustmentRequest>,
+) -> Result<impl Responder> {
+    let Some(claims) = req.get_claims() else {
+        return Ok(HttpResponse::Unauthorized().json(json!({
+            "error": "Authentication required"
+        })));
+    };
+    if claims.role != "admin" {
+        return Ok(HttpResponse::Forbidden().json(json!({
+            "error": "Admin access required"
+        })));
+    }
+    let product_id = serde_hash::hashids::decode_single(path.as_str())?;
+
+    let mut errors = Vec::new();
+    let movement_type = request.movement_type;
+    if !movement_type.is_manual() {
+        errors.push("Only manual_adjustment, receiving and shrink can be entered by hand".to_string());
+    }
+    let change = match (request.quantity_delta, request.counted_quantity) {
+        (Some(delta), None) => Some(StockChange::Delta(delta)),
+        (None, Some(counted)) => Some(StockChange::Counted(counted)),
+        _ => {
+            errors.push("Set either quantity_delta or counted_quantity".to_string());
+            None
+        }
+    };
+    match change {
+        Some(StockChange::Delta(delta)) if !delta.is_finite() || delta == 0.0 => {
+            errors.push("Quantity delta must be a non-zero number".to_string());
+        }
+        Some(StockChange::Counted(counted)) if !counted.is_finite() || counted < 0.0 => {
+            errors.push("Counted quantity cannot be negative".to_string());
+        }
+        Some(StockChange::Counted(_)) if movement_type != MovementType::ManualAdjustment => {
+            errors.push("Counts can only be entered as a manual_adjustment".to_string());
+        }
+        Some(StockChange::Delta(delta)) if movement_type == MovementType::Receiving && delta < 0.0 => {
+            errors.push("Receiving must add stock".to_string());
+        }
+        Some(StockChange::Delta(delta)) if movement_type == MovementType::Shrink && delta > 0.0 => {
+            errors.push("Shrink must remove stock".to_string());
+        }
+        _ => {}
+    }
+    let reason = request.reason.as_deref().map(str::trim).filter(|r| !r.is_empty());
+    if reason.is_none() && movement_type != MovementType::Receiving {
+        errors.push("A reason is required".to_string());
+    }
+    let Some(change) = change.filter(|_| errors.is_empty()) else {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "success": false,
+            "error": "Invalid stock adjustment",
+            "details": errors
+        })));
+    };
+
+    let pool = connection_data.get_pool().await?;
+    let movement = StockMovement {
+        movement_type,
+        reference_id: None,
+        user_id: Some(claims.sub),
+        reason,
+    };
+    match InventoryMovementRecord::adjust(&pool, product_id, change, &movement).await? {
+        StockAdjustmentResult::Applied(record) => Ok(HttpResponse::Created().json(json!({
+            "success": true,
+            "data": record
+        }))),
+        StockAdjustmentResult::Unchanged => Ok(HttpResponse::Ok().json(json!({
+            "success": true,
+            "message": "Count matches the current stock level; nothing recorded"
+        }))),
+        StockAdjustmentResult::ProductNotFound => Ok(HttpResponse::NotFound().json(json!({
+            "error": "Product not found"
+        }))),
+        StockAdjustmentResult::InsufficientStock { available } => {
+            Ok(HttpResponse::Conflict().json(json!({
+                "success": false,
+                "error": format!("Only {} in stock; the adjustment would go below zero", available)
+            })))
+        }
+    }
+}
+
+/// A product's stock ledger, newest first
+#[get("/{id}/movements")]
+pub async fn get_movements(
+    req: HttpRequest,
+    connection_data: web::Data<DatabaseConnectionData>,
+    path: web::Path<String>,
+    query: web::Query<MovementQuery>,
+) -> Result<impl Responder> {
+    let Some(claims) = req.get_claims() else {
+        return Ok(HttpResponse::Unauthorized().json(json!({
+            "error": "Authentication required"
+        })));
+    };
+    if claims.role != "admin" {
+        return Ok(HttpResponse::Forbidden().json(json!({
+            "error": "Admin access required"
+        })));
+    }
+    let pool = connection_data.get_pool().await?;
+    let product_id = serde_hash::hashids::decode_single(path.as_str())?;
+
+    let Some(product) = ProductRecord::get_by_id_simple(&pool, product_id).await? else {
+        return Ok(HttpResponse::NotFound().json(json!({
+            "error": "Product not found"
+        })));
+    };
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_MOVEMENT_LIMIT)
+        .clamp(1, MAX_MOVEMENT_LIMIT);
+    let offset = query.offset.unwrap_or(0);
+    let movements = InventoryMovementRecord::get_for_product(
+        &pool,
+        product_id,
+        query.movement_type,
+        limit,
+        offset,
+    )
+    .await?;
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "data": movements,
+        "stock_quantity": product.stock_quantity,
+        "count": movements.len()
+    })))
+}
@@ -0,0 +1,43 @@
+use serde_hash::HashIds;
+use sqlx::FromRow;
+
+/// An active product at or below its reorder point
+#[derive(HashIds, Debug, Clone, FromRow)]
+pub struct LowStockItem {
+    #[hash]
+    pub product_id: u64,
+    pub sku: String,
+    pub name: String,
+    pub category_name: String,
+    pub bin_location: String,
+    pub stock_quantity: f32,
+    pub reorder_point: f32,
+    pub allow_backorder: bool,
+}
+
+impl LowStockItem {
+    /// How far stock would have to rise to get back above the reorder point
+    pub fn shortfall(&self) -> f32 {
+        (self.reorder_point - self.stock_quantity).max(0.0)
+    }
+}
+
+/// Whether a change from `before` to `after` took stock from above `reorder_point` to at or
+/// below it. A product already low is not reported again until it has been restocked.
+pub fn crosses_reorder_point(before: f32, after: f32, reorder_point: f32) -> bool {
+    before > reorder_point && after <= reorder_point
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_the_first_drop_to_the_reorder_point_crosses_it() {
+        assert!(crosses_reorder_point(12.0, 10.0, 10.0));
+        assert!(crosses_reorder_point(12.0, 4.0, 10.0));
+        assert!(!crosses_reorder_point(10.0, 8.0, 10.0));
+        assert!(!crosses_reorder_point(15.0, 11.0, 10.0));
+        assert!(!crosses_reorder_point(8.0, 12.0, 10.0));
+    }
+}
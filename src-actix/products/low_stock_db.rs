@@ -0,0 +1,184 @@
+use crate::auth::EmailService;
+use crate::products::inventory_data::MovementType;
+use crate::products::low_stock_data::{crosses_reorder_point, LowStockItem};
+use database_common_lib::database_connection::DatabaseConnectionData;
+use log::*;
+use sqlx::MySqlPool;
+
+/// How often the background task reports products at or below their reorder point
+const LOW_STOCK_CHECK_INTERVAL_SECS: u64 = 24 * 60 * 60;
+
+const LOW_STOCK_SELECT: &str = r#"
+    SELECT p.id AS product_id, p.sku, p.name, c.name AS category_name, p.bin_location,
+           p.stock_quantity, p.reorder_point, p.allow_backorder
+    FROM `products` p
+    JOIN `categories` c ON p.category_id = c.id
+"#;
+
+/// Starts the background task that reports low stock once a day.
+///
+/// The first report goes out a full interval after startup, so restarting the server does
+/// not email the admins again.
+pub fn start_report_task() {
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(std::time::Duration::from_secs(LOW_STOCK_CHECK_INTERVAL_SECS)).await;
+            match DatabaseConnectionData::get().await {
+                Ok(conn_data) => match conn_data.get_pool().await {
+                    Ok(pool) => {
+                        if let Err(e) = LowStockItem::report(&pool).await {
+                            error!("Failed to produce low-stock report: {}", e);
+                        }
+                    }
+                    Err(e) => error!("Database connection failed: {}", e),
+                },
+                Err(e) => error!("Database connection failed: {}", e),
+            }
+        }
+    });
+}
+
+impl LowStockItem {
+    /// Active products at or below their reorder point, furthest below first
+    pub async fn get_all(pool: &MySqlPool) -> anyhow::Result<Vec<Self>> {
+        let items = sqlx::query_as::<_, Self>(&format!(
+            r#"{LOW_STOCK_SELECT}
+            WHERE p.is_active = TRUE AND p.reorder_point IS NOT NULL
+              AND p.stock_quantity <= p.reorder_point
+            ORDER BY (p.reorder_point - p.stock_quantity) DESC, p.name
+            "#
+        ))
+        .fetch_all(pool)
+        .await?;
+        Ok(items)
+    }
+
+    /// Products whose stock `order_id` took from above their reorder point to at or below it
+    pub async fn crossed_by_order(pool: &MySqlPool, order_id: u64) -> anyhow::Result<Vec<Self>> {
+        // An order takes stock once per line; a product on several lines has several entries
+        let changes: Vec<(u64, f64, f32)> = sqlx::query_as(
+            r#"
+            SELECT `product_id`, SUM(`quantity_delta`), MIN(`quantity_after`)
+            FROM `inventory_movements`
+            WHERE `reference_id` = ? AND `movement_type` = ?
+            GROUP BY `product_id`
+            "#,
+        )
+        .bind(order_id)
+        .bind(MovementType::OrderDecrement.as_db_str())
+        .fetch_all(pool)
+        .await?;
+        if changes.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let placeholders = vec!["?"; changes.len()].join(", ");
+        let query = format!(
+            r#"{LOW_STOCK_SELECT}
+            WHERE p.is_active = TRUE AND p.reorder_point IS NOT NULL AND p.id IN ({placeholders})
+            ORDER BY p.name
+            "#
+        );
+        let mut candidates = sqlx::query_as::<_, Self>(&query);
+        for (product_id, _, _) in &changes {
+            candidates = candidates.bind(product_id);
+        }
+        let candidates = candidates.fetch_all(pool).await?;
+
+        let crossed = candidates
+            .into_iter()
+            .filter_map(|mut item| {
+                let (_, delta, after) = changes.iter().find(|(id, _, _)| *id == item.product_id)?;
+                let before = after - *delta as f32;
+                crosses_reorder_point(before, *after, item.reorder_point).then(|| {
+                    // Report the level the order left, not whatever it is by now
+                    item.stock_quantity = *after;
+                    item
+                })
+            })
+            .collect();
+        Ok(crossed)
+    }
+
+    /// Logs the current low-stock report and emails it to the alert recipients
+    pub async fn report(pool: &MySqlPool) -> anyhow::Result<()> {
+        let items = Self::get_all(pool).await?;
+        if items.is_empty() {
+            debug!("No products at or below their reorder point");
+            return Ok(());
+        }
+        info!("{} product(s) at or below their reorder point", items.len());
+        send_alert(pool, &items, None).await
+    }
+
+    /// Emails the products `order_id` took below their reorder point, without holding up the
+    /// caller. Call once the order's transaction has been committed.
+    pub fn notify_crossed_by_order(pool: &MySqlPool, order_id: u64) {
+        let pool = pool.clone();
+        tokio::spawn(async move {
+            let result: anyhow::Result<()> = async {
+                let items = Self::crossed_by_order(&pool, order_id).await?;
+                if items.is_empty() {
+                    return Ok(());
+                }
+                let order_number: String =
+                    sqlx::query_scalar("SELECT `order_number` FROM `orders` WHERE `id` = ?")
+                        .bind(order_id)
+                        .fetch_one(&pool)
+                        .await?;
+                info!(
+                    "Order {} took {} product(s) to their reorder point",
+                    order_number,
+                    items.len()
+                );
+                send_alert(&pool, &items, Some(&order_number)).await
+            }
+            .await;
+            if let Err(e) = result {
+                error!("Failed to send low-stock alert for order {}: {}", order_id, e);
+            }
+        });
+    }
+}
+
+/// Addresses from `LOW_STOCK_ALERT_EMAILS` (comma separated), or every admin when unset
+async fn alert_recipients(pool: &MySqlPool) -> anyhow::Result<Vec<String>> {
+    if let Ok(configured) = std::env::var("LOW_STOCK_ALERT_EMAILS") {
+        let emails: Vec<String> = configured
+            .split(',')
+            .map(str::trim)
+            .filter(|e| !e.is_empty())
+            .map(str::to_string)
+            .collect();
+        if !emails.is_empty() {
+            return Ok(emails);
+        }
+    }
+    let admins = sqlx::query_scalar("SELECT `email` FROM `users` WHERE `role` = 'admin'")
+        .fetch_all(pool)
+        .await?;
+    Ok(admins)
+}
+
+async fn send_alert(
+    pool: &MySqlPool,
+    items: &[LowStockItem],
+    order_number: Option<&str>,
+) -> anyhow::Result<()> {
+    let recipients = alert_recipients(pool).await?;
+    if recipients.is_empty() {
+        warn!("No recipients for the low-stock alert; set LOW_STOCK_ALERT_EMAILS");
+        return Ok(());
+    }
+    let email_service = EmailService::new()?;
+    for recipient in &recipients {
+        // One bad address must not keep the alert from the others
+        if let Err(e) = email_service
+            .send_low_stock_email(recipient, items, order_number)
+            .await
+        {
+            error!("Failed to send low-stock alert to {}: {}", recipient, e);
+        }
+    }
+    Ok(())
+}
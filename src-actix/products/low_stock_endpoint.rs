@@ -0,0 +1,30 @@
+use crate::auth::ClaimsExtractor;
+use crate::products::low_stock_data::LowStockItem;
+use actix_web::{get, web, HttpRequest, HttpResponse, Responder};
+use database_common_lib::{database_connection::DatabaseConnectionData, http_error::Result};
+use serde_json::json;
+
+/// Active products at or below their reorder point, for the admin dashboard
+#[get("")]
+pub async fn get_low_stock(
+    req: HttpRequest,
+    connection_data: web::Data<DatabaseConnectionData>,
+) -> Result<impl Responder> {
+    let Some(claims) = req.get_claims() else {
+        return Ok(HttpResponse::Unauthorized().json(json!({
+            "error": "Authentication required"
+        })));
+    };
+    if claims.role != "admin" {
+        return Ok(HttpResponse::Forbidden().json(json!({
+            "error": "Admin access required"
+        })));
+    }
+    let pool = connection_data.get_pool().await?;
+    let items = LowStockItem::get_all(&pool).await?;
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "data": items,
+        "count": items.len()
+    })))
+}
@@ -1,7 +1,37 @@
+pub mod barcode_data;
+pub mod barcode_db;
+mod barcode_endpoint;
+pub mod barcode_svg;
+pub mod bulk_data;
+pub mod bulk_db;
+mod bulk_endpoint;
+pub mod export_data;
+pub mod export_db;
+mod export_endpoint;
+pub mod import_data;
+pub mod import_db;
+mod import_endpoint;
+pub mod inventory_data;
+pub mod inventory_db;
+mod inventory_endpoint;
+pub mod low_stock_data;
+pub mod low_stock_db;
+mod low_stock_endpoint;
 pub mod products_data;
 pub mod products_db;
 mod products_endpoint;
+pub mod search_data;
+pub mod search_db;
+mod search_endpoint;
 
 pub use products_data::ProductRecord;
-pub use products_db::initialize;
 pub use products_endpoint::configure;
+
+use sqlx::MySqlPool;
+
+pub async fn initialize(pool: &MySqlPool) -> anyhow::Result<()> {
+    products_db::initialize(pool).await?;
+    barcode_db::initialize(pool).await?;
+    inventory_db::initialize(pool).await?;
+    Ok(())
+}
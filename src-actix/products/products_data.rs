@@ -51,6 +51,12 @@ pub struct ProductRecord {
     pub price: rust_decimal::Decimal,
     pub in_stock: bool,
     pub stock_quantity: f32,
+    /// Units per case; suggested order quantities are rounded to multiples of it
+    pub case_pack_size: i32,
+    /// Orders may take more than is in stock, leaving the excess on backorder
+    pub allow_backorder: bool,
+    /// Stock level at or below which the product is reported as low; `None` disables alerts
+    pub reorder_point: Option<f32>,
     pub is_active: bool,
     pub created_at: chrono::NaiveDateTime,
     pub updated_at: chrono::NaiveDateTime,
@@ -73,6 +79,15 @@ pub struct CreateProductRequest {
     pub price: rust_decimal::Decimal,
     pub bin_location: String,
     pub unit_type: UnitType,
+    #[serde(default = "default_case_pack_size")]
+    pub case_pack_size: i32,
+    #[serde(default)]
+    pub allow_backorder: bool,
+    pub reorder_point: Option<f32>,
+}
+
+fn default_case_pack_size() -> i32 {
+    1
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -86,17 +101,232 @@ pub struct UpdateProductRequest {
     pub price: Option<rust_decimal::Decimal>,
     pub bin_location: Option<String>,
     pub unit_type: Option<UnitType>,
+    pub case_pack_size: Option<i32>,
+    pub allow_backorder: Option<bool>,
+    /// `null` clears the reorder point, leaving it out keeps the current one
+    #[serde(default, deserialize_with = "deserialize_present")]
+    pub reorder_point: Option<Option<f32>>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+impl UpdateProductRequest {
+    /// Problems with the values to set
+    pub fn validation_errors(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+        if self.case_pack_size.is_some_and(|size| size < 1) {
+            errors.push("Case pack size must be at least 1".to_string());
+        }
+        if self.reorder_point.flatten().is_some_and(|point| point < 0.0) {
+            errors.push("Reorder point cannot be negative".to_string());
+        }
+        errors
+    }
+
+    /// Whether the request sets any field
+    pub fn is_empty(&self) -> bool {
+        self.name.is_none()
+            && self.description.is_none()
+            && self.sku.is_none()
+            && self.category_id.is_none()
+            && self.image_url.is_none()
+            && self.is_active.is_none()
+            && self.price.is_none()
+            && self.bin_location.is_none()
+            && self.unit_type.is_none()
+            && self.case_pack_size.is_none()
+            && self.allow_backorder.is_none()
+            && self.reorder_point.is_none()
+    }
+}
+
+pub const DEFAULT_PRODUCT_PAGE_SIZE: u32 = 50;
+pub const MAX_PRODUCT_PAGE_SIZE: u32 = 500;
+
+/// Query string of `GET /api/products`, checked into a [`ProductFilter`]
+#[derive(Debug, Default, Deserialize)]
+pub struct ProductQuery {
+    pub category_id: Option<String>, // hashed ID
+    pub search: Option<String>,
+    pub in_stock: Option<bool>,
+    pub is_active: Option<bool>,
+    pub unit_type: Option<UnitType>,
+    pub min_price: Option<rust_decimal::Decimal>,
+    pub max_price: Option<rust_decimal::Decimal>,
+    /// Matches bin locations starting with this text, e.g. `A-12`
+    pub bin_location: Option<String>,
+    pub updated_since: Option<chrono::NaiveDateTime>,
+    #[serde(default)]
+    pub sort: ProductSort,
+    #[serde(default)]
+    pub order: SortDirection,
+    pub page: Option<u32>,
+    pub page_size: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProductSort {
+    #[default]
+    Name,
+    Price,
+    Sku,
+    UpdatedAt,
+}
+
+impl ProductSort {
+    pub fn column(&self) -> &'static str {
+        match self {
+            ProductSort::Name => "p.name",
+            ProductSort::Price => "p.price",
+            ProductSort::Sku => "p.sku",
+            ProductSort::UpdatedAt => "p.updated_at",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortDirection {
+    #[default]
+    Asc,
+    Desc,
+}
+
+impl SortDirection {
+    pub fn as_sql(&self) -> &'static str {
+        match self {
+            SortDirection::Asc => "ASC",
+            SortDirection::Desc => "DESC",
+        }
+    }
+}
+
+/// Decoded and validated product listing filters; every field left `None` matches all products
+#[derive(Debug, Clone, PartialEq)]
 pub struct ProductFilter {
-    pub category_id: Option<String>,
+    pub category_id: Option<u64>,
     pub search: Option<String>,
-    pub limit: Option<u32>,
-    pub offset: Option<u32>,
+    pub in_stock: Option<bool>,
+    pub is_active: Option<bool>,
+    pub unit_type: Option<UnitType>,
+    pub min_price: Option<rust_decimal::Decimal>,
+    pub max_price: Option<rust_decimal::Decimal>,
+    pub bin_location_prefix: Option<String>,
+    pub updated_since: Option<chrono::NaiveDateTime>,
+    pub sort: ProductSort,
+    pub direction: SortDirection,
+    /// 1-based
+    pub page: u32,
+    /// `None` when the caller sent no paging parameters, which lists every match as before
+    /// the listing was paged
+    pub page_size: Option<u32>,
+}
+
+impl ProductFilter {
+    /// Checks a query string, collecting every problem rather than stopping at the first
+    pub fn from_query(query: ProductQuery) -> Result<Self, Vec<String>> {
+        let mut errors = Vec::new();
+
+        let category_id = match query.category_id.as_deref().filter(|id| !id.is_empty()) {
+            Some(hash) => match decode_single(hash) {
+                Ok(id) => Some(id),
+                Err(_) => {
+                    errors.push(format!("Invalid category ID: {}", hash));
+                    None
+                }
+            },
+            None => None,
+        };
+        if query.min_price.is_some_and(|price| price.is_sign_negative())
+            || query.max_price.is_some_and(|price| price.is_sign_negative())
+        {
+            errors.push("Prices cannot be negative".to_string());
+        }
+        if let (Some(min), Some(max)) = (query.min_price, query.max_price) {
+            if min > max {
+                errors.push("min_price must not be above max_price".to_string());
+            }
+        }
+        if query.page == Some(0) {
+            errors.push("Pages are numbered from 1".to_string());
+        }
+        if query.page_size.is_some_and(|size| !(1..=MAX_PRODUCT_PAGE_SIZE).contains(&size)) {
+            errors.push(format!("page_size must be between 1 and {}", MAX_PRODUCT_PAGE_SIZE));
+        }
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        let non_empty = |text: Option<String>| {
+            text.map(|t| t.trim().to_string()).filter(|t| !t.is_empty())
+        };
+        Ok(Self {
+            category_id,
+            search: non_empty(query.search),
+            in_stock: query.in_stock,
+            is_active: query.is_active,
+            unit_type: query.unit_type,
+            min_price: query.min_price,
+            max_price: query.max_price,
+            bin_location_prefix: non_empty(query.bin_location),
+            updated_since: query.updated_since,
+            sort: query.sort,
+            direction: query.order,
+            page: query.page.unwrap_or(1),
+            page_size: match (query.page, query.page_size) {
+                (None, None) => None,
+                (_, page_size) => Some(page_size.unwrap_or(DEFAULT_PRODUCT_PAGE_SIZE)),
+            },
+        })
+    }
+
+    /// Whether any condition narrows the products matched
+    pub fn has_conditions(&self) -> bool {
+        self.category_id.is_some()
+            || self.search.is_some()
+            || self.in_stock.is_some()
+            || self.is_active.is_some()
+            || self.unit_type.is_some()
+            || self.min_price.is_some()
+            || self.max_price.is_some()
+            || self.bin_location_prefix.is_some()
+            || self.updated_since.is_some()
+    }
+
+    pub fn offset(&self) -> u64 {
+        self.page_size
+            .map_or(0, |page_size| u64::from(self.page - 1) * u64::from(page_size))
+    }
+}
+
+/// One page of a product listing
+#[derive(Debug)]
+pub struct ProductPage {
+    pub products: Vec<ProductWithCategory>,
+    /// Matching products across all pages
+    pub total: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Pagination {
+    pub page: u32,
+    pub page_size: u32,
+    pub total: u64,
+    pub total_pages: u64,
 }
 
-fn serialize_hash_id<S>(value: &u64, serializer: S) -> Result<S::Ok, S::Error>
+impl Pagination {
+    /// `None` for an unpaged listing
+    pub fn new(filter: &ProductFilter, total: u64) -> Option<Self> {
+        filter.page_size.map(|page_size| Self {
+            page: filter.page,
+            page_size,
+            total,
+            total_pages: total.div_ceil(u64::from(page_size)),
+        })
+    }
+}
+
+pub(crate) fn serialize_hash_id<S>(value: &u64, serializer: S) -> Result<S::Ok, S::Error>
 where
     S: serde::Serializer,
 {
@@ -113,9 +343,68 @@ where
     decode_single(&hash).map_err(|_| serde::de::Error::custom("Failed to decode hash ID"))
 }
 
+/// Tells a field sent as `null` (`Some(None)`) apart from one left out (`None`)
+fn deserialize_present<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
 fn serialize_decimal_to_f32<S>(value: &rust_decimal::Decimal, serializer: S) -> Result<S::Ok, S::Error>
                                where
     S: serde::Serializer,
 {
     value.to_f32().serialize(serializer)
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filters_default_to_every_product_by_name() {
+        let filter = ProductFilter::from_query(ProductQuery {
+            search: Some("  ".to_string()),
+            bin_location: Some(" A-12 ".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(filter.search, None);
+        assert_eq!(filter.bin_location_prefix.as_deref(), Some("A-12"));
+        assert_eq!((filter.sort, filter.direction), (ProductSort::Name, SortDirection::Asc));
+        assert_eq!((filter.page, filter.page_size, filter.offset()), (1, None, 0));
+        assert!(Pagination::new(&filter, 41).is_none());
+
+        let filter = ProductFilter::from_query(ProductQuery {
+            page: Some(2),
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(filter.page_size, Some(DEFAULT_PRODUCT_PAGE_SIZE));
+        assert_eq!(filter.offset(), u64::from(DEFAULT_PRODUCT_PAGE_SIZE));
+
+        let filter = ProductFilter::from_query(ProductQuery {
+            page: Some(3),
+            page_size: Some(20),
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(filter.offset(), 40);
+        assert_eq!(Pagination::new(&filter, 41).map(|p| p.total_pages), Some(3));
+        assert_eq!(Pagination::new(&filter, 0).map(|p| p.total_pages), Some(0));
+    }
+
+    #[test]
+    fn invalid_filters_report_every_problem() {
+        let errors = ProductFilter::from_query(ProductQuery {
+            min_price: Some(rust_decimal::Decimal::new(500, 2)),
+            max_price: Some(rust_decimal::Decimal::new(100, 2)),
+            page: Some(0),
+            page_size: Some(MAX_PRODUCT_PAGE_SIZE + 1),
+            ..Default::default()
+        })
+        .unwrap_err();
+        assert_eq!(errors.len(), 3);
+    }
+}
@@ -1,6 +1,6 @@
-use crate::products::products_data::{ProductFilter, ProductRecord, ProductWithCategory};
+use crate::products::products_data::{ProductFilter, ProductPage, ProductRecord, ProductWithCategory};
 use rust_decimal::Decimal;
-use sqlx::{Executor, MySqlPool};
+use sqlx::{Executor, MySql, MySqlPool, QueryBuilder, Transaction};
 use tokio::fs;
 
 pub async fn initialize(pool: &MySqlPool) -> anyhow::Result<()> {
@@ -19,6 +19,9 @@ pub async fn initialize(pool: &MySqlPool) -> anyhow::Result<()> {
             `price` DECIMAL(10,2) NOT NULL DEFAULT 0.00,
             `in_stock` BOOLEAN NOT NULL DEFAULT TRUE,
             `stock_quantity` FLOAT NOT NULL DEFAULT 0.0,
+            `case_pack_size` INT NOT NULL DEFAULT 1,
+            `allow_backorder` BOOLEAN NOT NULL DEFAULT FALSE,
+            `reorder_point` FLOAT NULL,
             `is_active` BOOLEAN NOT NULL DEFAULT TRUE,
             `created_at` DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
             `updated_at` DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
@@ -27,11 +30,19 @@ pub async fn initialize(pool: &MySqlPool) -> anyhow::Result<()> {
             INDEX `idx_category_id` (`category_id`),
             INDEX `idx_sku` (`sku`),
             INDEX `idx_active` (`is_active`),
-            INDEX `idx_name` (`name`)
+            INDEX `idx_name` (`name`),
+            FULLTEXT INDEX `ft_products_search` (`name`, `description`, `sku`)
         )
         "#,
     )
     .await?;
+
+    // Columns added after the initial schema; these fail harmlessly once they exist
+    pool.execute("ALTER TABLE products ADD COLUMN `case_pack_size` INT NOT NULL DEFAULT 1 AFTER `stock_quantity`").await.ok();
+    pool.execute("ALTER TABLE products ADD COLUMN `allow_backorder` BOOLEAN NOT NULL DEFAULT FALSE AFTER `case_pack_size`").await.ok();
+    pool.execute("ALTER TABLE products ADD COLUMN `reorder_point` FLOAT NULL AFTER `allow_backorder`").await.ok();
+    pool.execute("ALTER TABLE products ADD FULLTEXT INDEX `ft_products_search` (`name`, `description`, `sku`)").await.ok();
+
     fs::create_dir_all("products").await?;
 
     Ok(())
@@ -52,6 +63,9 @@ struct ProductWithCategoryQuery {
     price: Decimal,
     in_stock: bool,
     stock_quantity: f32,
+    case_pack_size: i32,
+    allow_backorder: bool,
+    reorder_point: Option<f32>,
     is_active: bool,
     created_at: chrono::NaiveDateTime,
     updated_at: chrono::NaiveDateTime,
@@ -74,6 +88,9 @@ impl From<ProductWithCategoryQuery> for ProductWithCategory {
                 price: query_result.price,
                 in_stock: query_result.in_stock,
                 stock_quantity: query_result.stock_quantity,
+                case_pack_size: query_result.case_pack_size,
+                allow_backorder: query_result.allow_backorder,
+                reorder_point: query_result.reorder_point,
                 is_active: query_result.is_active,
                 created_at: query_result.created_at,
                 updated_at: query_result.updated_at,
@@ -83,62 +100,106 @@ impl From<ProductWithCategoryQuery> for ProductWithCategory {
     }
 }
 
+/// Escapes `%`, `_` and `\` so user text matches literally inside a LIKE pattern
+fn escape_like(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+/// Appends `filter`'s conditions to a query ending in `WHERE 1 = 1`
+pub(crate) fn push_filter_conditions(query: &mut QueryBuilder<'_, MySql>, filter: &ProductFilter) {
+    if let Some(category_id) = filter.category_id {
+        query.push(" AND p.category_id = ").push_bind(category_id);
+    }
+    if let Some(search) = &filter.search {
+        let pattern = format!("%{}%", escape_like(search));
+        query
+            .push(" AND (p.name LIKE ")
+            .push_bind(pattern.clone())
+            .push(" OR p.description LIKE ")
+            .push_bind(pattern.clone())
+            .push(" OR p.sku LIKE ")
+            .push_bind(pattern)
+            .push(")");
+    }
+    if let Some(in_stock) = filter.in_stock {
+        query.push(" AND p.in_stock = ").push_bind(in_stock);
+    }
+    if let Some(is_active) = filter.is_active {
+        query.push(" AND p.is_active = ").push_bind(is_active);
+    }
+    if let Some(unit_type) = filter.unit_type {
+        query.push(" AND p.unit_type = ").push_bind(u8::from(unit_type));
+    }
+    if let Some(min_price) = filter.min_price {
+        query.push(" AND p.price >= ").push_bind(min_price);
+    }
+    if let Some(max_price) = filter.max_price {
+        query.push(" AND p.price <= ").push_bind(max_price);
+    }
+    if let Some(prefix) = &filter.bin_location_prefix {
+        query
+            .push(" AND p.bin_location LIKE ")
+            .push_bind(format!("{}%", escape_like(prefix)));
+    }
+    if let Some(updated_since) = filter.updated_since {
+        query.push(" AND p.updated_at >= ").push_bind(updated_since);
+    }
+}
+
 impl ProductRecord {
+    /// One page of the products matching `filter`, with the number matched across all pages
     pub async fn get_all_with_filter(
         pool: &MySqlPool,
         filter: &ProductFilter,
-    ) -> anyhow::Result<Vec<ProductWithCategory>> {
-        let mut query = String::from(
+    ) -> anyhow::Result<ProductPage> {
+        let mut count_query = QueryBuilder::<MySql>::new(
             r#"
-            SELECT p.*, c.name as category_name
+            SELECT COUNT(*)
             FROM `products` p
             JOIN `categories` c ON p.category_id = c.id
+            WHERE 1 = 1
             "#,
         );
+        push_filter_conditions(&mut count_query, filter);
+        let total: i64 = count_query.build_query_scalar().fetch_one(pool).await?;
 
-        let mut bind_values = Vec::new();
-        let mut conditions = Vec::new();
-
-        if let Some(category_id_str) = &filter.category_id {
-            if let Ok(category_id) = serde_hash::hashids::decode_single(category_id_str) {
-                conditions.push("p.category_id = ?");
-                bind_values.push(category_id.to_string());
-            }
-        }
-
-        if let Some(search_term) = &filter.search {
-            conditions.push("(p.name LIKE ? OR p.description LIKE ? OR p.sku LIKE ?)");
-            let search_pattern = format!("%{}%", search_term);
-            bind_values.push(search_pattern.clone());
-            bind_values.push(search_pattern.clone());
-            bind_values.push(search_pattern);
-        }
-
-        if !conditions.is_empty() {
-            query.push_str(" AND ");
-            query.push_str(&conditions.join(" AND "));
-        }
-
-        query.push_str(" ORDER BY p.name ASC");
-
-        if let Some(limit) = filter.limit {
-            query.push_str(&format!(" LIMIT {}", limit));
-            if let Some(offset) = filter.offset {
-                query.push_str(&format!(" OFFSET {}", offset));
-            }
-        }
-
-        let mut sql_query = sqlx::query_as::<_, ProductWithCategoryQuery>(&query);
-
-        // Bind values in order
-        for value in bind_values {
-            sql_query = sql_query.bind(value);
+        let mut query = QueryBuilder::<MySql>::new(
+            r#"
+            SELECT p.*, c.name as category_name
+            FROM `products` p
+            JOIN `categories` c ON p.category_id = c.id
+            WHERE 1 = 1
+            "#,
+        );
+        push_filter_conditions(&mut query, filter);
+        // The ID breaks ties so pages do not overlap or skip products
+        let direction = filter.direction.as_sql();
+        query.push(format!(
+            " ORDER BY {} {}, p.id {}",
+            filter.sort.column(),
+            direction,
+            direction
+        ));
+        if let Some(page_size) = filter.page_size {
+            query
+                .push(" LIMIT ")
+                .push_bind(page_size)
+                .push(" OFFSET ")
+                .push_bind(filter.offset());
         }
 
-        let query_results = sql_query.fetch_all(pool).await?;
+        let query_results = query
+            .build_query_as::<ProductWithCategoryQuery>()
+            .fetch_all(pool)
+            .await?;
         let products = query_results.into_iter().map(|q| q.into()).collect();
 
-        Ok(products)
+        Ok(ProductPage {
+            products,
+            total: total as u64,
+        })
     }
 
     pub async fn get_by_id(
@@ -191,14 +252,17 @@ impl ProductRecord {
         price: Decimal,
         bin_location: S,
         unit_type: i32,
+        case_pack_size: i32,
+        allow_backorder: bool,
+        reorder_point: Option<f32>,
     ) -> anyhow::Result<ProductRecord>
     where
         S: AsRef<str>,
     {
         let result = sqlx::query(
             r#"
-            INSERT INTO `products` (`name`, `description`, `sku`, `category_id`, `image_url`, `bin_location`, `unit_type`, `price`)
-            VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+            INSERT INTO `products` (`name`, `description`, `sku`, `category_id`, `image_url`, `bin_location`, `unit_type`, `price`, `case_pack_size`, `allow_backorder`, `reorder_point`)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
             "#
         )
         .bind(name.as_ref())
@@ -209,6 +273,9 @@ impl ProductRecord {
         .bind(bin_location.as_ref())
         .bind(unit_type)
         .bind(price)
+        .bind(case_pack_size)
+        .bind(allow_backorder)
+        .bind(reorder_point)
         .execute(pool)
         .await?;
 
@@ -230,6 +297,48 @@ impl ProductRecord {
         price: Option<Decimal>,
         bin_location: Option<&str>,
         unit_type: Option<i32>,
+        case_pack_size: Option<i32>,
+        allow_backorder: Option<bool>,
+        reorder_point: Option<Option<f32>>,
+    ) -> anyhow::Result<Option<ProductRecord>> {
+        let mut transaction = pool.begin().await?;
+        let product = Self::update_with_transaction(
+            &mut transaction,
+            id,
+            name,
+            description,
+            sku,
+            category_id,
+            image_url,
+            is_active,
+            price,
+            bin_location,
+            unit_type,
+            case_pack_size,
+            allow_backorder,
+            reorder_point,
+        )
+        .await?;
+        transaction.commit().await?;
+        Ok(product)
+    }
+
+    /// [`Self::update`] as one step of a larger change; `None` when there is no product `id`
+    pub async fn update_with_transaction(
+        transaction: &mut Transaction<'_, MySql>,
+        id: u64,
+        name: Option<&str>,
+        description: Option<&str>,
+        sku: Option<&str>,
+        category_id: Option<u64>,
+        image_url: Option<&str>,
+        is_active: Option<bool>,
+        price: Option<Decimal>,
+        bin_location: Option<&str>,
+        unit_type: Option<i32>,
+        case_pack_size: Option<i32>,
+        allow_backorder: Option<bool>,
+        reorder_point: Option<Option<f32>>,
     ) -> anyhow::Result<Option<ProductRecord>> {
         let mut query = "UPDATE products SET ".to_string();
         let mut updates = Vec::new();
@@ -271,11 +380,23 @@ impl ProductRecord {
             updates.push("unit_type = ?");
             bind_count += 1;
         }
+        if case_pack_size.is_some() {
+            updates.push("case_pack_size = ?");
+            bind_count += 1;
+        }
+        if allow_backorder.is_some() {
+            updates.push("allow_backorder = ?");
+            bind_count += 1;
+        }
+        if reorder_point.is_some() {
+            updates.push("reorder_point = ?");
+            bind_count += 1;
+        }
 
         updates.push("updated_at = CURRENT_TIMESTAMP");
 
         if bind_count == 0 {
-            return ProductRecord::get_by_id_simple(pool, id).await;
+            return ProductRecord::get_by_id_simple(&mut **transaction, id).await;
         }
 
         query.push_str(&updates.join(", "));
@@ -310,16 +431,25 @@ impl ProductRecord {
         if let Some(v) = unit_type {
             query_builder = query_builder.bind(v);
         }
+        if let Some(v) = case_pack_size {
+            query_builder = query_builder.bind(v);
+        }
+        if let Some(v) = allow_backorder {
+            query_builder = query_builder.bind(v);
+        }
+        if let Some(v) = reorder_point {
+            query_builder = query_builder.bind(v);
+        }
 
         query_builder = query_builder.bind(id);
 
-        let result = query_builder.execute(pool).await?;
+        let result = query_builder.execute(&mut **transaction).await?;
 
         if result.rows_affected() == 0 {
             return Ok(None);
         }
 
-        ProductRecord::get_by_id_simple(pool, id).await
+        ProductRecord::get_by_id_simple(&mut **transaction, id).await
     }
 
     pub async fn delete(pool: &MySqlPool, id: u64) -> anyhow::Result<bool> {
@@ -332,20 +462,23 @@ impl ProductRecord {
     }
 
     // Add a new function that returns just ProductRecord without category info
-    pub async fn get_by_id_simple(
-        pool: &MySqlPool,
+    pub async fn get_by_id_simple<'e, E>(
+        executor: E,
         id: u64,
-    ) -> anyhow::Result<Option<ProductRecord>> {
+    ) -> anyhow::Result<Option<ProductRecord>>
+    where
+        E: Executor<'e, Database = MySql>,
+    {
         let product = sqlx::query_as::<_, ProductRecord>(
             r#"
             SELECT id, name, description, sku, category_id, image_url, bin_location, unit_type, price, in_stock, stock_quantity,
-                   is_active, created_at, updated_at
+                   case_pack_size, allow_backorder, reorder_point, is_active, created_at, updated_at
             FROM `products`
             WHERE id = ?
             "#,
         )
         .bind(id)
-        .fetch_optional(pool)
+        .fetch_optional(executor)
         .await?;
 
         Ok(product)
@@ -1,5 +1,11 @@
 use crate::auth::{jwt_validator, ClaimsExtractor};
-use crate::products::products_data::{CreateProductRequest, ProductFilter, ProductRecord, UpdateProductRequest};
+use crate::products::{
+    barcode_endpoint, bulk_endpoint, export_endpoint, import_endpoint, inventory_endpoint,
+    low_stock_endpoint, search_endpoint,
+};
+use crate::products::products_data::{
+    CreateProductRequest, Pagination, ProductFilter, ProductQuery, ProductRecord, UpdateProductRequest,
+};
 use actix_web::{delete, get, post, put, web, HttpRequest, HttpResponse, Responder};
 use actix_web_httpauth::middleware::HttpAuthentication;
 use database_common_lib::{database_connection::DatabaseConnectionData, http_error::Result};
@@ -8,15 +14,26 @@ use serde_json::json;
 #[get("")]
 pub async fn get_products(
     connection_data: web::Data<DatabaseConnectionData>,
-    query: web::Query<ProductFilter>,
+    query: web::Query<ProductQuery>,
 ) -> Result<impl Responder> {
+    let filter = match ProductFilter::from_query(query.into_inner()) {
+        Ok(filter) => filter,
+        Err(errors) => {
+            return Ok(HttpResponse::BadRequest().json(json!({
+                "success": false,
+                "error": "Invalid product filter",
+                "details": errors
+            })));
+        }
+    };
     let pool = connection_data.get_pool().await?;
-    let products = ProductRecord::get_all_with_filter(&pool, &query).await?;
+    let page = ProductRecord::get_all_with_filter(&pool, &filter).await?;
 
     Ok(HttpResponse::Ok().json(json!({
         "success": true,
-        "data": products,
-        "count": products.len()
+        "count": page.products.len(),
+        "pagination": Pagination::new(&filter, page.total),
+        "data": page.products
     })))
 }
 
@@ -76,6 +93,17 @@ pub async fn create_product(
         })));
     }
 
+    if request.case_pack_size < 1 {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "error": "Case pack size must be at least 1"
+        })));
+    }
+    if request.reorder_point.is_some_and(|point| point < 0.0) {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "error": "Reorder point cannot be negative"
+        })));
+    }
+
     let pool = connection_data.get_pool().await?;
 
     // Decode category_id
@@ -91,6 +119,9 @@ pub async fn create_product(
         request.price,
         &request.bin_location,
         u8::from(request.unit_type) as i32,
+        request.case_pack_size,
+        request.allow_backorder,
+        request.reorder_point,
     ).await?;
 
     Ok(HttpResponse::Created().json(json!({
@@ -119,6 +150,12 @@ pub async fn update_product(
         })));
     }
 
+    if let Some(error) = request.validation_errors().into_iter().next() {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "error": error
+        })));
+    }
+
     let pool = connection_data.get_pool().await?;
     let product_id = serde_hash::hashids::decode_single(path.as_str())?;
 
@@ -141,6 +178,9 @@ pub async fn update_product(
         request.price,
         request.bin_location.as_deref(),
         request.unit_type.map(|u| u8::from(u) as i32),
+        request.case_pack_size,
+        request.allow_backorder,
+        request.reorder_point,
     ).await? {
         Some(product) => Ok(HttpResponse::Ok().json(json!({
             "success": true,
@@ -193,8 +233,24 @@ pub fn configure(cfg: &mut web::ServiceConfig) {
 
     cfg.service(
         web::scope("/products")
+            // Registered before `/{id}`, like `/search`, so it is not taken for a product ID
+            .service(
+                web::scope("/low-stock")
+                    .wrap(HttpAuthentication::bearer(jwt_validator))
+                    .service(low_stock_endpoint::get_low_stock)
+            )
+            .service(
+                web::scope("/bulk")
+                    .wrap(HttpAuthentication::bearer(jwt_validator))
+                    // Room for MAX_BULK_PRODUCTS hashed IDs
+                    .app_data(crate::json_config(64 * 1024))
+                    .service(bulk_endpoint::bulk_update_products)
+            )
             // Public endpoints - no auth required
             .service(get_products)
+            .service(search_endpoint::search_products)
+            .service(barcode_endpoint::lookup_barcode)
+            .service(barcode_endpoint::get_barcode_svg)
             .service(get_product)
             .service(get_products_by_category)
             // Admin-only endpoints with authentication
@@ -202,8 +258,15 @@ pub fn configure(cfg: &mut web::ServiceConfig) {
                 web::scope("/admin")
                     .wrap(auth)
                     .service(create_product)
+                    .service(import_endpoint::import_products)
+                    .service(export_endpoint::export_products)
                     .service(update_product)
                     .service(delete_product)
+                    .service(inventory_endpoint::adjust_stock)
+                    .service(inventory_endpoint::get_movements)
+                    .service(barcode_endpoint::get_product_barcodes)
+                    .service(barcode_endpoint::add_product_barcode)
+                    .service(barcode_endpoint::delete_product_barcode)
             )
             .default_service(web::to(|| async {
                 HttpResponse::NotFound().json(json!({ "error": "Product endpoint not found" }))
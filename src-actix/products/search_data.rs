@@ -0,0 +1,308 @@
+use crate::auth::email_service::escape_html;
+use crate::products::products_data::ProductWithCategory;
+use serde::{Deserialize, Serialize};
+
+pub const DEFAULT_SEARCH_LIMIT: u32 = 20;
+pub const MAX_SEARCH_LIMIT: u32 = 100;
+/// Shortest word the FULLTEXT index holds (`innodb_ft_min_token_size`); shorter terms are
+/// only matched by the typo-tolerant pass
+pub const MIN_INDEXED_TERM_CHARS: usize = 3;
+/// Approximate length of a description snippet
+const SNIPPET_CHARS: usize = 160;
+/// Characters of context kept before the first match in a snippet
+const SNIPPET_LEAD_CHARS: usize = 40;
+
+#[derive(Debug, Deserialize)]
+pub struct ProductSearchQuery {
+    pub q: Option<String>,
+    pub limit: Option<u32>,
+}
+
+/// How a search hit matched, best first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchKind {
+    /// The query is the product's SKU
+    ExactSku,
+    /// Every term is a word, or the start of a word, in the name, description or SKU
+    FullText,
+    /// Every term is close to a word in the name or SKU
+    Fuzzy,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SearchHighlights {
+    /// HTML-escaped, with matching words wrapped in `<mark>`
+    pub name: String,
+    pub sku: String,
+    /// Part of the description around its first match, if it has one
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ProductSearchHit {
+    #[serde(flatten)]
+    pub product: ProductWithCategory,
+    pub match_kind: MatchKind,
+    /// FULLTEXT relevance, or 0 to 1 for fuzzy matches; only comparable within a kind
+    pub score: f64,
+    pub highlights: SearchHighlights,
+}
+
+/// A search query split into lowercase words
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchTerms {
+    raw: String,
+    terms: Vec<String>,
+}
+
+impl SearchTerms {
+    /// Splits on anything that is not a letter or digit, which also drops the FULLTEXT
+    /// boolean operators
+    pub fn parse(query: &str) -> Self {
+        let mut terms: Vec<String> = Vec::new();
+        for (_, word) in words(query) {
+            let term = word.to_lowercase();
+            if !terms.contains(&term) {
+                terms.push(term);
+            }
+        }
+        Self {
+            raw: query.trim().to_string(),
+            terms,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    /// The query as typed, for comparing against SKUs
+    pub fn raw(&self) -> &str {
+        &self.raw
+    }
+
+    /// Boolean-mode FULLTEXT query requiring every indexed term as a word prefix, e.g.
+    /// `+paper* +towel*`; `None` when no term is long enough to be indexed
+    pub fn boolean_mode_query(&self) -> Option<String> {
+        let required: Vec<String> = self
+            .terms
+            .iter()
+            .filter(|term| term.chars().count() >= MIN_INDEXED_TERM_CHARS)
+            .map(|term| format!("+{}*", term))
+            .collect();
+        (!required.is_empty()).then(|| required.join(" "))
+    }
+
+    /// 0 to 1 when every term matches a word of `text` within its typo budget, best at 1
+    pub fn fuzzy_score(&self, text: &str) -> Option<f64> {
+        if self.terms.is_empty() {
+            return None;
+        }
+        let words: Vec<String> = words(text).map(|(_, word)| word.to_lowercase()).collect();
+        let mut total = 0.0;
+        for term in &self.terms {
+            let distance = words.iter().filter_map(|word| term_distance(term, word)).min()?;
+            total += 1.0 / (1.0 + distance as f64);
+        }
+        Some(total / self.terms.len() as f64)
+    }
+
+    /// Pieces of each term, one of which any word within the term's typo budget contains
+    /// unchanged: a term allowed `k` typos is cut into `k + 1` pieces and a typo can only
+    /// touch one of them. Pieces are letters and digits only, safe inside a `LIKE` pattern.
+    pub fn fuzzy_fragments(&self) -> Vec<Vec<String>> {
+        self.terms
+            .iter()
+            .map(|term| {
+                let chars: Vec<char> = term.chars().collect();
+                let pieces = typo_budget(chars.len()) + 1;
+                (0..pieces)
+                    .map(|i| {
+                        chars[i * chars.len() / pieces..(i + 1) * chars.len() / pieces]
+                            .iter()
+                            .collect()
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn matches_word(&self, word: &str) -> bool {
+        let word = word.to_lowercase();
+        self.terms.iter().any(|term| term_distance(term, &word).is_some())
+    }
+
+    /// `text` HTML-escaped with matching words wrapped in `<mark>`
+    pub fn highlight(&self, text: &str) -> String {
+        let mut highlighted = String::with_capacity(text.len());
+        let mut last = 0;
+        for (start, word) in words(text) {
+            if self.matches_word(word) {
+                highlighted.push_str(&escape_html(&text[last..start]));
+                highlighted.push_str("<mark>");
+                highlighted.push_str(&escape_html(word));
+                highlighted.push_str("</mark>");
+                last = start + word.len();
+            }
+        }
+        highlighted.push_str(&escape_html(&text[last..]));
+        highlighted
+    }
+
+    /// Highlighted part of `text` around its first matching word, cut at word boundaries
+    pub fn snippet(&self, text: &str) -> Option<String> {
+        let (first, first_word) = words(text).find(|(_, word)| self.matches_word(word))?;
+        let first_end = first + first_word.len();
+        let mid_word = |at: usize| {
+            text[..at].chars().next_back().is_some_and(char::is_alphanumeric)
+                && text[at..].chars().next().is_some_and(char::is_alphanumeric)
+        };
+
+        let lead_start = text[..first]
+            .char_indices()
+            .rev()
+            .nth(SNIPPET_LEAD_CHARS - 1)
+            .map(|(i, _)| i)
+            .unwrap_or(0);
+        // Start on a whole word
+        let mut lead_words = words(&text[lead_start..first]);
+        if mid_word(lead_start) {
+            lead_words.next();
+        }
+        let start = lead_words
+            .next()
+            .map(|(offset, _)| lead_start + offset)
+            .unwrap_or(first);
+        let limit = text[start..]
+            .char_indices()
+            .nth(SNIPPET_CHARS)
+            .map(|(i, _)| start + i)
+            .unwrap_or(text.len());
+        // End after a whole word, and never before the match
+        let mut trail_words: Vec<(usize, &str)> = words(&text[start..limit]).collect();
+        if mid_word(limit) {
+            trail_words.pop();
+        }
+        let end = trail_words
+            .last()
+            .map(|(offset, word)| start + offset + word.len())
+            .unwrap_or(limit)
+            .max(first_end);
+
+        let mut snippet = String::new();
+        if start > 0 {
+            snippet.push('…');
+        }
+        snippet.push_str(&self.highlight(text[start..end].trim()));
+        if end < text.len() {
+            snippet.push('…');
+        }
+        Some(snippet)
+    }
+}
+
+/// Runs of letters and digits in `text`, with their byte offsets
+fn words(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(move |word| (word.as_ptr() as usize - text.as_ptr() as usize, word))
+}
+
+/// Typos tolerated in a term: none for short terms, where a typo changes too much
+fn typo_budget(term_chars: usize) -> usize {
+    match term_chars {
+        0..=3 => 0,
+        4..=7 => 1,
+        _ => 2,
+    }
+}
+
+/// Typos between `term` and `word`, or its start of the same length so a partly typed word
+/// still matches; `None` when over the term's budget
+fn term_distance(term: &str, word: &str) -> Option<usize> {
+    if word.starts_with(term) {
+        return Some(0);
+    }
+    let term_chars = term.chars().count();
+    let prefix: String = word.chars().take(term_chars).collect();
+    let distance = edit_distance(term, word).min(edit_distance(term, &prefix));
+    (distance <= typo_budget(term_chars)).then_some(distance)
+}
+
+/// Levenshtein distance, counted in characters
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn terms_become_required_prefixes() {
+        let terms = SearchTerms::parse("  Paper +towels -(2 ply)* ");
+        assert_eq!(terms.raw(), "Paper +towels -(2 ply)*");
+        assert_eq!(
+            terms.boolean_mode_query().as_deref(),
+            Some("+paper* +towels* +ply*")
+        );
+        assert_eq!(SearchTerms::parse("a 2").boolean_mode_query(), None);
+        assert!(SearchTerms::parse(" -*+ ").is_empty());
+    }
+
+    #[test]
+    fn fuzzy_matching_tolerates_typos_and_partial_words() {
+        assert_eq!(edit_distance("towel", "towle"), 2);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+
+        let terms = SearchTerms::parse("papr towl");
+        assert!(terms.fuzzy_score("Paper Towels, 2 ply").is_some());
+        assert!(terms.fuzzy_score("Paper Plates").is_none());
+        assert_eq!(SearchTerms::parse("pap").fuzzy_score("Paper"), Some(1.0));
+        // Too short to forgive a typo
+        assert!(SearchTerms::parse("cup").fuzzy_score("Cap").is_none());
+    }
+
+    #[test]
+    fn fuzzy_fragments_survive_the_typo_budget() {
+        let terms = SearchTerms::parse("cup papr dispensr");
+        assert_eq!(
+            terms.fuzzy_fragments(),
+            vec![
+                vec!["cup".to_string()],
+                vec!["pa".to_string(), "pr".to_string()],
+                vec!["di".to_string(), "spe".to_string(), "nsr".to_string()],
+            ]
+        );
+        for (fragments, word) in terms.fuzzy_fragments().iter().zip(["cups", "paper", "dispenser"]) {
+            assert!(fragments.iter().any(|fragment| word.contains(fragment.as_str())));
+        }
+    }
+
+    #[test]
+    fn highlights_escape_and_mark_matches() {
+        let terms = SearchTerms::parse("towel");
+        assert_eq!(
+            terms.highlight("Paper <Towels> & more"),
+            "Paper &lt;<mark>Towels</mark>&gt; &amp; more"
+        );
+
+        let description = format!("{} towels for the break room. {}", "Soft ".repeat(20), "Bulk ".repeat(40));
+        let snippet = terms.snippet(&description).unwrap();
+        assert!(snippet.starts_with("…Soft"));
+        assert!(snippet.contains("<mark>towels</mark>"));
+        assert!(snippet.ends_with("Bulk…"));
+        assert_eq!(terms.snippet("Paper plates"), None);
+    }
+}
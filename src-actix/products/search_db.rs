@@ -0,0 +1,179 @@
+use crate::products::products_data::{ProductRecord, ProductWithCategory};
+use crate::products::search_data::{MatchKind, ProductSearchHit, SearchHighlights, SearchTerms};
+use sqlx::{FromRow, MySql, MySqlPool, QueryBuilder};
+use std::collections::{HashMap, HashSet};
+
+/// Most products the typo-tolerant pass scores in Rust. Candidates are already narrowed in
+/// SQL to those containing a piece of every term; past this many, the rest are not considered.
+const MAX_FUZZY_CANDIDATES: u64 = 2000;
+
+const SEARCH_FROM: &str = r#"
+    FROM `products` p
+    JOIN `categories` c ON p.category_id = c.id
+    WHERE p.is_active = TRUE AND c.is_active = TRUE
+"#;
+
+#[derive(FromRow)]
+struct SearchRow {
+    #[sqlx(flatten)]
+    product: ProductRecord,
+    category_name: String,
+    relevance: f64,
+}
+
+impl ProductSearchHit {
+    fn new(terms: &SearchTerms, row: SearchRow, match_kind: MatchKind, score: f64) -> Self {
+        let highlights = SearchHighlights {
+            name: terms.highlight(&row.product.name),
+            sku: terms.highlight(&row.product.sku),
+            description: terms.snippet(&row.product.description),
+        };
+        Self {
+            product: ProductWithCategory {
+                product: row.product,
+                category_name: row.category_name,
+            },
+            match_kind,
+            score,
+            highlights,
+        }
+    }
+
+    /// Active products matching `terms`, best first: the product whose SKU is the query,
+    /// then FULLTEXT matches by relevance, then typo-tolerant matches on name and SKU to
+    /// fill up to `limit`.
+    ///
+    /// The FULLTEXT index is maintained by MySQL as products are written, so it needs no
+    /// syncing. The typo-tolerant pass compares against the name and SKU of every active
+    /// product, which is cheap at catalogue sizes but only runs when the first pass comes
+    /// up short.
+    pub async fn search(
+        pool: &MySqlPool,
+        terms: &SearchTerms,
+        limit: u32,
+    ) -> anyhow::Result<Vec<Self>> {
+        let boolean_query = terms.boolean_mode_query();
+
+        let mut query = QueryBuilder::<MySql>::new("SELECT p.*, c.name AS category_name, ");
+        match &boolean_query {
+            Some(boolean_query) => {
+                query
+                    .push("MATCH(p.name, p.description, p.sku) AGAINST (")
+                    .push_bind(boolean_query.clone())
+                    .push(" IN BOOLEAN MODE)");
+            }
+            None => {
+                query.push("0e0");
+            }
+        }
+        query
+            .push(" AS relevance")
+            .push(SEARCH_FROM)
+            .push(" AND (p.sku = ")
+            .push_bind(terms.raw().to_string());
+        if let Some(boolean_query) = &boolean_query {
+            query
+                .push(" OR MATCH(p.name, p.description, p.sku) AGAINST (")
+                .push_bind(boolean_query.clone())
+                .push(" IN BOOLEAN MODE)");
+        }
+        query
+            .push(") ORDER BY p.sku = ")
+            .push_bind(terms.raw().to_string())
+            .push(" DESC, relevance DESC, p.name ASC LIMIT ")
+            .push_bind(limit);
+        let rows = query.build_query_as::<SearchRow>().fetch_all(pool).await?;
+
+        let mut hits: Vec<Self> = rows
+            .into_iter()
+            .map(|row| {
+                // SKUs compare case-insensitively in MySQL, so the same here
+                let match_kind = if row.product.sku.eq_ignore_ascii_case(terms.raw()) {
+                    MatchKind::ExactSku
+                } else {
+                    MatchKind::FullText
+                };
+                let score = row.relevance;
+                Self::new(terms, row, match_kind, score)
+            })
+            .collect();
+
+        let wanted = (limit as usize).saturating_sub(hits.len());
+        if wanted > 0 {
+            let found: HashSet<u64> = hits.iter().map(|hit| hit.product.product.id).collect();
+            hits.extend(Self::search_fuzzy(pool, terms, &found, wanted).await?);
+        }
+        Ok(hits)
+    }
+
+    /// Up to `wanted` products, other than `found`, whose name and SKU match every term
+    /// within its typo budget, closest first.
+    ///
+    /// Only products containing one of each term's [`SearchTerms::fuzzy_fragments`] are
+    /// fetched for scoring, at most [`MAX_FUZZY_CANDIDATES`] of them.
+    async fn search_fuzzy(
+        pool: &MySqlPool,
+        terms: &SearchTerms,
+        found: &HashSet<u64>,
+        wanted: usize,
+    ) -> anyhow::Result<Vec<Self>> {
+        let mut candidates_query = QueryBuilder::<MySql>::new("SELECT p.id, p.name, p.sku");
+        candidates_query.push(SEARCH_FROM);
+        for fragments in terms.fuzzy_fragments() {
+            candidates_query.push(" AND (");
+            let mut any = candidates_query.separated(" OR ");
+            for fragment in fragments {
+                any.push("CONCAT(p.name, ' ', p.sku) LIKE ")
+                    .push_bind_unseparated(format!("%{}%", fragment));
+            }
+            candidates_query.push(")");
+        }
+        candidates_query
+            .push(" ORDER BY p.name LIMIT ")
+            .push_bind(MAX_FUZZY_CANDIDATES);
+        let candidates: Vec<(u64, String, String)> = candidates_query
+            .build_query_as()
+            .fetch_all(pool)
+            .await?;
+        let mut scored: Vec<(u64, f64)> = candidates
+            .into_iter()
+            .filter(|(id, _, _)| !found.contains(id))
+            .filter_map(|(id, name, sku)| {
+                terms
+                    .fuzzy_score(&format!("{} {}", name, sku))
+                    .map(|score| (id, score))
+            })
+            .collect();
+        scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+        scored.truncate(wanted);
+        if scored.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut query =
+            QueryBuilder::<MySql>::new("SELECT p.*, c.name AS category_name, 0e0 AS relevance");
+        query.push(SEARCH_FROM).push(" AND p.id IN (");
+        let mut ids = query.separated(", ");
+        for (id, _) in &scored {
+            ids.push_bind(*id);
+        }
+        query.push(")");
+        let mut rows: HashMap<u64, SearchRow> = query
+            .build_query_as::<SearchRow>()
+            .fetch_all(pool)
+            .await?
+            .into_iter()
+            .map(|row| (row.product.id, row))
+            .collect();
+
+        // Rows come back in any order; keep the ranking
+        let hits = scored
+            .into_iter()
+            .filter_map(|(id, score)| {
+                let row = rows.remove(&id)?;
+                Some(Self::new(terms, row, MatchKind::Fuzzy, score))
+            })
+            .collect();
+        Ok(hits)
+    }
+}
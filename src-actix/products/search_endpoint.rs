@@ -0,0 +1,33 @@
+use crate::products::search_data::{
+    ProductSearchHit, ProductSearchQuery, SearchTerms, DEFAULT_SEARCH_LIMIT, MAX_SEARCH_LIMIT,
+};
+use actix_web::{get, web, HttpResponse, Responder};
+use database_common_lib::{database_connection::DatabaseConnectionData, http_error::Result};
+use serde_json::json;
+
+/// Active products ranked by how well they match `q`, with highlighted snippets
+#[get("/search")]
+pub async fn search_products(
+    connection_data: web::Data<DatabaseConnectionData>,
+    query: web::Query<ProductSearchQuery>,
+) -> Result<impl Responder> {
+    let terms = SearchTerms::parse(query.q.as_deref().unwrap_or_default());
+    if terms.is_empty() {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "success": false,
+            "error": "A search term is required"
+        })));
+    }
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_SEARCH_LIMIT)
+        .clamp(1, MAX_SEARCH_LIMIT);
+
+    let pool = connection_data.get_pool().await?;
+    let hits = ProductSearchHit::search(&pool, &terms, limit).await?;
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "data": hits,
+        "count": hits.len()
+    })))
+}
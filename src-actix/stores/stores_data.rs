@@ -1,3 +1,4 @@
+use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 use serde_hash::HashIds;
 use sqlx::FromRow;
@@ -10,6 +11,20 @@ pub struct StoreRecord {
     pub address: Option<String>,
 }
 
+impl StoreRecord {
+    /// Name shown on manifests and pick lists: "City - Address" with whatever parts are set
+    pub fn display_name(&self) -> String {
+        match (self.city.as_deref(), self.address.as_deref()) {
+            (Some(city), Some(addr)) if !city.is_empty() && !addr.is_empty() => {
+                format!("{} - {}", city, addr)
+            }
+            (Some(city), _) if !city.is_empty() => city.to_string(),
+            (_, Some(addr)) if !addr.is_empty() => addr.to_string(),
+            _ => format!("Store {}", self.id),
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CreateStoreRequest {
     pub city: Option<String>,
@@ -21,3 +36,9 @@ pub struct UpdateStoreRequest {
     pub city: Option<String>,
     pub address: Option<String>,
 }
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SetApprovalThresholdRequest {
+    /// Orders totalling more than this need admin approval; `null` removes the limit
+    pub threshold: Option<Decimal>,
+}
@@ -1,5 +1,6 @@
 use anyhow::Result;
-use sqlx::{Executor, MySqlPool};
+use rust_decimal::Decimal;
+use sqlx::{Executor, MySqlPool, Row};
 use crate::stores::stores_data::{StoreRecord, CreateStoreRequest, UpdateStoreRequest};
 
 pub async fn initialize(pool: &MySqlPool) -> Result<()> {
@@ -16,6 +17,9 @@ pub async fn initialize(pool: &MySqlPool) -> Result<()> {
     )
     .await?;
 
+    // Orders totalling more than this wait for admin approval; NULL means no limit
+    pool.execute("ALTER TABLE stores ADD COLUMN `approval_threshold` DECIMAL(10,2) NULL").await.ok();
+
     Ok(())
 }
 
@@ -139,4 +143,28 @@ impl StoreRecord {
 
         Ok(stores)
     }
+
+    pub async fn get_approval_threshold(pool: &MySqlPool, store_id: u64) -> Result<Option<Decimal>> {
+        let row = sqlx::query("SELECT approval_threshold FROM stores WHERE id = ?")
+            .bind(store_id)
+            .fetch_optional(pool)
+            .await?;
+
+        Ok(row.and_then(|r| r.get("approval_threshold")))
+    }
+
+    /// Sets or clears (`None`) the order total above which the store's orders need approval
+    pub async fn set_approval_threshold(
+        pool: &MySqlPool,
+        store_id: u64,
+        threshold: Option<Decimal>,
+    ) -> Result<()> {
+        sqlx::query("UPDATE stores SET approval_threshold = ? WHERE id = ?")
+            .bind(threshold)
+            .bind(store_id)
+            .execute(pool)
+            .await?;
+
+        Ok(())
+    }
 }
@@ -1,8 +1,10 @@
-use crate::stores::stores_data::{CreateStoreRequest, StoreRecord, UpdateStoreRequest};
-use actix_web::{delete, get, post, put, web, HttpResponse, Responder};
+use crate::stores::stores_data::{
+    CreateStoreRequest, SetApprovalThresholdRequest, StoreRecord, UpdateStoreRequest,
+};
+use actix_web::{delete, get, post, put, web, HttpRequest, HttpResponse, Responder};
 use database_common_lib::{database_connection::DatabaseConnectionData, http_error::Result};
 use serde_json::json;
-use crate::auth::{jwt_validator};
+use crate::auth::{jwt_validator, ClaimsExtractor};
 use actix_web_httpauth::middleware::HttpAuthentication;
 
 #[get("")]
@@ -102,6 +104,78 @@ pub async fn delete_store(
     }
 }
 
+#[get("/{id}/approval-threshold")]
+pub async fn get_approval_threshold(
+    req: HttpRequest,
+    connection_data: web::Data<DatabaseConnectionData>,
+    path: web::Path<String>,
+) -> Result<impl Responder> {
+    let claims = req
+        .get_claims()
+        .ok_or_else(|| anyhow::anyhow!("Authentication required"))?;
+    if claims.role != "admin" {
+        return Ok(HttpResponse::Forbidden().json(json!({
+            "success": false,
+            "error": "Admin access required"
+        })));
+    }
+    let pool = connection_data.get_pool().await?;
+    let store_id = serde_hash::hashids::decode_single(path.as_str())?;
+
+    if StoreRecord::get_by_id(&pool, store_id).await?.is_none() {
+        return Ok(HttpResponse::NotFound().json(json!({
+            "error": "Store not found"
+        })));
+    }
+    let threshold = StoreRecord::get_approval_threshold(&pool, store_id).await?;
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "data": { "threshold": threshold }
+    })))
+}
+
+#[put("/{id}/approval-threshold")]
+pub async fn set_approval_threshold(
+    req: HttpRequest,
+    connection_data: web::Data<DatabaseConnectionData>,
+    path: web::Path<String>,
+    request: web::Json<SetApprovalThresholdRequest>,
+) -> Result<impl Responder> {
+    let claims = req
+        .get_claims()
+        .ok_or_else(|| anyhow::anyhow!("Authentication required"))?;
+    if claims.role != "admin" {
+        return Ok(HttpResponse::Forbidden().json(json!({
+            "success": false,
+            "error": "Admin access required"
+        })));
+    }
+    let pool = connection_data.get_pool().await?;
+    let store_id = serde_hash::hashids::decode_single(path.as_str())?;
+
+    if request.threshold.is_some_and(|t| t.is_sign_negative()) {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "success": false,
+            "error": "Approval threshold cannot be negative"
+        })));
+    }
+    if StoreRecord::get_by_id(&pool, store_id).await?.is_none() {
+        return Ok(HttpResponse::NotFound().json(json!({
+            "error": "Store not found"
+        })));
+    }
+
+    StoreRecord::set_approval_threshold(&pool, store_id, request.threshold).await?;
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "data": { "threshold": request.threshold },
+        "message": match request.threshold {
+            Some(_) => "Approval threshold saved",
+            None => "Approval threshold removed",
+        }
+    })))
+}
+
 pub fn configure(cfg: &mut web::ServiceConfig) {
     let auth = HttpAuthentication::bearer(jwt_validator);
 
@@ -117,6 +191,8 @@ pub fn configure(cfg: &mut web::ServiceConfig) {
                     .service(create_store)
                     .service(update_store)
                     .service(delete_store)
+                    .service(get_approval_threshold)
+                    .service(set_approval_threshold)
             )
             .default_service(web::to(|| async {
                 HttpResponse::NotFound().json(json!({ "error": "API endpoint not found" }))
@@ -87,6 +87,9 @@ pub async fn upload_product_image(
         None,
         None,
         None,
+        None,
+        None,
+        None,
     )
     .await? {
         Some(_product) => Ok(HttpResponse::Ok().json(json!({
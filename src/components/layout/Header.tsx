@@ -127,7 +127,7 @@ const Header: React.FC = () =>
             setUsersUnauthorized(false);
             try
             {
-                const prodPromise = productsApi.getProducts({search: query.trim(), limit: 10}).catch(() => ({data: []} as any));
+                const prodPromise = productsApi.searchProducts(query.trim(), 10).catch(() => ({data: []} as any));
                 const ordersPromise = ordersApi.getOrders().catch(() => ({data: []} as any));
                 const usersPromise = authApi.getUsers().catch(() =>
                 {
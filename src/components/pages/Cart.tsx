@@ -1,4 +1,4 @@
-import React, {useState} from "react";
+import React, {useRef, useState} from "react";
 import {useNavigate} from "react-router-dom";
 import {Button, Card, CardBody, CardHeader, Chip, Divider, Modal, ModalBody, ModalContent, ModalFooter, ModalHeader, useDisclosure} from "@heroui/react";
 import {Icon} from "@iconify-icon/react";
@@ -7,6 +7,25 @@ import {useAuth} from "../../providers/AuthProvider";
 import {ordersApi} from "../../utils/api";
 import {Input} from "../extension/Input.tsx";
 
+// crypto.randomUUID only exists in secure contexts, so plain-HTTP deployments build a v4 UUID themselves
+const newIdempotencyKey = (): string =>
+{
+    if (typeof crypto !== "undefined" && typeof crypto.randomUUID === "function") return crypto.randomUUID();
+
+    const bytes = new Uint8Array(16);
+    if (typeof crypto !== "undefined" && typeof crypto.getRandomValues === "function")
+    {
+        crypto.getRandomValues(bytes);
+    } else
+    {
+        for (let i = 0; i < bytes.length; i++) bytes[i] = Math.floor(Math.random() * 256);
+    }
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    const hex = Array.from(bytes, b => b.toString(16).padStart(2, "0")).join("");
+    return `${hex.slice(0, 8)}-${hex.slice(8, 12)}-${hex.slice(12, 16)}-${hex.slice(16, 20)}-${hex.slice(20)}`;
+};
+
 const Cart: React.FC = () =>
 {
     document.title = "Cart - Store Orders";
@@ -17,6 +36,8 @@ const Cart: React.FC = () =>
     const {user} = useAuth();
     const [isPlacing, setIsPlacing] = useState(false);
     const [errorMsg, setErrorMsg] = useState<string | null>(null);
+    // One key per checkout, kept until an order is placed: retrying after a dropped response cannot order twice
+    const idempotencyKey = useRef<string | null>(null);
 
     const handleQuantityChange = (productId: string, newQuantity: number) =>
     {
@@ -40,6 +61,8 @@ const Cart: React.FC = () =>
             return;
         }
 
+        const key = idempotencyKey.current ?? newIdempotencyKey();
+        idempotencyKey.current = key;
         setIsPlacing(true);
         try
         {
@@ -48,7 +71,7 @@ const Cart: React.FC = () =>
                 items: items.map(i => ({product_id: i.product.id, quantity: i.quantity})),
                 notes: orderNotes || undefined
             };
-            const resp = await ordersApi.createOrder(payload);
+            const resp = await ordersApi.createOrder(payload, key);
             if (!resp || (resp as any).success === false)
             {
                 throw new Error((resp as any)?.error || "Failed to create order");
@@ -56,6 +79,7 @@ const Cart: React.FC = () =>
             const data: any = (resp as any).data || resp; // ApiResponse vs direct
             const orderId = data?.id || data?.order?.id;
             if (!orderId) throw new Error("Order ID missing in response");
+            idempotencyKey.current = null;
             clearCart();
             navigate(`/app/order-confirmation/${orderId}`);
         } catch (e: any)
@@ -88,8 +112,6 @@ const Cart: React.FC = () =>
         );
     }
 
-    console.log("Cart Items: ", items);
-
     return (
         <div className="p-6">
             {/* Header */}
@@ -36,13 +36,13 @@ const Dashboard: React.FC = () =>
             {
                 const [ordersResponse, productsResponse] = await Promise.all([
                     ordersApi.getOrders(),
-                    productsApi.getProducts()
+                    productsApi.getProducts({page_size: 1})
                 ]);
 
                 setStats({
                     totalOrders: ordersResponse.data?.length || 0,
                     pendingOrders: ordersResponse.data?.filter((order: any) => order.status === "pending").length || 0,
-                    totalProducts: productsResponse.count || 0
+                    totalProducts: productsResponse.pagination?.total || 0
                 });
             } catch (error)
             {
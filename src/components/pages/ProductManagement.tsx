@@ -74,7 +74,9 @@ const ProductManagement: React.FC = () =>
 {
     document.title = "Product Management - Store Orders";
     const {user: currentUser} = useAuth();
+    // One page of products; the server filters and pages them
     const [products, setProducts] = useState<Product[]>([]);
+    const [productTotal, setProductTotal] = useState(0);
     const [categories, setCategories] = useState<Category[]>([]);
     const [loading, setLoading] = useState(true);
     const [actionLoading, setActionLoading] = useState(false);
@@ -117,12 +119,19 @@ const ProductManagement: React.FC = () =>
     const {isOpen: isEditCategoryOpen, onOpen: onEditCategoryOpen, onOpenChange: onEditCategoryOpenChange} = useDisclosure();
     const {isOpen: isDeleteCategoryOpen, onOpen: onDeleteCategoryOpen, onOpenChange: onDeleteCategoryOpenChange} = useDisclosure();
 
-    // Load products and categories on component mount
+    // Load categories on component mount
     useEffect(() =>
     {
-        loadData();
+        loadCategories();
     }, []);
 
+    // Reload the page of products whenever it or the filters change; typing in the search waits for a pause
+    useEffect(() =>
+    {
+        const timeout = setTimeout(loadProducts, productSearchQuery ? 300 : 0);
+        return () => clearTimeout(timeout);
+    }, [productPage, productSearchQuery, productCategoryFilter, productStatusFilter]);
+
     // Reset form when create modal opens (fresh form each time)
     useEffect(() =>
     {
@@ -145,34 +154,54 @@ const ProductManagement: React.FC = () =>
         }
     }, [isEditOpen]);
 
-    const loadData = async () =>
+    const loadProducts = async () =>
     {
         try
         {
             setLoading(true);
-            const [productsResponse, categoriesResponse] = await Promise.all([
-                productsApi.getProducts(),
-                categoriesApi.getCategories()
-            ]);
-
-            if (productsResponse.success)
-            {
-                setProducts(productsResponse.data || []);
-            }
+            const filters: Record<string, string> = {
+                page: String(productPage),
+                page_size: String(itemsPerPage)
+            };
+            if (productSearchQuery.trim()) filters.search = productSearchQuery.trim();
+            if (productCategoryFilter) filters.category_id = productCategoryFilter;
+            if (productStatusFilter) filters.is_active = String(productStatusFilter === "active");
 
-            if (categoriesResponse.success)
+            const response = await productsApi.getProducts(filters);
+            if (response.success)
             {
-                setCategories(categoriesResponse.data || []);
+                setProducts(response.data || []);
+                setProductTotal(response.pagination?.total ?? 0);
             }
         } catch (error)
         {
-            console.error("Failed to load data:", error);
+            console.error("Failed to load products:", error);
         } finally
         {
             setLoading(false);
         }
     };
 
+    const loadCategories = async () =>
+    {
+        try
+        {
+            const response = await categoriesApi.getCategories();
+            if (response.success)
+            {
+                setCategories(response.data || []);
+            }
+        } catch (error)
+        {
+            console.error("Failed to load categories:", error);
+        }
+    };
+
+    const loadData = async () =>
+    {
+        await Promise.all([loadProducts(), loadCategories()]);
+    };
+
     const handleCreateProduct = async () =>
     {
         try
@@ -503,44 +532,7 @@ const ProductManagement: React.FC = () =>
         return queryIndex === queryLower.length;
     };
 
-    // Filtered and paginated products
-    const filteredProducts = useMemo(() =>
-    {
-        return products.filter(product =>
-        {
-            // General search filter (fuzzy search on name and SKU)
-            if (productSearchQuery)
-            {
-                const matchesName = fuzzySearch(productSearchQuery, product.name);
-                const matchesSku = fuzzySearch(productSearchQuery, product.sku);
-                if (!matchesName && !matchesSku) return false;
-            }
-
-            // Category filter
-            if (productCategoryFilter && product.category_id !== productCategoryFilter)
-            {
-                return false;
-            }
-
-            // Status filter
-            if (productStatusFilter)
-            {
-                const isActive = product.is_active ?? false;
-                if (productStatusFilter === "active" && !isActive) return false;
-                if (productStatusFilter === "inactive" && isActive) return false;
-            }
-
-            return true;
-        });
-    }, [products, productSearchQuery, productCategoryFilter, productStatusFilter]);
-
-    const paginatedProducts = useMemo(() =>
-    {
-        const startIndex = (productPage - 1) * itemsPerPage;
-        return filteredProducts.slice(startIndex, startIndex + itemsPerPage);
-    }, [filteredProducts, productPage]);
-
-    const productTotalPages = Math.ceil(filteredProducts.length / itemsPerPage);
+    const productTotalPages = Math.ceil(productTotal / itemsPerPage);
 
     // Filtered and paginated categories
     const filteredCategories = useMemo(() =>
@@ -571,6 +563,12 @@ const ProductManagement: React.FC = () =>
         setProductPage(1);
     }, [productSearchQuery, productCategoryFilter, productStatusFilter]);
 
+    // Step back when the last product on the last page is deleted
+    useEffect(() =>
+    {
+        if (productPage > 1 && productPage > productTotalPages) setProductPage(Math.max(productTotalPages, 1));
+    }, [productTotalPages]);
+
     useEffect(() =>
     {
         setCategoryPage(1);
@@ -664,7 +662,7 @@ const ProductManagement: React.FC = () =>
                             </Select>
                         </div>
                         <div className="text-sm text-gray-500">
-                            Showing {paginatedProducts.length} of {filteredProducts.length} products
+                            Showing {products.length} of {productTotal} products
                         </div>
                     </div>
                 </CardHeader>
@@ -686,7 +684,7 @@ const ProductManagement: React.FC = () =>
                                     <TableColumn width={64} hideHeader>ACTIONS</TableColumn>
                                 </TableHeader>
                                 <TableBody>
-                                    {paginatedProducts.map((product) => (
+                                    {products.map((product) => (
                                         <TableRow key={product.id}>
                                             <TableCell>
                                                 <div className="w-[5rem] h-12 bg-gray-100 rounded-lg flex items-center justify-center overflow-hidden">
@@ -72,7 +72,7 @@ const Products: React.FC = () =>
                     // Fetch all products with optional filters
                     const filters = {
                         search: searchTerm || undefined,
-                        limit: 100 // Reasonable limit for display
+                        page_size: 100 // Reasonable limit for display
                     };
                     response = await productsApi.getProducts(filters);
                 }
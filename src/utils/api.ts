@@ -5,11 +5,20 @@ import {DisabledUser, DisableUserRequest, OrderWithItemsDto, StoreOrderRecordDto
 
 const API_BASE_URL = "/api";
 
+export interface Pagination
+{
+    page: number;
+    page_size: number;
+    total: number;
+    total_pages: number;
+}
+
 export interface ApiResponse<T = any>
 {
     success: boolean;
     data?: T;
     count?: number;
+    pagination?: Pagination;
     error?: string;
     message?: string;
 }
@@ -62,11 +71,11 @@ class ApiClient
         return this.handleResponse<T>(response);
     }
 
-    async post<T>(endpoint: string, data?: any): Promise<T>
+    async post<T>(endpoint: string, data?: any, headers?: Record<string, string>): Promise<T>
     {
         const response = await fetch(`${API_BASE_URL}${endpoint}`, {
             method: "POST",
-            headers: this.getAuthHeaders(),
+            headers: {...this.getAuthHeaders(), ...headers},
             body: data ? JSON.stringify(data) : undefined
         });
         return this.handleResponse<T>(response);
@@ -82,6 +91,16 @@ class ApiClient
         return this.handleResponse<T>(response);
     }
 
+    async patch<T>(endpoint: string, data?: any): Promise<T>
+    {
+        const response = await fetch(`${API_BASE_URL}${endpoint}`, {
+            method: "PATCH",
+            headers: this.getAuthHeaders(),
+            body: data ? JSON.stringify(data) : undefined
+        });
+        return this.handleResponse<T>(response);
+    }
+
     async delete<T>(endpoint: string): Promise<T>
     {
         const response = await fetch(`${API_BASE_URL}${endpoint}`, {
@@ -128,7 +147,9 @@ export const ordersApi = {
 
     getOrder: (orderId: string) => apiClient.get<ApiResponse<OrderWithItemsDto>>(`/orders/${orderId}`),
 
-    createOrder: (order: any) => apiClient.post<ApiResponse<OrderWithItemsDto>>("/orders", order),
+    /** Resending with the same `idempotencyKey` returns the original order instead of placing another */
+    createOrder: (order: any, idempotencyKey?: string) =>
+        apiClient.post<ApiResponse<OrderWithItemsDto>>("/orders", order, idempotencyKey ? {"Idempotency-Key": idempotencyKey} : undefined),
 
     updateOrderStatus: (orderId: string, status: string, notes?: string) =>
         apiClient.put<ApiResponse<OrderWithItemsDto>>(`/orders/${orderId}/status`, {status, notes}),
@@ -146,6 +167,13 @@ export const productsApi = {
 
     getProduct: (productId: string) => apiClient.get<ApiResponse>(`/products/${productId}`),
 
+    searchProducts: (query: string, limit?: number) =>
+    {
+        const params = new URLSearchParams({q: query});
+        if (limit) params.set("limit", String(limit));
+        return apiClient.get<ApiResponse>(`/products/search?${params}`);
+    },
+
     getProductsByCategory: (categoryId: string) =>
         apiClient.get<ApiResponse>(`/products/category/${categoryId}`),
 
@@ -158,8 +186,40 @@ export const productsApi = {
     deleteProduct: (productId: string) =>
         apiClient.delete<ApiResponse>(`/products/admin/${productId}`),
 
-    updateProductStock: (productId: string, quantity: number) =>
-        apiClient.put<ApiResponse>(`/products/admin/${productId}`, {stock_quantity: quantity})
+    updateProductStock: (productId: string, quantity: number, reason: string) =>
+        apiClient.post<ApiResponse>(`/products/admin/${productId}/stock-adjustments`, {
+            movement_type: "manual_adjustment",
+            counted_quantity: quantity,
+            reason
+        }),
+
+    getProductMovements: (productId: string, filters?: Record<string, string>) => {
+        const params = filters ? `?${new URLSearchParams(filters)}` : "";
+        return apiClient.get<ApiResponse>(`/products/admin/${productId}/movements${params}`);
+    },
+
+    getLowStock: () => apiClient.get<ApiResponse>("/products/low-stock"),
+
+    lookupBarcode: (code: string) =>
+        apiClient.get<ApiResponse>(`/products/barcode/${encodeURIComponent(code)}`),
+
+    getBarcodeSvgUrl: (code: string) =>
+        `${API_BASE_URL}/products/barcode/${encodeURIComponent(code)}/svg`,
+
+    getProductBarcodes: (productId: string) =>
+        apiClient.get<ApiResponse>(`/products/admin/${productId}/barcodes`),
+
+    addProductBarcode: (productId: string, code: string, kind?: string) =>
+        apiClient.post<ApiResponse>(`/products/admin/${productId}/barcodes`, {code, kind}),
+
+    deleteProductBarcode: (productId: string, barcodeId: string) =>
+        apiClient.delete<ApiResponse>(`/products/admin/${productId}/barcodes/${barcodeId}`),
+
+    importProducts: (products: any[], dryRun: boolean = false) =>
+        apiClient.post<ApiResponse>(`/products/admin/import?format=json&dry_run=${dryRun}`, products),
+
+    bulkUpdateProducts: (selection: { ids: string[] } | { filter: Record<string, any> }, patch: any) =>
+        apiClient.patch<ApiResponse>("/products/bulk", {...selection, patch})
 };
 
 export const categoriesApi = {
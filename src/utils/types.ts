@@ -25,13 +25,21 @@ export interface StoreOrderRecordDto
     status_changed_to_pending?: string | null; // ISO timestamp
     /** Date and time when the status was changed to completed */
     status_changed_to_completed?: string | null; // ISO timestamp
+    /** Order this one was created to fulfill the backordered remainder of */
+    backorder_of_order_id?: string | null;
 }
 
 export enum StoreOrderStatus
 {
+    AwaitingApproval = "AwaitingApproval",
     Pending = "Pending",
+    OnHold = "OnHold",
+    Picking = "Picking",
+    Packed = "Packed",
     Shipped = "Shipped",
     Delivered = "Delivered",
+    DeliveredWithDiscrepancies = "DeliveredWithDiscrepancies",
+    Cancelled = "Cancelled",
 }
 
 /** Order item (basic) */
@@ -41,6 +49,11 @@ export interface OrderItemRecordDto
     order_id: string;
     product_id: string;
     quantity: number;
+    quantity_shipped: number;
+    /** Unshipped quantity that will not ship on this order */
+    quantity_backordered: number;
+    /** Quantity still expected to ship on this order */
+    quantity_outstanding: number;
     unit_price: number;
     total_price: number;
     created_at: string; // ISO timestamp
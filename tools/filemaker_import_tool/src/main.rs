@@ -6,6 +6,7 @@ use serde::{Deserialize, Deserializer};
 use serde_json::Value;
 use sqlx::{MySql, Transaction};
 use std::collections::HashMap;
+use store_orders_lib::products::inventory_data::{InventoryMovementRecord, MovementType, StockMovement};
 
 #[derive(Debug, Clone)]
 struct OrderItem {
@@ -156,10 +157,16 @@ async fn import_items(
         .execute(&mut **transaction)
         .await?;
 
+    let movement = StockMovement {
+        movement_type: MovementType::ImportSync,
+        reference_id: None,
+        user_id: None,
+        reason: Some("FileMaker import"),
+    };
     for item in items {
         let category_id = categories.get(&item.filter_name).unwrap_or(&0);
 
-        sqlx::query(r#"insert into products (name, description, sku, category_id, image_url, price, in_stock, stock_quantity, bin_location, unit_type) values (?, ?, ?, ?, NULL, ?, ?, ?, ?, ?);"#)
+        let result = sqlx::query(r#"insert into products (name, description, sku, category_id, image_url, price, in_stock, stock_quantity, bin_location, unit_type) values (?, ?, ?, ?, NULL, ?, ?, ?, ?, ?);"#)
             .bind(&item.desc_short.trim_end_matches("..."))
             .bind(&item.desc_full)
             .bind(&item.item_number)
@@ -170,6 +177,9 @@ async fn import_items(
             .bind(format!("{}, {}", item.bin_loc1, item.bin_loc2))
             .bind(match item.unit.to_lowercase().as_str() {"each"=>0u8,"case"=>1u8,"roll"=> 2u8,_=>0u8})
             .execute(&mut **transaction).await?;
+
+        let stock = item.cases_on_hand as f32;
+        InventoryMovementRecord::record(transaction, result.last_insert_id(), stock, stock, &movement).await?;
     }
 
     Ok(())